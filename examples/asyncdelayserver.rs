@@ -0,0 +1,70 @@
+//! Async version of the `delayserver` example.
+//!
+//! Each request runs in its own task. There is no hand written drain loop, as the
+//! stream's `read` future retries on `WouldBlock` until the buffer has been drained.
+
+use std::io::Result;
+use std::net::ToSocketAddrs;
+
+use mini_mio::runtime::{self, net::TcpStream};
+
+fn main() -> Result<()> {
+    let num_requests = 5;
+
+    let host = std::env::var("HOST").unwrap_or_else(|_| "localhost".to_string());
+
+    let socket_addr = format!("{host}:8080")
+        .to_socket_addrs()?
+        .next()
+        .expect("could not resolve delayserver address");
+
+    runtime::block_on(async move {
+        let handles: Vec<_> = (0..num_requests)
+            .map(|i| {
+                // first request has longest delay, so expect
+                // responses to arrive in reverse order.
+                let delay = (num_requests - i) * 1000;
+                runtime::spawn(request(socket_addr, i, delay))
+            })
+            .collect();
+
+        for handle in handles {
+            handle.await?;
+        }
+
+        println!("FINISHED PROGRAM");
+        Ok(())
+    })
+}
+
+async fn request(socket_addr: std::net::SocketAddr, i: usize, delay: usize) -> Result<()> {
+    let stream = TcpStream::connect(socket_addr).await?;
+    stream.set_nodelay(true)?;
+
+    let request = format!(
+        "GET /{delay}/request-{i} HTTP/1.1\r\n\
+             Host: localhost\r\n\
+             Connection: close\r\n\
+             \r\n"
+    );
+
+    stream.write_all(request.as_bytes()).await?;
+    println!("Sent request {i} with delay {delay} ms");
+
+    let mut response = Vec::new();
+    let mut buffer = vec![0u8; 4096]; // 4KB buffer
+
+    loop {
+        match stream.read(&mut buffer).await? {
+            0 => break,
+            n => response.extend_from_slice(&buffer[..n]),
+        }
+    }
+
+    println!(
+        "\n--- Response {i} ---\n{}",
+        String::from_utf8_lossy(&response)
+    );
+
+    Ok(())
+}
@@ -1,13 +1,14 @@
 #![allow(dead_code, unused)]
 
 use mini_mio::interests::Interest;
-use mini_mio::interfaces::{Event, Events, SysEvent, SysSelector, Token};
+use mini_mio::interfaces::{Event, SysEvent, SysSelector, Token};
 use mini_mio::poll::*;
 
 use std::{
     collections::HashSet,
     io::{self, Read, Result, Write},
     net::TcpStream,
+    ops::ControlFlow,
     thread,
     time::Duration,
 };
@@ -44,18 +45,33 @@ fn main() -> Result<()> {
     // do below while we haven't got a response from all the requests
     // Note that we are using edge-triggered mode, so we need to drain the buffer completely.
     while handled_events < num_events {
-        let mut events = Events::with_capacity(10);
+        let mut result = Ok(());
 
-        // poll for events
-        poll.poll(&mut events, None)?;
+        println!("\n------------------------------------\n");
+        println!("Handling events...");
+        println!("\n------------------------------------\n");
+
+        // poll for events, handled one at a time in a buffer owned by the event queue
+        let n = poll.poll_with(None, |event| {
+            match handle_event(event, &mut streams, &mut handled_ids) {
+                Ok(handled) => {
+                    handled_events += handled as usize;
+                    ControlFlow::Continue(())
+                }
+                Err(e) => {
+                    // stop handling events, the rest are handed over by the next poll
+                    result = Err(e);
+                    ControlFlow::Break(())
+                }
+            }
+        })?;
+
+        result?;
 
         // reach here when thread is woken up
-        if events.is_empty() {
+        if n == 0 {
             println!("TIMEOUT OR SPURIOUS WAKEUP EVENT NOTIFICATION");
-            continue;
         }
-
-        handled_events += handle_events(&events, &mut streams, &mut handled_ids)?;
     }
 
     println!("FINISHED PROGRAM");
@@ -133,77 +149,70 @@ fn get_req(path: &str) -> Vec<u8> {
     req.into_bytes()
 }
 
-fn handle_events(
-    events: &Events,
+/// Read the response a ready stream holds, returning whether it was the first time the
+/// stream was handled.
+fn handle_event(
+    event: &Event,
     streams: &mut [TcpStream],
     handled_ids: &mut HashSet<usize>,
-) -> Result<usize> {
-    let mut handled_events = 0;
+) -> Result<bool> {
+    let mut handled = false;
 
-    println!("\n------------------------------------\n");
-    println!("Handling events...");
-    println!("num_events: {}", events.len());
+    let token = event.token();
+    let identifier = token.0;
+    println!("Processing event {identifier}: {event:?}");
     println!("\n------------------------------------\n");
 
-    for event in events {
-        let token = event.token();
-        let identifier = token.0;
-        println!("Processing event {identifier}: {event:?}");
-        println!("\n------------------------------------\n");
+    let mut buffer = vec![0u8; 4096]; // 4KB buffer
 
-        let mut buffer = vec![0u8; 4096]; // 4KB buffer
-
-        let mut i = 0_usize;
-        let mut txt = String::new();
-        let mut new_response = true;
-
-        loop {
-            // use a loop to ensure we drain the buffer.
-            // This is important for edge-triggered mode, as if the buffer isn't
-            // drained, then it will never reset to notify us of new events.
-            match streams[identifier].read(&mut buffer) {
-                Ok(0) => {
-                    // read 0 bytes - buffer has been drained successfully
-
-                    // `insert` returns false if the value already existed in the set.
-                    if !handled_ids.insert(identifier) {
-                        println!("Event already handled");
-                        break;
-                    }
-
-                    handled_events += 1;
-
-                    println!(
-                        "\n\nBuffer drained after {i} iteration(s), breaking out of loop...\n"
-                    );
-                    println!("------------------------------------\n");
-                    i = 0;
-                    new_response = true;
-                    break;
-                }
-                Ok(n) => {
-                    // read in `n` bytes successfully
-                    let txt = String::from_utf8_lossy(&buffer[..n]);
-                    if new_response {
-                        println!("\n--- Response ---");
-                        new_response = false;
-                    }
-                    print!("{txt}");
-                    i = i.saturating_add(1);
-                }
-                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
-                    println!("\n\nWouldBlock error, breaking out of loop...");
+    let mut i = 0_usize;
+    let mut txt = String::new();
+    let mut new_response = true;
+
+    loop {
+        // use a loop to ensure we drain the buffer.
+        // This is important for edge-triggered mode, as if the buffer isn't
+        // drained, then it will never reset to notify us of new events.
+        match streams[identifier].read(&mut buffer) {
+            Ok(0) => {
+                // read 0 bytes - buffer has been drained successfully
+
+                // `insert` returns false if the value already existed in the set.
+                if !handled_ids.insert(identifier) {
+                    println!("Event already handled");
                     break;
                 }
-                // if the read operation is interrupted (e.g. signal from OS), we can continue
-                Err(e) if e.kind() == io::ErrorKind::Interrupted => {
-                    println!("\n\nnRead operation interrupted, continuing...");
-                    break;
+
+                handled = true;
+
+                println!("\n\nBuffer drained after {i} iteration(s), breaking out of loop...\n");
+                println!("------------------------------------\n");
+                i = 0;
+                new_response = true;
+                break;
+            }
+            Ok(n) => {
+                // read in `n` bytes successfully
+                let txt = String::from_utf8_lossy(&buffer[..n]);
+                if new_response {
+                    println!("\n--- Response ---");
+                    new_response = false;
                 }
-                Err(e) => return Err(e),
+                print!("{txt}");
+                i = i.saturating_add(1);
+            }
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                println!("\n\nWouldBlock error, breaking out of loop...");
+                break;
+            }
+            // if the read operation is interrupted (e.g. signal from OS), we can continue
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {
+                println!("\n\nnRead operation interrupted, continuing...");
+                break;
             }
+            Err(e) => return Err(e),
         }
     }
 
-    Ok(handled_events)
+    Ok(handled)
 }
@@ -1,35 +1,155 @@
-//! From https://www.reddit.com/r/learnrust/comments/13eyfcx/fastest_and_safest_way_to_convert_a_generic_type/
-//! Also see bytemuck crate: https://crates.io/crates/bytemuck
-use std::mem::{size_of, MaybeUninit};
+//! `mini-mio-decode`: print the events held in a dump of `epoll_event` or `kevent` structs.
+//!
+//! ```text
+//! usage: mini-mio-decode [--struct epoll|kevent] [--arch ARCH] [--binary] [FILE]
+//! ```
+//!
+//! Reads FILE, or stdin, as a hex dump in any of the forms accepted by
+//! `mini_mio::decode::parse_hex`, or as raw bytes with `--binary`. `event` lines of a
+//! session written by the recorder are decoded from their `raw` field instead.
+//!
+//! The struct and architecture default to those of the current target. Pass
+//! `--arch x86_64` for dumps taken on x86_64, where `epoll_event` is packed, and any
+//! other architecture, e.g. `--arch aarch64`, for the aligned layout.
+//!
+//! ```text
+//! $ echo "01 00 00 80 05 00 00 00 00 00 00 00" | mini-mio-decode --arch x86_64
+//! #0 token=5 events=EPOLLIN|EPOLLET ready=readable
+//! ```
+use std::fs::File;
+use std::io::{self, Read};
+use std::process::ExitCode;
 
-unsafe fn from_bytes<T: Copy>(bytes: &[u8]) -> T {
-    assert_eq!(bytes.len(), std::mem::size_of::<T>());
+use mini_mio::decode::{self, Decoded, Kind, Layout};
+use mini_mio::interfaces::Token;
 
-    let mut to: MaybeUninit<T> = MaybeUninit::uninit();
+const USAGE: &str =
+    "usage: mini-mio-decode [--struct epoll|kevent] [--arch ARCH] [--binary] [FILE]";
 
-    unsafe {
-        std::ptr::copy_nonoverlapping(bytes.as_ptr(), to.as_mut_ptr().cast::<u8>(), size_of::<T>());
+struct Args {
+    kind: Option<Kind>,
+    arch: String,
+    binary: bool,
+    path: Option<String>,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = Args {
+        kind: None,
+        arch: std::env::consts::ARCH.to_string(),
+        binary: false,
+        path: None,
+    };
+
+    let mut argv = std::env::args().skip(1);
+
+    while let Some(arg) = argv.next() {
+        let mut value = || argv.next().ok_or_else(|| format!("{arg} requires a value"));
+
+        match arg.as_str() {
+            "--struct" => args.kind = Some(value()?.parse().map_err(|e| format!("{e}"))?),
+            "--arch" => args.arch = value()?,
+            "--binary" => args.binary = true,
+            "-h" | "--help" => return Err(USAGE.to_string()),
+            _ if arg.starts_with('-') => return Err(format!("unknown option {arg}\n{USAGE}")),
+            _ if args.path.is_none() => args.path = Some(arg),
+            _ => return Err(format!("unexpected argument {arg}\n{USAGE}")),
+        }
+    }
+
+    Ok(args)
+}
+
+/// Decode a `<micros> event token=N ready=... raw=...` line of a recorded session.
+fn recorded_event(line: &str) -> Option<io::Result<Decoded>> {
+    let mut words = line.split_whitespace();
+    words.next()?;
+
+    if words.next()? != "event" {
+        return None;
+    }
+
+    let mut token = None;
+    let mut raw = None;
 
-        to.assume_init()
+    for word in words {
+        match word.split_once('=') {
+            Some(("token", value)) => token = value.parse().ok().map(Token),
+            Some(("raw", value)) => raw = Some(value),
+            _ => {}
+        }
     }
+
+    Some(decode::parse_recorded(raw?, token?))
+}
+
+/// Any other line of a recorded session, such as `<micros> poll timeout=none n=1`.
+fn recorded_call(line: &str) -> bool {
+    let mut words = line.split_whitespace();
+
+    words
+        .next()
+        .is_some_and(|micros| micros.parse::<u64>().is_ok())
+        && words.next().is_some_and(|call| {
+            ["register", "reregister", "rearm", "deregister", "poll"].contains(&call)
+        })
 }
 
-fn to_bytes<T: Copy>(value: T) -> Vec<u8> {
-    let mut bytes = Vec::with_capacity(size_of::<T>());
+fn run(args: Args) -> io::Result<Vec<Decoded>> {
+    let mut input = Vec::new();
+
+    match &args.path {
+        Some(path) => File::open(path)?.read_to_end(&mut input)?,
+        None => io::stdin().read_to_end(&mut input)?,
+    };
+
+    let layout = Layout::new(args.kind.unwrap_or_else(Kind::native), &args.arch);
 
-    unsafe {
-        std::ptr::copy_nonoverlapping(
-            std::ptr::addr_of!(value).cast::<u8>(),
-            bytes.as_mut_ptr(),
-            size_of::<T>(),
-        );
-        bytes.set_len(size_of::<T>());
+    if args.binary {
+        return decode::decode(&input, layout);
     }
 
-    bytes
+    let text = String::from_utf8(input)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "not a text dump, try --binary"))?;
+
+    let mut decoded = Vec::new();
+    let mut dump = String::new();
+
+    for line in text.lines() {
+        match recorded_event(line) {
+            Some(event) => decoded.push(event?),
+            // the session header, and every other recorded call
+            None if line.starts_with('#') || recorded_call(line) => {}
+            None => {
+                dump.push_str(line);
+                dump.push('\n');
+            }
+        }
+    }
+
+    decoded.extend(decode::decode(&decode::parse_hex(&dump)?, layout)?);
+    Ok(decoded)
 }
 
-fn main() {
-    let bytes = to_bytes(100);
-    println!("{}", unsafe { from_bytes::<i32>(bytes.as_slice()) });
+fn main() -> ExitCode {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(msg) => {
+            eprintln!("{msg}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match run(args) {
+        Ok(events) => {
+            for (i, event) in events.iter().enumerate() {
+                println!("#{i} {event}");
+            }
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("mini-mio-decode: {e}");
+            ExitCode::FAILURE
+        }
+    }
 }
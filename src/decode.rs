@@ -0,0 +1,480 @@
+//! Decode raw `epoll_event` and `kevent` structs, as found in memory dumps.
+//!
+//! Used by the `mini-mio-decode` binary. Dumps are taken from 64-bit, little-endian
+//! targets, which covers x86_64 and aarch64 on both Linux and macOS, but may come from a
+//! different architecture or OS than the one decoding them.
+//!
+//! ```
+//! use mini_mio::decode::{self, Layout};
+//!
+//! // a readable, edge-triggered event for token 5, from an x86_64 core dump
+//! let bytes = decode::parse_hex("0x7ffc10: 0x01 0x00 0x00 0x80 0x05 0x00 0x00 0x00 0x00 0x00 0x00 0x00").unwrap();
+//! let events = decode::decode(&bytes, Layout::EpollPacked).unwrap();
+//!
+//! assert_eq!(events[0].to_string(), "token=5 events=EPOLLIN|EPOLLET ready=readable");
+//! ```
+
+use std::fmt;
+use std::io;
+
+use crate::flags::{kqueue_filter_name, EpollFlags, KqueueFlags};
+use crate::interfaces::{SysEvent, Token};
+use crate::sys::events::{epoll, kqueue};
+
+/// Memory layout of the structs in a dump.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Layout {
+    /// `epoll_event` on x86_64, where it is packed into 12 bytes for compatibility with
+    /// 32-bit x86.
+    EpollPacked,
+    /// `epoll_event` on every other architecture, such as aarch64, where `data` is
+    /// aligned to 8 bytes, making the struct 16 bytes.
+    Epoll,
+    /// `kevent` on 64-bit macOS, 32 bytes.
+    Kevent,
+}
+
+impl Layout {
+    /// Layout of a struct on the given architecture, as named by `std::env::consts::ARCH`.
+    pub fn new(kind: Kind, arch: &str) -> Self {
+        match kind {
+            Kind::Epoll if arch == "x86_64" => Layout::EpollPacked,
+            Kind::Epoll => Layout::Epoll,
+            Kind::Kevent => Layout::Kevent,
+        }
+    }
+
+    /// Layout of the event struct used by this crate on the current target.
+    pub fn native() -> Self {
+        Self::new(Kind::native(), std::env::consts::ARCH)
+    }
+
+    /// Size of a single struct in bytes.
+    pub fn size(&self) -> usize {
+        match self {
+            Layout::EpollPacked => 12,
+            Layout::Epoll => 16,
+            Layout::Kevent => 32,
+        }
+    }
+}
+
+/// Which struct a dump holds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    Epoll,
+    Kevent,
+}
+
+impl Kind {
+    /// Struct used by this crate on the current OS.
+    pub fn native() -> Self {
+        if cfg!(target_os = "macos") {
+            Kind::Kevent
+        } else {
+            Kind::Epoll
+        }
+    }
+}
+
+impl std::str::FromStr for Kind {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> io::Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "epoll" | "epoll_event" => Ok(Kind::Epoll),
+            "kevent" | "kqueue" => Ok(Kind::Kevent),
+            _ => Err(invalid(format!("unknown struct: {s}"))),
+        }
+    }
+}
+
+/// A decoded event.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Decoded {
+    Epoll {
+        events: EpollFlags,
+        data: u64,
+    },
+    Kevent {
+        ident: u64,
+        filter: i16,
+        flags: KqueueFlags,
+        fflags: u32,
+        data: i64,
+        udata: u64,
+    },
+}
+
+impl Decoded {
+    pub fn token(&self) -> Token {
+        match self {
+            Decoded::Epoll { .. } => self.as_epoll().token(),
+            Decoded::Kevent { .. } => self.as_kevent().token(),
+        }
+    }
+
+    /// Names of the readiness flags set on the event, as reported by `Event`.
+    pub fn ready(&self) -> Vec<&'static str> {
+        match self {
+            Decoded::Epoll { .. } => ready(&self.as_epoll()),
+            Decoded::Kevent { .. } => ready(&self.as_kevent()),
+        }
+    }
+
+    fn as_epoll(&self) -> epoll::OsEvent {
+        match *self {
+            Decoded::Epoll { events, data } => epoll::OsEvent {
+                events: events.bits() as i32,
+                epoll_data: data as usize,
+            },
+            Decoded::Kevent { .. } => unreachable!("not an epoll_event"),
+        }
+    }
+
+    fn as_kevent(&self) -> kqueue::OsEvent {
+        match *self {
+            Decoded::Kevent {
+                ident,
+                filter,
+                flags,
+                fflags,
+                data,
+                udata,
+            } => kqueue::OsEvent {
+                ident: ident as usize,
+                filter,
+                flags: flags.bits(),
+                fflags,
+                data: data as isize,
+                udata: udata as usize,
+            },
+            Decoded::Epoll { .. } => unreachable!("not a kevent"),
+        }
+    }
+}
+
+fn ready(event: &impl SysEvent) -> Vec<&'static str> {
+    [
+        ("readable", event.is_readable()),
+        ("writable", event.is_writable()),
+        ("read_closed", event.is_read_closed()),
+        ("write_closed", event.is_write_closed()),
+        ("error", event.is_error()),
+    ]
+    .into_iter()
+    .filter(|(_, set)| *set)
+    .map(|(name, _)| name)
+    .collect()
+}
+
+/// A single line, such as `token=5 events=EPOLLIN|EPOLLET ready=readable`.
+impl fmt::Display for Decoded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "token={}", self.token().0)?;
+
+        match *self {
+            Decoded::Epoll { events, .. } => write!(f, " events={events}")?,
+            Decoded::Kevent {
+                ident,
+                filter,
+                flags,
+                fflags,
+                data,
+                ..
+            } => {
+                write!(f, " ident={ident} filter=")?;
+
+                match kqueue_filter_name(filter) {
+                    Some(name) => write!(f, "{name}")?,
+                    None => write!(f, "{filter}")?,
+                }
+
+                write!(f, " flags={flags} fflags={fflags:#x} data={data}")?;
+            }
+        }
+
+        let ready = self.ready();
+
+        if ready.is_empty() {
+            write!(f, " ready=-")
+        } else {
+            write!(f, " ready={}", ready.join("|"))
+        }
+    }
+}
+
+/// Decode every struct in `bytes`, which must hold a whole number of them.
+pub fn decode(bytes: &[u8], layout: Layout) -> io::Result<Vec<Decoded>> {
+    let size = layout.size();
+
+    if !bytes.len().is_multiple_of(size) {
+        return Err(invalid(format!(
+            "{} bytes is not a multiple of the {size} byte {layout:?} struct",
+            bytes.len()
+        )));
+    }
+
+    Ok(bytes
+        .chunks_exact(size)
+        .map(|chunk| decode_one(chunk, layout))
+        .collect())
+}
+
+fn decode_one(b: &[u8], layout: Layout) -> Decoded {
+    let u16_at = |i: usize| u16::from_le_bytes(b[i..i + 2].try_into().unwrap());
+    let u32_at = |i: usize| u32::from_le_bytes(b[i..i + 4].try_into().unwrap());
+    let u64_at = |i: usize| u64::from_le_bytes(b[i..i + 8].try_into().unwrap());
+
+    match layout {
+        // `data` directly follows `events`
+        Layout::EpollPacked => Decoded::Epoll {
+            events: EpollFlags::from_bits(u32_at(0)),
+            data: u64_at(4),
+        },
+        // 4 bytes of padding align `data` to 8 bytes
+        Layout::Epoll => Decoded::Epoll {
+            events: EpollFlags::from_bits(u32_at(0)),
+            data: u64_at(8),
+        },
+        Layout::Kevent => Decoded::Kevent {
+            ident: u64_at(0),
+            filter: u16_at(8) as i16,
+            flags: KqueueFlags::from_bits(u16_at(10)),
+            fflags: u32_at(12),
+            data: u64_at(16) as i64,
+            udata: u64_at(24),
+        },
+    }
+}
+
+/// Extract the bytes of a hex dump, one line at a time.
+///
+/// Understands the following, mixed in any way:
+/// - plain hex, with optional whitespace, commas and `0x` prefixes: `01 00 00 80`
+/// - `xxd`: `00000000: 0100 0080 0500 0000  ........`
+/// - `hexdump -C`: `00000000  01 00 00 80 05 00 00 00  |........|`
+/// - strace's `-e read=`/`-e write=` dumps: ` | 00000  01 00 00 80  .... |`
+/// - strace's `-xx` strings: `"\x01\x00\x00\x80"`
+/// - gdb's `x/Nxb`, which separates bytes by tabs: `0x7ffc10 <buf>: 0x01 0x00 0x00 0x80`
+///
+/// The ASCII column of `xxd` must be separated from the hex by two spaces, as `xxd` does.
+/// Blank lines and lines starting with `#` are ignored.
+pub fn parse_hex(text: &str) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+
+    for line in text.lines() {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if line.contains("\\x") {
+            for escaped in line.split("\\x").skip(1) {
+                let hex = escaped.get(..2).ok_or_else(|| invalid_hex(line))?;
+                bytes.push(u8::from_str_radix(hex, 16).map_err(|_| invalid_hex(line))?);
+            }
+            continue;
+        }
+
+        for token in hex_tokens(line) {
+            let hex = token.strip_prefix("0x").unwrap_or(token);
+
+            if !hex.len().is_multiple_of(2) {
+                return Err(invalid_hex(line));
+            }
+
+            for i in (0..hex.len()).step_by(2) {
+                let byte = hex.get(i..i + 2).ok_or_else(|| invalid_hex(line))?;
+                bytes.push(u8::from_str_radix(byte, 16).map_err(|_| invalid_hex(line))?);
+            }
+        }
+    }
+
+    Ok(bytes)
+}
+
+/// Hex words of a dump line, without its offset or address column, and without any
+/// trailing ASCII column.
+fn hex_tokens(line: &str) -> Vec<&str> {
+    // strace: "| 00000  01 02 03 04 05 06 07 08  09 0a  ascii |"
+    if let Some(rest) = line.strip_prefix('|') {
+        let rest = rest.trim_start();
+        let rest = rest.split_once(' ').map_or("", |(_offset, rest)| rest);
+        return strace_hex(rest);
+    }
+
+    // hexdump -C: "00000000  01 02 03 04 05 06 07 08  09 0a  |ascii|"
+    if let Some((hex, _ascii)) = line.split_once(" |") {
+        return words(hex).into_iter().skip(1).collect();
+    }
+
+    // xxd: "00000000: 0102 ...  ascii", gdb: "0x7ffc10 <buf>:\t0x01\t..."
+    if let Some((address, rest)) = line.split_once(':') {
+        if address.starts_with(|c: char| c.is_ascii_hexdigit()) {
+            if rest.contains('\t') {
+                return words(rest);
+            }
+            return words(rest.trim_start().split("  ").next().unwrap_or(""));
+        }
+    }
+
+    words(line)
+}
+
+fn words(s: &str) -> Vec<&str> {
+    s.split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|word| !word.is_empty())
+        .collect()
+}
+
+/// strace separates bytes by a single space, and the two groups of eight bytes by two
+/// spaces. Any wider gap, or the 16th byte, ends the hex column.
+fn strace_hex(mut rest: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    rest = rest.trim_start();
+
+    while !rest.is_empty() && tokens.len() < 16 {
+        let end = rest.find(' ').unwrap_or(rest.len());
+        tokens.push(&rest[..end]);
+
+        let after = &rest[end..];
+        rest = after.trim_start();
+
+        let gap = after.len() - rest.len();
+        if gap > 2 || (gap == 2 && tokens.len() != 8) {
+            break;
+        }
+    }
+
+    tokens
+}
+
+/// Decode the `raw` field of a recorded `event` line, as written by the recorder.
+///
+/// The `raw` field of a session recorded on Linux holds the `events` bits, and on macOS
+/// `filter:flags:fflags:data`, so the OS is known from its form alone.
+pub fn parse_recorded(raw: &str, token: Token) -> io::Result<Decoded> {
+    let err = || invalid(format!("invalid raw event: {raw}"));
+    let hex = |s| strip_hex(s).ok_or_else(err);
+
+    if !raw.contains(':') {
+        return Ok(Decoded::Epoll {
+            events: EpollFlags::from_bits(u32::from_str_radix(hex(raw)?, 16).map_err(|_| err())?),
+            data: token.0 as u64,
+        });
+    }
+
+    let mut fields = raw.split(':');
+    let mut next = || fields.next().ok_or_else(err);
+
+    Ok(Decoded::Kevent {
+        ident: 0,
+        filter: next()?.parse().map_err(|_| err())?,
+        flags: KqueueFlags::from_bits(u16::from_str_radix(hex(next()?)?, 16).map_err(|_| err())?),
+        fflags: u32::from_str_radix(hex(next()?)?, 16).map_err(|_| err())?,
+        data: next()?.parse().map_err(|_| err())?,
+        udata: token.0 as u64,
+    })
+}
+
+fn strip_hex(s: &str) -> Option<&str> {
+    s.strip_prefix("0x")
+}
+
+fn invalid_hex(line: &str) -> io::Error {
+    invalid(format!("invalid hex dump line: {line}"))
+}
+
+fn invalid(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `EPOLLIN|EPOLLRDHUP` for token 7, in both epoll layouts.
+    const PACKED: [u8; 12] = [0x01, 0x20, 0, 0, 7, 0, 0, 0, 0, 0, 0, 0];
+    const ALIGNED: [u8; 16] = [
+        0x01, 0x20, 0, 0, 0xaa, 0xaa, 0xaa, 0xaa, 7, 0, 0, 0, 0, 0, 0, 0,
+    ];
+
+    #[test]
+    fn epoll_layouts() {
+        let expected = Decoded::Epoll {
+            events: EpollFlags::EPOLLIN | EpollFlags::EPOLLRDHUP,
+            data: 7,
+        };
+
+        assert_eq!(decode(&PACKED, Layout::EpollPacked).unwrap(), [expected]);
+        // the padding is ignored
+        assert_eq!(decode(&ALIGNED, Layout::Epoll).unwrap(), [expected]);
+
+        assert_eq!(
+            expected.to_string(),
+            "token=7 events=EPOLLIN|EPOLLRDHUP ready=readable|read_closed"
+        );
+
+        // 24 bytes are two packed structs, but one and a half aligned ones
+        let doubled = [PACKED, PACKED].concat();
+        assert_eq!(decode(&doubled, Layout::EpollPacked).unwrap().len(), 2);
+        assert!(decode(&doubled[..20], Layout::Epoll).is_err());
+    }
+
+    #[test]
+    fn kevent_layout() {
+        let mut bytes = [0u8; 32];
+        bytes[0] = 9; // ident
+        bytes[8..10].copy_from_slice(&(-1i16).to_le_bytes()); // EVFILT_READ
+        bytes[10..12].copy_from_slice(&0x8021u16.to_le_bytes()); // EV_ADD|EV_CLEAR|EV_EOF
+        bytes[16] = 12; // data
+        bytes[24] = 3; // udata
+
+        let event = decode(&bytes, Layout::Kevent).unwrap()[0];
+        assert_eq!(event.token(), Token(3));
+        assert_eq!(
+            event.to_string(),
+            "token=3 ident=9 filter=EVFILT_READ flags=EV_ADD|EV_CLEAR|EV_EOF fflags=0x0 \
+             data=12 ready=readable|read_closed|error"
+        );
+    }
+
+    #[test]
+    fn hex_dump_formats() {
+        let expected = PACKED.to_vec();
+
+        let dumps = [
+            "01 20 00 00 07 00 00 00 00 00 00 00",
+            "0x01,0x20,0x00,0x00\n0x07,0x00,0x00,0x00,0x00,0x00,0x00,0x00",
+            "00000000: 0120 0000 0700 0000 0000 0000  . ..........",
+            " | 00000  01 20 00 00 07 00 00 00  00 00 00 00              . .......... |",
+            "00000000  01 20 00 00 07 00 00 00  00 00 00 00              |. ..........|",
+            "epoll_wait(3, \"\\x01\\x20\\x00\\x00\\x07\\x00\\x00\\x00\\x00\\x00\\x00\\x00\", 1, -1) = 1",
+            "0x7ffc10 <events>:\t0x01\t0x20\t0x00\t0x00\t0x07\t0x00\t0x00\t0x00\n\
+             0x7ffc18 <events+8>:\t0x00\t0x00\t0x00\t0x00",
+        ];
+
+        for dump in dumps {
+            assert_eq!(parse_hex(dump).unwrap(), expected, "{dump}");
+        }
+
+        assert!(parse_hex("0x1").is_err());
+        assert!(parse_hex("zz").is_err());
+    }
+
+    #[test]
+    fn recorded_events() {
+        let event = parse_recorded("0x80002001", Token(1)).unwrap();
+        assert_eq!(
+            event.to_string(),
+            "token=1 events=EPOLLIN|EPOLLRDHUP|EPOLLET ready=readable|read_closed"
+        );
+
+        let event = parse_recorded("-2:0x20:0x0:8192", Token(2)).unwrap();
+        assert_eq!(event.ready(), ["writable"]);
+
+        assert!(parse_recorded("2001", Token(1)).is_err());
+    }
+}
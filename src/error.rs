@@ -0,0 +1,304 @@
+//! Errors returned by `Poll` and `Registry`, with the operation, file descriptor and token
+//! they failed on.
+//!
+//! The errno values callers are expected to handle get a variant of their own. `kind` and
+//! `raw_os_error` report the same values as the `io::Error` of the underlying syscall, and
+//! an `Error` converts into an `io::Error` of the same kind, carrying the typed error as
+//! its inner error, so that `?` keeps working in functions returning `io::Result`.
+
+use std::fmt;
+use std::io;
+use std::os::fd::RawFd;
+
+use crate::interfaces::Token;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Operation of `Poll` or `Registry` that failed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Operation {
+    Register,
+    Reregister,
+    Rearm,
+    Deregister,
+    Poll,
+    /// Creating the event queue, or replacing it after a fork.
+    Open,
+}
+
+impl Operation {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Operation::Register => "register",
+            Operation::Reregister => "reregister",
+            Operation::Rearm => "rearm",
+            Operation::Deregister => "deregister",
+            Operation::Poll => "poll",
+            Operation::Open => "open",
+        }
+    }
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// What a failed call was made on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Context {
+    pub op: Operation,
+    /// `None` for calls on the event queue itself.
+    pub fd: Option<RawFd>,
+    /// Token passed in by the call, `None` for calls that do not take one.
+    pub token: Option<Token>,
+    /// Token the file descriptor is already registered with, for `AlreadyRegistered`
+    /// errors caught by the registry.
+    pub existing: Option<Token>,
+}
+
+impl Context {
+    pub fn new(op: Operation) -> Self {
+        Self {
+            op,
+            fd: None,
+            token: None,
+            existing: None,
+        }
+    }
+
+    pub fn fd(mut self, fd: RawFd) -> Self {
+        self.fd = Some(fd);
+        self
+    }
+
+    pub fn token(mut self, token: Token) -> Self {
+        self.token = Some(token);
+        self
+    }
+
+    pub fn existing(mut self, token: Token) -> Self {
+        self.existing = Some(token);
+        self
+    }
+}
+
+impl fmt::Display for Context {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.op)?;
+
+        if let Some(fd) = self.fd {
+            write!(f, " fd={fd}")?;
+        }
+
+        if let Some(token) = self.token {
+            write!(f, " token={}", token.0)?;
+        }
+
+        if let Some(existing) = self.existing {
+            write!(f, " existing={}", existing.0)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// `EEXIST`, the file descriptor is already registered.
+    AlreadyRegistered(Context),
+
+    /// `ENOENT`, the file descriptor is not registered.
+    NotRegistered(Context),
+
+    /// `EPERM`, the file descriptor does not support polling, e.g. a regular file or a
+    /// directory.
+    UnsupportedFileType(Context),
+
+    /// `EINVAL` from a poll into an `Events` buffer without any capacity.
+    InvalidCapacity(Context),
+
+    /// `ENOSPC`, the per-user limit on registrations was reached, see
+    /// `/proc/sys/fs/epoll/max_user_watches`.
+    LimitExceeded(Context),
+
+    /// `EINTR`, a signal arrived while blocked.
+    Interrupted(Context),
+
+    /// Any other error.
+    Io(Context, io::Error),
+}
+
+impl Error {
+    /// Classify the error of a call made with `context`.
+    pub(crate) fn new(context: Context, err: io::Error) -> Self {
+        let ctl = matches!(context.op, Operation::Register | Operation::Reregister);
+
+        match err.raw_os_error() {
+            Some(libc::EEXIST) => Error::AlreadyRegistered(context),
+            Some(libc::ENOENT) => Error::NotRegistered(context),
+            Some(libc::EPERM) if ctl => Error::UnsupportedFileType(context),
+            Some(libc::ENOSPC) if ctl => Error::LimitExceeded(context),
+            Some(libc::EINTR) => Error::Interrupted(context),
+            _ => Error::Io(context, err),
+        }
+    }
+
+    pub fn context(&self) -> &Context {
+        match self {
+            Error::AlreadyRegistered(context)
+            | Error::NotRegistered(context)
+            | Error::UnsupportedFileType(context)
+            | Error::InvalidCapacity(context)
+            | Error::LimitExceeded(context)
+            | Error::Interrupted(context)
+            | Error::Io(context, _) => context,
+        }
+    }
+
+    pub fn operation(&self) -> Operation {
+        self.context().op
+    }
+
+    pub fn fd(&self) -> Option<RawFd> {
+        self.context().fd
+    }
+
+    pub fn token(&self) -> Option<Token> {
+        self.context().token
+    }
+
+    /// Token the file descriptor was already registered with, if known.
+    pub fn existing_token(&self) -> Option<Token> {
+        self.context().existing
+    }
+
+    /// errno of the failed syscall, if any.
+    pub fn raw_os_error(&self) -> Option<i32> {
+        match self {
+            Error::AlreadyRegistered(_) => Some(libc::EEXIST),
+            Error::NotRegistered(_) => Some(libc::ENOENT),
+            Error::UnsupportedFileType(_) => Some(libc::EPERM),
+            Error::InvalidCapacity(_) => Some(libc::EINVAL),
+            Error::LimitExceeded(_) => Some(libc::ENOSPC),
+            Error::Interrupted(_) => Some(libc::EINTR),
+            Error::Io(_, err) => err.raw_os_error(),
+        }
+    }
+
+    /// Same kind as the `io::Error` of the failed syscall.
+    pub fn kind(&self) -> io::ErrorKind {
+        match (self, self.raw_os_error()) {
+            (Error::Io(_, err), _) => err.kind(),
+            (_, Some(errno)) => io::Error::from_raw_os_error(errno).kind(),
+            (_, None) => io::ErrorKind::Other,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self {
+            Error::AlreadyRegistered(_) => "already registered",
+            Error::NotRegistered(_) => "not registered",
+            Error::UnsupportedFileType(_) => "file type does not support polling",
+            Error::InvalidCapacity(_) => "events buffer has no capacity",
+            Error::LimitExceeded(_) => "limit on registrations reached",
+            Error::Interrupted(_) => "interrupted by a signal",
+            Error::Io(context, err) => return write!(f, "{context}: {err}"),
+        };
+
+        write!(f, "{}: {reason}", self.context())
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(_, err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(err: Error) -> Self {
+        io::Error::new(err.kind(), err)
+    }
+}
+
+/// Changes of a `Batch` that failed, every other change having been applied.
+#[derive(Debug)]
+pub struct BatchError {
+    /// Number of changes in the batch.
+    pub total: usize,
+    /// Failed changes, with the index each was added to the batch at, in order.
+    pub errors: Vec<(usize, Error)>,
+}
+
+impl fmt::Display for BatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} of {} changes failed", self.errors.len(), self.total)?;
+
+        if let Some((i, err)) = self.errors.first() {
+            write!(f, ", first #{i}: {err}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for BatchError {}
+
+impl From<BatchError> for io::Error {
+    fn from(err: BatchError) -> Self {
+        let kind = err
+            .errors
+            .first()
+            .map_or(io::ErrorKind::Other, |(_, err)| err.kind());
+
+        io::Error::new(kind, err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classify_errno() {
+        let register = Context::new(Operation::Register).fd(5).token(Token(3));
+        let os = io::Error::from_raw_os_error;
+
+        let err = Error::new(register, os(libc::EPERM));
+        assert!(matches!(err, Error::UnsupportedFileType(_)));
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+        assert_eq!(
+            err.to_string(),
+            "register fd=5 token=3: file type does not support polling"
+        );
+
+        let err = Error::new(register, os(libc::ENOSPC));
+        assert!(matches!(err, Error::LimitExceeded(_)));
+        assert_eq!(err.raw_os_error(), Some(libc::ENOSPC));
+
+        // only registrations are limited by the file type
+        let poll = Context::new(Operation::Poll);
+        assert!(matches!(Error::new(poll, os(libc::EPERM)), Error::Io(..)));
+        assert!(matches!(
+            Error::new(poll, os(libc::EINTR)),
+            Error::Interrupted(_)
+        ));
+
+        let err = Error::new(Context::new(Operation::Deregister).fd(5), os(libc::EBADF));
+        assert_eq!(err.raw_os_error(), Some(libc::EBADF));
+        assert!(err.to_string().starts_with("deregister fd=5: "));
+
+        let io: io::Error = Error::new(register, os(libc::EEXIST)).into();
+        assert_eq!(io.kind(), io::ErrorKind::AlreadyExists);
+        let inner = io.get_ref().unwrap().downcast_ref::<Error>().unwrap();
+        assert_eq!(inner.token(), Some(Token(3)));
+    }
+}
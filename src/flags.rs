@@ -0,0 +1,316 @@
+//! Typed views of the raw flag fields of `epoll_event` and `kevent`.
+//!
+//! Both types are available on every OS, so that events recorded or dumped on one OS can be
+//! decoded on another.
+//!
+//! ```
+//! use mini_mio::flags::EpollFlags;
+//!
+//! let flags = EpollFlags::EPOLLIN | EpollFlags::EPOLLET;
+//! assert_eq!(flags.to_string(), "EPOLLIN|EPOLLET");
+//! assert_eq!("EPOLLIN|EPOLLET".parse::<EpollFlags>().unwrap(), flags);
+//!
+//! // bits without a name are kept, and shown in hex
+//! let flags = EpollFlags::from_bits(0x8000_0005);
+//! assert_eq!(flags.to_string(), "EPOLLIN|EPOLLOUT|EPOLLET");
+//! assert_eq!(EpollFlags::from_bits(0x10_0001).to_string(), "EPOLLIN|0x100000");
+//! ```
+
+use std::fmt;
+use std::io;
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign};
+use std::str::FromStr;
+
+use crate::sys::constants::{epoll, kqueue};
+
+/// Defines a set of flags backed by `$bits`, with a constant per named flag.
+///
+/// Names are listed in the order they are displayed in. Bits without a name are kept as
+/// they are, so converting to and from the raw bits never loses information.
+macro_rules! flag_set {
+    (
+        $(#[$meta:meta])*
+        pub struct $name:ident($bits:ty) {
+            $( $(#[$flag_meta:meta])* $flag:ident = $value:expr, )*
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+        pub struct $name($bits);
+
+        impl $name {
+            $( $(#[$flag_meta])* pub const $flag: Self = Self($value as $bits); )*
+
+            /// Every named flag, in display order.
+            pub const NAMED: &'static [(&'static str, Self)] = &[
+                $( (stringify!($flag), Self::$flag), )*
+            ];
+
+            pub const fn empty() -> Self {
+                Self(0)
+            }
+
+            /// Union of every named flag.
+            pub const fn all() -> Self {
+                Self(0 $( | ($value as $bits) )*)
+            }
+
+            /// Keeps every bit, including those without a name.
+            pub const fn from_bits(bits: $bits) -> Self {
+                Self(bits)
+            }
+
+            /// Drops the bits without a name.
+            pub const fn from_bits_truncate(bits: $bits) -> Self {
+                Self(bits & Self::all().0)
+            }
+
+            pub const fn bits(self) -> $bits {
+                self.0
+            }
+
+            /// Bits that have no name.
+            pub const fn unknown(self) -> $bits {
+                self.0 & !Self::all().0
+            }
+
+            pub const fn is_empty(self) -> bool {
+                self.0 == 0
+            }
+
+            /// Returns true if all of the bits in `other` are set.
+            pub const fn contains(self, other: Self) -> bool {
+                self.0 & other.0 == other.0
+            }
+
+            /// Returns true if any of the bits in `other` are set.
+            pub const fn intersects(self, other: Self) -> bool {
+                self.0 & other.0 != 0
+            }
+        }
+
+        impl BitOr for $name {
+            type Output = Self;
+
+            fn bitor(self, rhs: Self) -> Self {
+                Self(self.0 | rhs.0)
+            }
+        }
+
+        impl BitOrAssign for $name {
+            fn bitor_assign(&mut self, rhs: Self) {
+                self.0 |= rhs.0
+            }
+        }
+
+        impl BitAnd for $name {
+            type Output = Self;
+
+            fn bitand(self, rhs: Self) -> Self {
+                Self(self.0 & rhs.0)
+            }
+        }
+
+        impl BitAndAssign for $name {
+            fn bitand_assign(&mut self, rhs: Self) {
+                self.0 &= rhs.0
+            }
+        }
+
+        /// Named flags separated by `|`, followed by any unknown bits in hex, or `0` when
+        /// no bit is set.
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                if self.is_empty() {
+                    return write!(f, "0");
+                }
+
+                let mut separator = "";
+                let mut remaining = self.0;
+
+                for (name, flag) in Self::NAMED {
+                    if flag.0 != 0 && remaining & flag.0 == flag.0 {
+                        write!(f, "{separator}{name}")?;
+                        separator = "|";
+                        remaining &= !flag.0;
+                    }
+                }
+
+                if remaining != 0 {
+                    write!(f, "{separator}{remaining:#x}")?;
+                }
+
+                Ok(())
+            }
+        }
+
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt::Display::fmt(self, f)
+            }
+        }
+
+        /// Parses the output of `Display`. Each part is either a flag name, or a decimal
+        /// or `0x` prefixed hexadecimal number.
+        impl FromStr for $name {
+            type Err = io::Error;
+
+            fn from_str(s: &str) -> io::Result<Self> {
+                s.split('|').try_fold(Self::empty(), |flags, part| {
+                    let part = part.trim();
+
+                    let flag = match Self::NAMED.iter().find(|(name, _)| *name == part) {
+                        Some((_, flag)) => *flag,
+                        None => Self(parse_bits(part).ok_or_else(|| {
+                            io::Error::new(
+                                io::ErrorKind::InvalidInput,
+                                format!("unknown flag {part:?} in {s:?}"),
+                            )
+                        })?),
+                    };
+
+                    Ok(flags | flag)
+                })
+            }
+        }
+    };
+}
+
+fn parse_bits<T: TryFrom<u64>>(s: &str) -> Option<T> {
+    let bits = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok()?,
+        None => s.parse().ok()?,
+    };
+
+    bits.try_into().ok()
+}
+
+flag_set! {
+    /// The `events` field of an `epoll_event`.
+    ///
+    /// The field is declared as `i32`, convert with `from_bits(event.events as u32)`.
+    pub struct EpollFlags(u32) {
+        EPOLLIN = epoll::events::EPOLLIN,
+        EPOLLPRI = epoll::events::EPOLLPRI,
+        EPOLLOUT = epoll::events::EPOLLOUT,
+        EPOLLERR = epoll::events::EPOLLERR,
+        EPOLLHUP = epoll::events::EPOLLHUP,
+        EPOLLRDNORM = epoll::events::EPOLLRDNORM,
+        EPOLLRDBAND = epoll::events::EPOLLRDBAND,
+        EPOLLWRNORM = epoll::events::EPOLLWRNORM,
+        EPOLLWRBAND = epoll::events::EPOLLWRBAND,
+        EPOLLMSG = epoll::events::EPOLLMSG,
+        EPOLLRDHUP = epoll::events::EPOLLRDHUP,
+        EPOLLEXCLUSIVE = epoll::events::EPOLLEXCLUSIVE,
+        EPOLLWAKEUP = epoll::events::EPOLLWAKEUP,
+        EPOLLONESHOT = epoll::events::EPOLLONESHOT,
+        EPOLLET = epoll::events::EPOLLET,
+    }
+}
+
+flag_set! {
+    /// The `flags` field of a `kevent`.
+    ///
+    /// `EV_FLAG0` and `EV_FLAG1` are aliases of `EV_POLL` and `EV_OOBAND`, and are shown
+    /// as the latter.
+    pub struct KqueueFlags(u16) {
+        EV_ADD = kqueue::flags::EV_ADD,
+        EV_DELETE = kqueue::flags::EV_DELETE,
+        EV_ENABLE = kqueue::flags::EV_ENABLE,
+        EV_DISABLE = kqueue::flags::EV_DISABLE,
+        EV_ONESHOT = kqueue::flags::EV_ONESHOT,
+        EV_CLEAR = kqueue::flags::EV_CLEAR,
+        EV_RECEIPT = kqueue::flags::EV_RECEIPT,
+        EV_DISPATCH = kqueue::flags::EV_DISPATCH,
+        EV_POLL = kqueue::flags::EV_POLL,
+        EV_OOBAND = kqueue::flags::EV_OOBAND,
+        EV_ERROR = kqueue::flags::EV_ERROR,
+        EV_EOF = kqueue::flags::EV_EOF,
+    }
+}
+
+/// Name of a `kevent` filter, such as `EVFILT_READ`.
+pub fn kqueue_filter_name(filter: i16) -> Option<&'static str> {
+    use kqueue::filters::*;
+
+    let name = match filter {
+        EVFILT_READ => "EVFILT_READ",
+        EVFILT_WRITE => "EVFILT_WRITE",
+        EVFILT_AIO => "EVFILT_AIO",
+        EVFILT_VNODE => "EVFILT_VNODE",
+        EVFILT_PROC => "EVFILT_PROC",
+        EVFILT_SIGNAL => "EVFILT_SIGNAL",
+        EVFILT_TIMER => "EVFILT_TIMER",
+        EVFILT_MACHPORT => "EVFILT_MACHPORT",
+        EVFILT_FS => "EVFILT_FS",
+        EVFILT_USER => "EVFILT_USER",
+        EVFILT_VM => "EVFILT_VM",
+        _ => return None,
+    };
+
+    Some(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_and_parse() {
+        let flags = EpollFlags::EPOLLIN | EpollFlags::EPOLLRDHUP | EpollFlags::EPOLLET;
+        assert_eq!(flags.to_string(), "EPOLLIN|EPOLLRDHUP|EPOLLET");
+        assert_eq!(flags.to_string().parse::<EpollFlags>().unwrap(), flags);
+
+        // the value epoll_wait returns for a readable, edge triggered registration
+        let flags = EpollFlags::from_bits(-2147483647i32 as u32);
+        assert_eq!(flags, EpollFlags::EPOLLIN | EpollFlags::EPOLLET);
+
+        assert_eq!(EpollFlags::empty().to_string(), "0");
+        assert_eq!("0".parse::<EpollFlags>().unwrap(), EpollFlags::empty());
+        assert_eq!(
+            " EV_ADD | EV_CLEAR ".parse::<KqueueFlags>().unwrap(),
+            KqueueFlags::EV_ADD | KqueueFlags::EV_CLEAR
+        );
+
+        let err = "EPOLLIN|EPOLLFOO".parse::<EpollFlags>().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!("0x10000".parse::<KqueueFlags>().is_err());
+    }
+
+    #[test]
+    fn unknown_bits() {
+        let flags = EpollFlags::from_bits(0x0010_0004);
+        assert_eq!(flags.unknown(), 0x0010_0000);
+        assert_eq!(flags.to_string(), "EPOLLOUT|0x100000");
+        assert_eq!("EPOLLOUT|0x100000".parse::<EpollFlags>().unwrap(), flags);
+        assert_eq!(
+            EpollFlags::from_bits_truncate(flags.bits()),
+            EpollFlags::EPOLLOUT
+        );
+
+        // aliases are shown by their first name
+        let flags = KqueueFlags::from_bits(kqueue::flags::EV_FLAG0 | kqueue::flags::EV_EOF);
+        assert_eq!(flags.to_string(), "EV_POLL|EV_EOF");
+        assert!(flags.contains(KqueueFlags::EV_EOF));
+        assert!(!flags.contains(KqueueFlags::EV_EOF | KqueueFlags::EV_ERROR));
+        assert!(flags.intersects(KqueueFlags::EV_EOF | KqueueFlags::EV_ERROR));
+        assert_eq!(flags & KqueueFlags::EV_EOF, KqueueFlags::EV_EOF);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn event_debug_names_flags() {
+        use crate::interfaces::Event;
+        use crate::sys::OsEvent;
+
+        let event = Event::new(OsEvent {
+            events: epoll::events::EPOLLIN | epoll::events::EPOLLET,
+            epoll_data: 3,
+        });
+
+        let debug = format!("{event:?}");
+        assert!(debug.contains("token: Token(3)"), "{debug}");
+        assert!(debug.contains("readable: true"), "{debug}");
+        assert!(debug.contains("events: EPOLLIN|EPOLLET"), "{debug}");
+    }
+}
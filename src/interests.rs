@@ -22,11 +22,11 @@ impl Interest {
     // This is not associated with a memory location on the type, but
     // are inlined into the context they are used.
 
-    pub const READABLE: Interest = Interest(unsafe { NonZeroU8::new_unchecked(READABLE) });
-    pub const WRITABLE: Interest = Interest(unsafe { NonZeroU8::new_unchecked(WRITABLE) });
+    pub const READABLE: Interest = Interest(NonZeroU8::new(READABLE).unwrap());
+    pub const WRITABLE: Interest = Interest(NonZeroU8::new(WRITABLE).unwrap());
 
     #[cfg(all(test, target_os = "macos"))]
-    pub const TIMER: Interest = Interest(unsafe { NonZeroU8::new_unchecked(TIMER) });
+    pub const TIMER: Interest = Interest(NonZeroU8::new(TIMER).unwrap());
 
     /// Add an interest via a bitwise or
     /// returns a new owned `Interest`
@@ -89,6 +89,18 @@ impl std::fmt::Debug for Interest {
     }
 }
 
+/// How readiness is reported for a registered source.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Trigger {
+    /// Notify only when a source becomes ready. The source must be drained until it
+    /// returns `WouldBlock` before it is reported again.
+    #[default]
+    Edge,
+
+    /// Notify on every poll for as long as the source is ready.
+    Level,
+}
+
 // Implementing the From trait for converting from Interest to u8
 impl From<Interest> for u8 {
     fn from(value: Interest) -> Self {
@@ -126,7 +138,8 @@ mod tests {
         assert!(interest.is_readable());
         assert!(interest.is_writable());
 
-        if cfg!(target_os = "macos") {
+        #[cfg(target_os = "macos")]
+        {
             assert!(!interest.is_timer());
             let interest = interest.add_interest(Interest::TIMER);
             assert!(interest.is_timer());
@@ -0,0 +1,214 @@
+//! Fixed capacity `Events` alternative, held inline rather than on the heap.
+
+use std::io;
+use std::mem::MaybeUninit;
+use std::time::Duration;
+
+use super::buffer::Sealed;
+use super::events::{Coalesced, Iter};
+use super::{Event, SysSelector};
+use crate::signal::SigSet;
+use crate::sys::{OsEvent, OsEvents};
+
+/// Buffer of at most `N` events, for polling without any heap allocation.
+///
+/// The epoll and kqueue backends write events straight into it. Other backends, and the
+/// `testing`, `record` and `metrics` wrappers, poll into a temporary buffer first.
+///
+/// Unlike `Events`, it cannot grow or defer events over a per-token limit. A saturated
+/// poll leaves the remaining events in the kernel, to be reported by the next poll.
+pub struct ArrayEvents<const N: usize> {
+    buf: [MaybeUninit<OsEvent>; N],
+
+    /// Number of events at the front of `buf` written by the last poll.
+    len: usize,
+}
+
+impl<const N: usize> ArrayEvents<N> {
+    pub const fn new() -> Self {
+        Self {
+            buf: [const { MaybeUninit::uninit() }; N],
+            len: 0,
+        }
+    }
+
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Whether the last poll filled the buffer. The kernel might be holding more ready
+    /// events, which are reported by the next poll.
+    pub fn is_saturated(&self) -> bool {
+        N > 0 && self.len == N
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    pub fn iter(&self) -> Iter<'_> {
+        Iter::new(self.as_slice())
+    }
+
+    /// Readiness per token, merged from every event of the last poll. See
+    /// `Events::coalesced`.
+    pub fn coalesced(&self) -> Coalesced {
+        Coalesced::new(self.as_slice())
+    }
+
+    fn as_slice(&self) -> &[OsEvent] {
+        // the first `len` events were written by the last poll
+        unsafe { std::slice::from_raw_parts(self.buf.as_ptr().cast(), self.len) }
+    }
+}
+
+impl<const N: usize> Default for ArrayEvents<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, const N: usize> IntoIterator for &'a ArrayEvents<N> {
+    type Item = &'a Event;
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<const N: usize> Sealed for ArrayEvents<N> {
+    fn before_poll(&mut self, timeout: Option<Duration>) -> Option<Duration> {
+        self.len = 0;
+        timeout
+    }
+
+    fn poll_from<S>(&mut self, selector: &S, timeout: Option<Duration>) -> io::Result<usize>
+    where
+        S: SysSelector<OsEvent = OsEvent, OsEvents = OsEvents>,
+    {
+        self.len = selector.poll_uninit(&mut self.buf, timeout)?;
+        Ok(self.len)
+    }
+
+    fn poll_from_with_sigmask<S>(
+        &mut self,
+        selector: &S,
+        timeout: Option<Duration>,
+        sigmask: &SigSet,
+    ) -> io::Result<usize>
+    where
+        S: SysSelector<OsEvent = OsEvent, OsEvents = OsEvents>,
+    {
+        self.len = selector.poll_uninit_with_sigmask(&mut self.buf, timeout, sigmask)?;
+        Ok(self.len)
+    }
+
+    fn after_poll(&mut self) {}
+
+    fn capacity(&self) -> usize {
+        N
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interests::Interest;
+    use crate::interfaces::{SysEvent, Token};
+    use crate::poll::{Backend, Poll};
+
+    #[test]
+    fn polls_in_place() {
+        use std::io::Write;
+        use std::os::unix::net::UnixStream;
+
+        let mut poll = Poll::builder().backend(Backend::Epoll).build().unwrap();
+        let (a, mut b) = UnixStream::pair().unwrap();
+        let (c, _d) = UnixStream::pair().unwrap();
+
+        poll.registry()
+            .register(&a, Token(1), Interest::READABLE)
+            .unwrap();
+        poll.registry()
+            .register(&c, Token(2), Interest::WRITABLE)
+            .unwrap();
+        b.write_all(b"ping").unwrap();
+
+        // room for a single event, the other is left for the next poll
+        let mut events = ArrayEvents::<1>::new();
+        poll.poll(&mut events, Some(Duration::from_millis(50)))
+            .unwrap();
+        assert_eq!(events.len(), 1);
+        assert!(events.is_saturated());
+        let first = events.iter().next().unwrap().token().0;
+
+        let mut events = ArrayEvents::<4>::new();
+        poll.poll(&mut events, Some(Duration::ZERO)).unwrap();
+        assert_eq!(events.len(), 1);
+        assert!(!events.is_saturated());
+
+        let mut tokens = [first, events.iter().next().unwrap().token().0];
+        tokens.sort();
+        assert_eq!(tokens, [1, 2]);
+    }
+
+    #[test]
+    fn polls_with_sigmask() {
+        use std::io::Write;
+        use std::os::unix::net::UnixStream;
+
+        use crate::signal::SigSet;
+
+        // in place on epoll, through a temporary buffer on poll(2)
+        for backend in [Backend::Epoll, Backend::Poll] {
+            let mut poll = Poll::builder().backend(backend).build().unwrap();
+            let (a, mut b) = UnixStream::pair().unwrap();
+
+            poll.registry()
+                .register(&a, Token(1), Interest::READABLE)
+                .unwrap();
+            b.write_all(b"ping").unwrap();
+
+            let mut events = ArrayEvents::<4>::new();
+            poll.poll_with_sigmask(
+                &mut events,
+                Some(Duration::from_millis(50)),
+                &SigSet::empty(),
+            )
+            .unwrap();
+            assert_eq!(events.len(), 1, "{backend:?}");
+            assert_eq!(events.iter().next().unwrap().token(), Token(1));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "testing")]
+    fn polls_through_temporary_buffer() {
+        let mut poll = Poll::builder().mock().build().unwrap();
+        let mock = poll.mock().unwrap();
+
+        for token in 0..3 {
+            mock.readable(Token(token));
+        }
+
+        let mut events = ArrayEvents::<2>::default();
+        poll.poll(&mut events, None).unwrap();
+
+        let tokens: Vec<_> = (&events).into_iter().map(|e| e.token().0).collect();
+        assert_eq!(tokens, [0, 1]);
+        assert!(events.is_saturated());
+
+        poll.poll(&mut events, None).unwrap();
+        assert_eq!(events.len(), 1);
+        assert!(events.iter().all(|event| event.is_readable()));
+    }
+}
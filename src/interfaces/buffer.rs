@@ -0,0 +1,54 @@
+//! Storage `Poll::poll` reads ready events into.
+
+use std::io;
+use std::time::Duration;
+
+use super::SysSelector;
+use crate::signal::SigSet;
+use crate::sys::{OsEvent, OsEvents};
+
+/// Buffer of ready events accepted by `Poll::poll`: either `Events`, on the heap, or
+/// `ArrayEvents`, which polls without allocating.
+///
+/// Sealed, as polling into a buffer depends on the internals of the selectors.
+pub trait EventBuffer: Sealed {}
+
+impl<T: Sealed> EventBuffer for T {}
+
+/// Steps of a poll, as carried out by `Poll::poll`.
+///
+/// Public, but unnameable outside of the crate, which keeps `EventBuffer` from being
+/// implemented elsewhere.
+pub trait Sealed {
+    /// Prepare for a poll, returning the timeout to poll with.
+    fn before_poll(&mut self, timeout: Option<Duration>) -> Option<Duration>;
+
+    /// Replace the events held with those ready in `selector`.
+    fn poll_from<S>(&mut self, selector: &S, timeout: Option<Duration>) -> io::Result<usize>
+    where
+        S: SysSelector<OsEvent = OsEvent, OsEvents = OsEvents>;
+
+    /// Same as `poll_from`, with the signal mask replaced by `sigmask` while blocked.
+    fn poll_from_with_sigmask<S>(
+        &mut self,
+        selector: &S,
+        timeout: Option<Duration>,
+        sigmask: &SigSet,
+    ) -> io::Result<usize>
+    where
+        S: SysSelector<OsEvent = OsEvent, OsEvents = OsEvents>;
+
+    /// Update the buffer's state from the events just polled.
+    fn after_poll(&mut self);
+
+    /// Drop the events held over from a previous poll that `f` returns false for. A no-op
+    /// for buffers that hold none over.
+    fn retain_deferred<F>(&mut self, _f: F)
+    where
+        F: FnMut(&OsEvent) -> bool,
+    {
+    }
+
+    /// Maximum number of events a single poll can report.
+    fn capacity(&self) -> usize;
+}
@@ -35,7 +35,7 @@ impl Event {
 ///
 /// Review: Is it really safe to clone these if underlying OsEvents might contain
 /// fields with pointers? Copying the pointer values might lead to double free.
-#[derive(Clone, Debug)]
+#[derive(Clone)]
 #[repr(transparent)]
 pub struct GenericEvent<T>
 where
@@ -56,6 +56,24 @@ where
     }
 }
 
+/// Shows the token, the decoded readiness, and the raw OS event with its flags by name.
+impl<T> std::fmt::Debug for GenericEvent<T>
+where
+    T: SysEvent + std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Event")
+            .field("token", &self.token())
+            .field("readable", &self.is_readable())
+            .field("writable", &self.is_writable())
+            .field("read_closed", &self.is_read_closed())
+            .field("write_closed", &self.is_write_closed())
+            .field("error", &self.is_error())
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
 trait WrapperEvent {}
 
 impl<T> SysEvent for GenericEvent<T>
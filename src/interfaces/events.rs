@@ -1,14 +1,48 @@
 #![allow(unused)]
 use std::{
+    collections::HashMap,
+    io,
     iter::Iterator,
     ops::{Deref, DerefMut},
+    time::Duration,
 };
 
-use super::Event;
+use super::buffer::Sealed;
+use super::{Event, Ready, SysEvent, SysSelector, Token};
+use crate::signal::SigSet;
 
 /// Wrapper around the OsEvents type.
 pub struct Events {
     inner: crate::sys::OsEvents,
+
+    /// Whether the last poll filled the buffer, so that more events might be ready.
+    saturated: bool,
+
+    /// Capacity the buffer may grow to after a saturated poll, if growing is enabled.
+    max_capacity: Option<usize>,
+
+    /// Maximum number of events reported per token by a single poll.
+    token_limit: Option<usize>,
+
+    /// Events over the per token limit, reported first by the next poll. At most one per
+    /// registration, later events for it being merged in.
+    deferred: Vec<crate::sys::OsEvent>,
+
+    /// Buffers kept across polls by `after_poll`, so that applying the per token limit
+    /// does not allocate once they have grown to fit.
+    scratch: Scratch,
+}
+
+#[derive(Default)]
+struct Scratch {
+    /// Swapped with the buffer polled into, then refilled with the events reported.
+    polled: crate::sys::OsEvents,
+    /// Swapped with the deferred events, which are pending again.
+    pending: Vec<crate::sys::OsEvent>,
+    /// Events reported per token so far.
+    counts: HashMap<Token, usize>,
+    /// Position of the deferred event of each registration.
+    deferred: HashMap<crate::sys::EventKey, usize>,
 }
 
 impl From<Events> for crate::sys::OsEvents {
@@ -21,8 +55,146 @@ impl Events {
     pub fn with_capacity(capacity: usize) -> Self {
         Self {
             inner: crate::sys::OsEvents::with_capacity(capacity),
+            saturated: false,
+            max_capacity: None,
+            token_limit: None,
+            deferred: Vec::new(),
+            scratch: Scratch::default(),
+        }
+    }
+
+    /// A buffer doubling its capacity, up to `max_capacity`, after every poll that filled
+    /// it. The events left in the kernel are retrieved by the next poll.
+    pub fn with_growth(capacity: usize, max_capacity: usize) -> Self {
+        Self {
+            max_capacity: Some(max_capacity.max(capacity)),
+            ..Self::with_capacity(capacity)
+        }
+    }
+
+    /// Report at most `limit` events per token from a single poll, so that a busy source
+    /// cannot crowd out the others. The rest are deferred, and reported first by the next
+    /// poll, which then does not block.
+    pub fn with_token_limit(mut self, limit: usize) -> Self {
+        self.token_limit = Some(limit.max(1));
+        self
+    }
+
+    /// Whether the last poll filled the buffer. The kernel might be holding more ready
+    /// events, which are reported by the next poll.
+    pub fn is_saturated(&self) -> bool {
+        self.saturated
+    }
+
+    /// Number of events held back by the per token limit until the next poll.
+    pub fn deferred(&self) -> usize {
+        self.deferred.len()
+    }
+
+    /// Grow the buffer if the last poll saturated it, returning the timeout to poll with.
+    pub(crate) fn before_poll(&mut self, timeout: Option<Duration>) -> Option<Duration> {
+        if let Some(max_capacity) = self.max_capacity {
+            let capacity = self.inner.capacity();
+
+            if self.saturated && capacity < max_capacity {
+                self.inner.clear();
+                self.inner
+                    .reserve_exact((capacity * 2).clamp(1, max_capacity));
+            }
+        }
+
+        // deferred events are ready already
+        match self.deferred.is_empty() {
+            true => timeout,
+            false => Some(Duration::ZERO),
         }
     }
+
+    /// Record whether the poll saturated the buffer, and apply the per token limit.
+    pub(crate) fn after_poll(&mut self) {
+        self.saturated = self.inner.capacity() > 0 && self.inner.len() == self.inner.capacity();
+
+        let Some(limit) = self.token_limit else {
+            return;
+        };
+
+        let capacity = self.inner.capacity();
+        let scratch = &mut self.scratch;
+
+        std::mem::swap(&mut self.inner, &mut scratch.polled);
+        std::mem::swap(&mut self.deferred, &mut scratch.pending);
+        self.inner.clear();
+        self.inner.reserve_exact(capacity);
+        scratch.counts.clear();
+        scratch.deferred.clear();
+
+        for event in scratch.pending.drain(..).chain(scratch.polled.drain(..)) {
+            // reported again while still deferred, e.g. by a level-triggered queue
+            if let Some(&i) = scratch.deferred.get(&event.key()) {
+                self.deferred[i].merge(&event);
+                continue;
+            }
+
+            let count = scratch.counts.entry(event.token()).or_default();
+
+            if *count < limit && self.inner.len() < capacity {
+                *count += 1;
+                self.inner.push(event);
+            } else {
+                scratch.deferred.insert(event.key(), self.deferred.len());
+                self.deferred.push(event);
+            }
+        }
+    }
+
+    /// Drop the deferred events `f` returns false for, e.g. those of sources deregistered
+    /// since they were polled.
+    pub(crate) fn retain_deferred<F>(&mut self, f: F)
+    where
+        F: FnMut(&crate::sys::OsEvent) -> bool,
+    {
+        self.deferred.retain(f);
+    }
+}
+
+impl Sealed for Events {
+    fn before_poll(&mut self, timeout: Option<Duration>) -> Option<Duration> {
+        Events::before_poll(self, timeout)
+    }
+
+    fn poll_from<S>(&mut self, selector: &S, timeout: Option<Duration>) -> io::Result<usize>
+    where
+        S: SysSelector<OsEvent = crate::sys::OsEvent, OsEvents = crate::sys::OsEvents>,
+    {
+        selector.poll(&mut self.inner, timeout)
+    }
+
+    fn poll_from_with_sigmask<S>(
+        &mut self,
+        selector: &S,
+        timeout: Option<Duration>,
+        sigmask: &SigSet,
+    ) -> io::Result<usize>
+    where
+        S: SysSelector<OsEvent = crate::sys::OsEvent, OsEvents = crate::sys::OsEvents>,
+    {
+        selector.poll_with_sigmask(&mut self.inner, timeout, sigmask)
+    }
+
+    fn after_poll(&mut self) {
+        Events::after_poll(self)
+    }
+
+    fn retain_deferred<F>(&mut self, f: F)
+    where
+        F: FnMut(&crate::sys::OsEvent) -> bool,
+    {
+        Events::retain_deferred(self, f)
+    }
+
+    fn capacity(&self) -> usize {
+        self.inner.capacity()
+    }
 }
 
 impl Deref for Events {
@@ -38,6 +210,61 @@ impl DerefMut for Events {
     }
 }
 
+// ################ Coalesced ################
+
+/// Readiness per token, merged from every event of a poll, in order of each token's first
+/// event. Returned by `Events::coalesced`.
+///
+/// kqueue reports one event per filter, so a source registered for both directions can
+/// produce two events with the same token, where epoll produces one. Handlers iterating
+/// over this view run once per token on every backend.
+pub struct Coalesced {
+    inner: std::vec::IntoIter<(Token, Ready)>,
+}
+
+impl Events {
+    pub fn coalesced(&self) -> Coalesced {
+        Coalesced::new(&self.inner)
+    }
+}
+
+impl Coalesced {
+    pub(super) fn new(events: &[crate::sys::OsEvent]) -> Self {
+        let mut merged: Vec<(Token, Ready)> = Vec::with_capacity(events.len());
+        let mut index: HashMap<Token, usize> = HashMap::with_capacity(events.len());
+
+        for event in events {
+            let ready = Ready::from_event(event);
+
+            match index.get(&event.token()) {
+                Some(&i) => merged[i].1 |= ready,
+                None => {
+                    index.insert(event.token(), merged.len());
+                    merged.push((event.token(), ready));
+                }
+            }
+        }
+
+        Coalesced {
+            inner: merged.into_iter(),
+        }
+    }
+}
+
+impl Iterator for Coalesced {
+    type Item = (Token, Ready);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl ExactSizeIterator for Coalesced {}
+
 // ################ IntoIter ################
 pub struct IntoIter(Events);
 
@@ -83,15 +310,21 @@ pub struct Iter<'a> {
     inner: std::slice::Iter<'a, crate::sys::OsEvent>,
 }
 
+impl<'a> Iter<'a> {
+    pub(super) fn new(events: &'a [crate::sys::OsEvent]) -> Self {
+        Iter {
+            inner: events.iter(),
+        }
+    }
+}
+
 impl Events {
     /// Returns an iterator over mutable references.
     ///
     /// Implemented via wrapping an inner iterator that yields OsEvents,
     /// and returning this as an iterator that yields Events.
     fn iter(&self) -> Iter<'_> {
-        Iter {
-            inner: self.inner.iter(),
-        }
+        Iter::new(&self.inner)
     }
 }
 
@@ -162,4 +395,104 @@ mod tests {
         assert!(matches!(iter.next(), Some(&_)));
         assert!(iter.next().is_none());
     }
+
+    /// Fill `events` via a poll, rather than pushing events by hand.
+    #[cfg(feature = "testing")]
+    fn poll_tokens(tokens: &[usize]) -> Events {
+        use crate::poll::Poll;
+
+        let mut poll = Poll::builder().mock().build().unwrap();
+        let mock = poll.mock().unwrap();
+        let mut events = Events::with_capacity(tokens.len());
+
+        for &token in tokens {
+            mock.readable(super::super::Token(token));
+        }
+
+        poll.poll(&mut events, None).unwrap();
+        events
+    }
+
+    #[test]
+    #[cfg(feature = "testing")]
+    fn iter_polled_events() {
+        use crate::interfaces::SysEvent;
+
+        let events = poll_tokens(&[1, 2, 3]);
+
+        let tokens: Vec<_> = events.iter().map(|event| event.token().0).collect();
+        assert_eq!(tokens, [1, 2, 3]);
+        assert!(events.iter().all(|event| event.is_readable()));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn coalesced_merges_tokens() {
+        use crate::sys::constants::epoll::events::*;
+
+        let event = |events, token| crate::sys::OsEvent {
+            events,
+            epoll_data: token,
+        };
+
+        let mut events = Events::with_capacity(4);
+        events.push(event(EPOLLIN, 7));
+        events.push(event(EPOLLOUT, 3));
+        events.push(event(EPOLLOUT | EPOLLHUP, 7));
+
+        let coalesced: Vec<_> = events.coalesced().collect();
+        assert_eq!(
+            coalesced,
+            [
+                (
+                    Token(7),
+                    Ready::READABLE | Ready::WRITABLE | Ready::READ_CLOSED | Ready::WRITE_CLOSED
+                ),
+                (Token(3), Ready::WRITABLE),
+            ]
+        );
+        assert_eq!(format!("{:?}", coalesced[1].1), "WRITABLE");
+        assert_eq!(format!("{:?}", Ready::EMPTY), "EMPTY");
+    }
+
+    #[test]
+    #[cfg(feature = "testing")]
+    fn grows_after_saturated_poll() {
+        use crate::poll::Poll;
+
+        let mut poll = Poll::builder().mock().build().unwrap();
+        let mock = poll.mock().unwrap();
+        let mut events = Events::with_growth(2, 3);
+
+        for token in 0..5 {
+            mock.readable(super::super::Token(token));
+        }
+
+        poll.poll(&mut events, None).unwrap();
+        assert_eq!(events.len(), 2);
+        assert!(events.is_saturated());
+
+        // doubled, but capped at the ceiling
+        poll.poll(&mut events, None).unwrap();
+        assert_eq!(events.capacity(), 3);
+        assert_eq!(events.len(), 3);
+        assert!(events.is_saturated());
+
+        poll.poll(&mut events, None).unwrap();
+        assert_eq!(events.capacity(), 3);
+        assert!(events.is_empty());
+        assert!(!events.is_saturated());
+    }
+
+    #[test]
+    #[cfg(feature = "testing")]
+    fn into_iter_polled_events() {
+        use crate::interfaces::SysEvent;
+
+        let events = poll_tokens(&[1, 2, 3]);
+
+        // consuming iterator pops from the back
+        let tokens: Vec<_> = events.into_iter().map(|event| event.token().0).collect();
+        assert_eq!(tokens, [3, 2, 1]);
+    }
 }
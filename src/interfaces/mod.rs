@@ -2,14 +2,17 @@
 //!
 //! These will wrap the various OS specific types and provide a common interface.
 
+mod array_events;
+mod buffer;
 mod event;
 mod events;
+mod ready;
 mod sysevent;
 mod sysselector;
 mod token;
 
 #[allow(unused_imports)]
-pub use sysselector::SysSelector;
+pub use sysselector::{Change, SelectorOptions, SysSelector};
 
 #[allow(unused_imports)]
 pub use sysevent::SysEvent;
@@ -18,7 +21,18 @@ pub use sysevent::SysEvent;
 pub use event::Event;
 
 #[allow(unused_imports)]
-pub use events::Events;
+pub use events::{Coalesced, Events};
+
+#[allow(unused_imports)]
+pub use array_events::ArrayEvents;
+
+#[allow(unused_imports)]
+pub use buffer::EventBuffer;
+
+pub(crate) use buffer::Sealed;
+
+#[allow(unused_imports)]
+pub use ready::Ready;
 
 #[allow(unused_imports)]
 pub use token::Token;
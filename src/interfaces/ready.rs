@@ -0,0 +1,115 @@
+//! Readiness of a source, merged from every event reported for its token.
+
+use std::fmt;
+use std::ops::{BitOr, BitOrAssign};
+
+use super::SysEvent;
+
+const READABLE: u8 = 1;
+const WRITABLE: u8 = 1 << 1;
+const READ_CLOSED: u8 = 1 << 2;
+const WRITE_CLOSED: u8 = 1 << 3;
+const ERROR: u8 = 1 << 4;
+
+/// Set of readiness flags, as reported by `Events::coalesced`.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Ready(u8);
+
+impl Ready {
+    pub const EMPTY: Ready = Ready(0);
+    pub const READABLE: Ready = Ready(READABLE);
+    pub const WRITABLE: Ready = Ready(WRITABLE);
+    pub const READ_CLOSED: Ready = Ready(READ_CLOSED);
+    pub const WRITE_CLOSED: Ready = Ready(WRITE_CLOSED);
+    pub const ERROR: Ready = Ready(ERROR);
+
+    /// Readiness reported by a single event.
+    pub fn from_event<E: SysEvent>(event: &E) -> Self {
+        let flags = [
+            (event.is_readable(), Ready::READABLE),
+            (event.is_writable(), Ready::WRITABLE),
+            (event.is_read_closed(), Ready::READ_CLOSED),
+            (event.is_write_closed(), Ready::WRITE_CLOSED),
+            (event.is_error(), Ready::ERROR),
+        ];
+
+        flags
+            .into_iter()
+            .filter(|(set, _)| *set)
+            .fold(Ready::EMPTY, |ready, (_, flag)| ready | flag)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn contains(&self, other: Ready) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn is_readable(&self) -> bool {
+        self.contains(Ready::READABLE)
+    }
+
+    pub fn is_writable(&self) -> bool {
+        self.contains(Ready::WRITABLE)
+    }
+
+    pub fn is_read_closed(&self) -> bool {
+        self.contains(Ready::READ_CLOSED)
+    }
+
+    pub fn is_write_closed(&self) -> bool {
+        self.contains(Ready::WRITE_CLOSED)
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.contains(Ready::ERROR)
+    }
+}
+
+impl BitOr for Ready {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Ready(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for Ready {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// Flag names joined by ` | `, e.g. `READABLE | WRITABLE`, or `EMPTY`.
+impl fmt::Debug for Ready {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names = [
+            (Ready::READABLE, "READABLE"),
+            (Ready::WRITABLE, "WRITABLE"),
+            (Ready::READ_CLOSED, "READ_CLOSED"),
+            (Ready::WRITE_CLOSED, "WRITE_CLOSED"),
+            (Ready::ERROR, "ERROR"),
+        ];
+
+        if self.is_empty() {
+            return f.write_str("EMPTY");
+        }
+
+        let mut names = names
+            .into_iter()
+            .filter(|(flag, _)| self.contains(*flag))
+            .map(|(_, name)| name);
+
+        if let Some(first) = names.next() {
+            f.write_str(first)?;
+        }
+
+        for name in names {
+            write!(f, " | {name}")?;
+        }
+
+        Ok(())
+    }
+}
@@ -1,24 +1,100 @@
 #![allow(unused)]
 
 use std::io;
+use std::mem::MaybeUninit;
 
-use crate::interests::Interest;
+use crate::interests::{Interest, Trigger};
 use crate::interfaces::Token;
+use crate::poll::Backend;
+use crate::signal::SigSet;
 
 use std::os::fd::RawFd;
 use std::time::Duration;
 
 use super::SysEvent;
 
+/// Options used when creating the OSes event queue.
+#[derive(Clone, Copy, Debug)]
+pub struct SelectorOptions {
+    /// Backend to use, or `None` for the platform default.
+    ///
+    /// Only used by selectors that dispatch to one of several backends.
+    pub backend: Option<Backend>,
+
+    /// Set close-on-exec on the event queue file descriptor, so that it is not leaked
+    /// into programs started via `exec`.
+    pub cloexec: bool,
+
+    /// Trigger mode applied to every registration.
+    pub trigger: Trigger,
+
+    /// Expected number of events retrieved per poll. Backends with fixed size kernel
+    /// structures use this to size them.
+    pub capacity: usize,
+}
+
+impl Default for SelectorOptions {
+    fn default() -> Self {
+        Self {
+            backend: None,
+            cloexec: true,
+            trigger: Trigger::Edge,
+            capacity: 128,
+        }
+    }
+}
+
+/// A registration change, applied together with others via `SysSelector::apply`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Change {
+    Register {
+        fd: RawFd,
+        token: Token,
+        interests: Interest,
+    },
+    Reregister {
+        fd: RawFd,
+        token: Token,
+        interests: Interest,
+    },
+    Deregister {
+        fd: RawFd,
+    },
+}
+
+impl Change {
+    pub fn fd(&self) -> RawFd {
+        match *self {
+            Change::Register { fd, .. }
+            | Change::Reregister { fd, .. }
+            | Change::Deregister { fd } => fd,
+        }
+    }
+}
+
 pub trait SysSelector
 where
     Self: Sized,
 {
     type OsEvent: SysEvent;
-    type OsEvents: AsMut<Vec<Self::OsEvent>>;
+    type OsEvents: AsMut<Vec<Self::OsEvent>> + Default;
+
+    /// Create a new instance of the OSes event queue with default options.
+    fn new() -> io::Result<Self> {
+        Self::with_options(SelectorOptions::default())
+    }
 
     /// Create a new instance of the OSes event queue and store event queue file descriptor
-    fn new() -> io::Result<Self>;
+    fn with_options(options: SelectorOptions) -> io::Result<Self>;
+
+    /// The backend this selector is using.
+    fn backend(&self) -> Backend;
+
+    /// File descriptor of the OSes event queue, which becomes readable when events are
+    /// ready. `None` for backends without a kernel side event queue.
+    fn raw_fd(&self) -> Option<RawFd> {
+        None
+    }
 
     /// Register interest in events on a sources file descriptor.
     ///
@@ -34,9 +110,108 @@ where
     /// The `RawFd` is just an alias to c_int, which is an i32 on unix / OSX.
     fn reregister(&self, fd: RawFd, token: Token, interests: Interest) -> io::Result<()>;
 
+    /// Re-enable notifications for `interests` after the source returned `WouldBlock`.
+    ///
+    /// Backends with native edge-triggered support keep notifying on every new edge, so
+    /// this is a no-op by default. Backends that emulate edge-triggered mode disarm a
+    /// source once it has been reported, and need to be told it has been drained.
+    fn rearm(&self, fd: RawFd, interests: Interest) -> io::Result<()> {
+        Ok(())
+    }
+
     /// Poll for events on file descriptors
     fn poll(&self, events: &mut Self::OsEvents, timeout: Option<Duration>) -> io::Result<usize>;
 
+    /// Poll for events into uninitialised storage, returning the number of events written
+    /// to the front of `events`.
+    ///
+    /// Polls into a temporary buffer by default. Backends able to hand the storage to the
+    /// kernel directly override this, so that polling does not allocate.
+    fn poll_uninit(
+        &self,
+        events: &mut [MaybeUninit<Self::OsEvent>],
+        timeout: Option<Duration>,
+    ) -> io::Result<usize> {
+        poll_via_buffer::<Self>(events, |polled| self.poll(polled, timeout))
+    }
+
+    /// Poll for events with the calling thread's signal mask replaced by `sigmask` for
+    /// the duration of the wait only.
+    ///
+    /// Backends without an atomic way to swap the mask, such as kqueue, fail with
+    /// `Unsupported`, as emulating it via `pthread_sigmask` would reintroduce the race.
+    fn poll_with_sigmask(
+        &self,
+        events: &mut Self::OsEvents,
+        timeout: Option<Duration>,
+        sigmask: &SigSet,
+    ) -> io::Result<usize> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!(
+                "{:?} backend cannot wait with a signal mask",
+                self.backend()
+            ),
+        ))
+    }
+
+    /// Same as `poll_with_sigmask`, into uninitialised storage. See `poll_uninit`.
+    fn poll_uninit_with_sigmask(
+        &self,
+        events: &mut [MaybeUninit<Self::OsEvent>],
+        timeout: Option<Duration>,
+        sigmask: &SigSet,
+    ) -> io::Result<usize> {
+        poll_via_buffer::<Self>(events, |polled| {
+            self.poll_with_sigmask(polled, timeout, sigmask)
+        })
+    }
+
     /// Stop monitoring for events on file descriptor
     fn deregister(&self, fd: RawFd) -> io::Result<()>;
+
+    /// Apply `changes` in order, returning the result of each.
+    ///
+    /// Makes one call per change by default. Backends able to submit several changes in
+    /// a single syscall, while still reporting an error per change, override this.
+    fn apply(&self, changes: &[Change]) -> Vec<io::Result<()>> {
+        changes
+            .iter()
+            .map(|change| match *change {
+                Change::Register {
+                    fd,
+                    token,
+                    interests,
+                } => self.register(fd, token, interests),
+                Change::Reregister {
+                    fd,
+                    token,
+                    interests,
+                } => self.reregister(fd, token, interests),
+                Change::Deregister { fd } => self.deregister(fd),
+            })
+            .collect()
+    }
+}
+
+/// Poll into a temporary buffer via `poll`, then move the events to the front of `events`.
+fn poll_via_buffer<S: SysSelector>(
+    events: &mut [MaybeUninit<S::OsEvent>],
+    poll: impl FnOnce(&mut S::OsEvents) -> io::Result<usize>,
+) -> io::Result<usize> {
+    let mut polled = S::OsEvents::default();
+    polled.as_mut().reserve_exact(events.len());
+
+    poll(&mut polled)?;
+
+    let polled = polled.as_mut();
+    // never more than asked for, even if the backend handed back extra
+    polled.truncate(events.len());
+
+    let n = polled.len();
+    for (slot, event) in events.iter_mut().zip(polled.drain(..)) {
+        slot.write(event);
+    }
+
+    Ok(n)
 }
@@ -1,3 +1,4 @@
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 #[repr(transparent)]
 pub struct Token(pub usize);
 
@@ -1,6 +1,25 @@
+pub mod decode;
+pub mod error;
+pub mod flags;
 pub mod interests;
 pub mod poll;
+pub mod signal;
 
 pub mod interfaces;
 
 mod sys;
+
+#[cfg(feature = "runtime")]
+pub mod runtime;
+
+#[cfg(feature = "testing")]
+pub mod testing;
+
+#[cfg(feature = "record")]
+pub mod record;
+
+#[cfg(feature = "metrics")]
+pub mod metrics;
+
+#[cfg(feature = "trace")]
+pub mod trace;
@@ -0,0 +1,409 @@
+//! Opt-in counters for the calls made to the selector.
+//!
+//! Pass a `Metrics` handle to `PollBuilder::metrics`, and take a `snapshot` whenever the
+//! numbers are to be exported. Counters are only ever incremented, so the rate of
+//! anything is the difference between two snapshots.
+//!
+//! ```
+//! use mini_mio::interfaces::Events;
+//! use mini_mio::metrics::Metrics;
+//! use mini_mio::poll::Poll;
+//!
+//! let metrics = Metrics::new();
+//! let mut poll = Poll::builder().metrics(metrics.clone()).build().unwrap();
+//! let mut events = Events::with_capacity(8);
+//!
+//! poll.poll(&mut events, Some(std::time::Duration::ZERO)).unwrap();
+//!
+//! let snapshot = metrics.snapshot();
+//! assert_eq!(snapshot.waits, 1);
+//! assert_eq!(snapshot.timeouts, 1);
+//! ```
+
+use std::io;
+use std::os::fd::RawFd;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::interests::Interest;
+use crate::interfaces::{Change, SelectorOptions, SysSelector, Token};
+use crate::poll::Backend;
+use crate::signal::SigSet;
+use crate::sys::{OsEvent, OsEvents};
+
+/// Number of buckets in `Histogram`, the last of which is unbounded.
+pub const HISTOGRAM_BUCKETS: usize = 16;
+
+/// Number of events returned per wakeup, in power of two buckets.
+///
+/// Bucket `i` counts wakeups returning between `2^i` and `2^(i+1) - 1` events, apart from
+/// the last bucket which counts everything above.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Histogram {
+    pub buckets: [u64; HISTOGRAM_BUCKETS],
+}
+
+impl Histogram {
+    /// Inclusive range of event counts for bucket `i`.
+    pub fn bounds(i: usize) -> (usize, usize) {
+        let low = 1 << i;
+
+        if i + 1 == HISTOGRAM_BUCKETS {
+            (low, usize::MAX)
+        } else {
+            (low, (low << 1) - 1)
+        }
+    }
+
+    fn bucket(events: usize) -> usize {
+        (events.max(1).ilog2() as usize).min(HISTOGRAM_BUCKETS - 1)
+    }
+}
+
+/// Numbers collected since the `Metrics` handle was created.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Snapshot {
+    /// `epoll_ctl(EPOLL_CTL_ADD)`, or the equivalent call of the backend.
+    pub registers: u64,
+    /// `epoll_ctl(EPOLL_CTL_MOD)`, or the equivalent call of the backend.
+    pub reregisters: u64,
+    /// `epoll_ctl(EPOLL_CTL_DEL)`, or the equivalent call of the backend.
+    pub deregisters: u64,
+    /// Calls to `Registry::rearm`, which only make a syscall on some backends.
+    pub rearms: u64,
+    /// Calls of any of the above that failed.
+    pub ctl_errors: u64,
+
+    /// Calls to `epoll_wait`, or the equivalent call of the backend.
+    pub waits: u64,
+    /// Waits returning no events.
+    pub timeouts: u64,
+    /// Waits returning at least one event.
+    pub wakeups: u64,
+    /// Waits that failed, including `EINTR`.
+    pub wait_errors: u64,
+    /// Wakeups that filled the `Events` buffer, suggesting its capacity is too small.
+    pub saturated: u64,
+    /// Total number of events returned.
+    pub events: u64,
+    pub events_per_wakeup: Histogram,
+
+    /// Time spent blocked in the wait syscall.
+    pub blocked: Duration,
+    /// Time spent between the wait returning and the next call to `Poll::poll`. A busy
+    /// loop shows up as a high number of timeouts or wakeups with little time blocked.
+    pub processing: Duration,
+}
+
+#[derive(Default)]
+struct Counters {
+    registers: AtomicU64,
+    reregisters: AtomicU64,
+    deregisters: AtomicU64,
+    rearms: AtomicU64,
+    ctl_errors: AtomicU64,
+    waits: AtomicU64,
+    timeouts: AtomicU64,
+    wakeups: AtomicU64,
+    wait_errors: AtomicU64,
+    saturated: AtomicU64,
+    events: AtomicU64,
+    histogram: [AtomicU64; HISTOGRAM_BUCKETS],
+    blocked_nanos: AtomicU64,
+    processing_nanos: AtomicU64,
+    /// When the last wait returned.
+    returned: Mutex<Option<Instant>>,
+}
+
+fn add(counter: &AtomicU64, value: u64) {
+    counter.fetch_add(value, Ordering::Relaxed);
+}
+
+fn nanos(duration: Duration) -> u64 {
+    duration.as_nanos().try_into().unwrap_or(u64::MAX)
+}
+
+/// Handle to the counters of an event queue. Clones share the same counters.
+#[derive(Clone, Default)]
+pub struct Metrics {
+    counters: Arc<Counters>,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Copy of the counters as they are now.
+    pub fn snapshot(&self) -> Snapshot {
+        let c = &*self.counters;
+        let get = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+
+        Snapshot {
+            registers: get(&c.registers),
+            reregisters: get(&c.reregisters),
+            deregisters: get(&c.deregisters),
+            rearms: get(&c.rearms),
+            ctl_errors: get(&c.ctl_errors),
+            waits: get(&c.waits),
+            timeouts: get(&c.timeouts),
+            wakeups: get(&c.wakeups),
+            wait_errors: get(&c.wait_errors),
+            saturated: get(&c.saturated),
+            events: get(&c.events),
+            events_per_wakeup: Histogram {
+                buckets: std::array::from_fn(|i| get(&c.histogram[i])),
+            },
+            blocked: Duration::from_nanos(get(&c.blocked_nanos)),
+            processing: Duration::from_nanos(get(&c.processing_nanos)),
+        }
+    }
+
+    fn ctl<T>(&self, counter: &AtomicU64, result: io::Result<T>) -> io::Result<T> {
+        add(counter, 1);
+
+        if result.is_err() {
+            add(&self.counters.ctl_errors, 1);
+        }
+
+        result
+    }
+}
+
+impl std::fmt::Debug for Metrics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Metrics").field(&self.snapshot()).finish()
+    }
+}
+
+/// Wraps a selector, counting every call made to it.
+pub struct MeteredSelector<S> {
+    inner: S,
+    metrics: Metrics,
+}
+
+impl<S: SysSelector> MeteredSelector<S> {
+    pub fn new(inner: S, metrics: Metrics) -> Self {
+        Self { inner, metrics }
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    pub(crate) fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+}
+
+impl<S> SysSelector for MeteredSelector<S>
+where
+    S: SysSelector<OsEvent = OsEvent, OsEvents = OsEvents>,
+{
+    type OsEvent = OsEvent;
+    type OsEvents = OsEvents;
+
+    fn with_options(options: SelectorOptions) -> io::Result<Self> {
+        Ok(Self::new(S::with_options(options)?, Metrics::new()))
+    }
+
+    fn backend(&self) -> Backend {
+        self.inner.backend()
+    }
+
+    fn raw_fd(&self) -> Option<RawFd> {
+        self.inner.raw_fd()
+    }
+
+    fn register(&self, fd: RawFd, token: Token, interests: Interest) -> io::Result<()> {
+        let result = self.inner.register(fd, token, interests);
+        self.metrics.ctl(&self.metrics.counters.registers, result)
+    }
+
+    fn reregister(&self, fd: RawFd, token: Token, interests: Interest) -> io::Result<()> {
+        let result = self.inner.reregister(fd, token, interests);
+        self.metrics.ctl(&self.metrics.counters.reregisters, result)
+    }
+
+    fn rearm(&self, fd: RawFd, interests: Interest) -> io::Result<()> {
+        let result = self.inner.rearm(fd, interests);
+        self.metrics.ctl(&self.metrics.counters.rearms, result)
+    }
+
+    fn deregister(&self, fd: RawFd) -> io::Result<()> {
+        let result = self.inner.deregister(fd);
+        self.metrics.ctl(&self.metrics.counters.deregisters, result)
+    }
+
+    /// Counted per change, as if each had been a call of its own.
+    fn apply(&self, changes: &[Change]) -> Vec<io::Result<()>> {
+        let c = &self.metrics.counters;
+
+        changes
+            .iter()
+            .zip(self.inner.apply(changes))
+            .map(|(change, result)| {
+                let counter = match change {
+                    Change::Register { .. } => &c.registers,
+                    Change::Reregister { .. } => &c.reregisters,
+                    Change::Deregister { .. } => &c.deregisters,
+                };
+                self.metrics.ctl(counter, result)
+            })
+            .collect()
+    }
+
+    fn poll(&self, events: &mut Self::OsEvents, timeout: Option<Duration>) -> io::Result<usize> {
+        self.count_wait(events, |inner, events| inner.poll(events, timeout))
+    }
+
+    fn poll_with_sigmask(
+        &self,
+        events: &mut Self::OsEvents,
+        timeout: Option<Duration>,
+        sigmask: &SigSet,
+    ) -> io::Result<usize> {
+        self.count_wait(events, |inner, events| {
+            inner.poll_with_sigmask(events, timeout, sigmask)
+        })
+    }
+}
+
+impl<S> MeteredSelector<S> {
+    fn count_wait(
+        &self,
+        events: &mut OsEvents,
+        wait: impl FnOnce(&S, &mut OsEvents) -> io::Result<usize>,
+    ) -> io::Result<usize> {
+        let c = &*self.metrics.counters;
+        let start = Instant::now();
+
+        if let Some(returned) = *c.returned.lock().unwrap() {
+            add(&c.processing_nanos, nanos(start - returned));
+        }
+
+        let result = wait(&self.inner, events);
+
+        let returned = Instant::now();
+        add(&c.blocked_nanos, nanos(returned - start));
+        *c.returned.lock().unwrap() = Some(returned);
+
+        add(&c.waits, 1);
+
+        match result {
+            Ok(0) => add(&c.timeouts, 1),
+            Ok(n) => {
+                add(&c.wakeups, 1);
+                add(&c.events, n as u64);
+                add(&c.histogram[Histogram::bucket(n)], 1);
+
+                if n == events.capacity() {
+                    add(&c.saturated, 1);
+                }
+            }
+            Err(_) => add(&c.wait_errors, 1),
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::os::unix::net::UnixStream;
+
+    use super::*;
+    use crate::interfaces::Events;
+    use crate::poll::Poll;
+
+    #[test]
+    fn histogram_buckets() {
+        assert_eq!(Histogram::bucket(1), 0);
+        assert_eq!(Histogram::bucket(3), 1);
+        assert_eq!(Histogram::bucket(4), 2);
+        assert_eq!(Histogram::bucket(usize::MAX), HISTOGRAM_BUCKETS - 1);
+
+        assert_eq!(Histogram::bounds(0), (1, 1));
+        assert_eq!(Histogram::bounds(2), (4, 7));
+        assert_eq!(Histogram::bounds(HISTOGRAM_BUCKETS - 1).1, usize::MAX);
+    }
+
+    #[test]
+    fn counts_calls() {
+        let metrics = Metrics::new();
+        let mut poll = Poll::builder().metrics(metrics.clone()).build().unwrap();
+        let mut events = Events::with_capacity(2);
+        let timeout = Some(Duration::from_millis(10));
+
+        let pairs: Vec<_> = (0..3).map(|_| UnixStream::pair().unwrap()).collect();
+
+        for (i, (a, _)) in pairs.iter().enumerate() {
+            poll.registry()
+                .register(a, Token(i), Interest::READABLE)
+                .unwrap();
+        }
+        let (unknown, _peer) = UnixStream::pair().unwrap();
+        poll.registry().deregister(&unknown).unwrap_err();
+
+        // nothing to report yet
+        poll.poll(&mut events, timeout).unwrap();
+
+        for (_, b) in &pairs {
+            (&*b).write_all(b"hello").unwrap();
+        }
+
+        poll.poll(&mut events, timeout).unwrap();
+        poll.poll(&mut events, timeout).unwrap();
+
+        poll.registry()
+            .reregister(&pairs[0].0, Token(0), Interest::WRITABLE)
+            .unwrap();
+        poll.registry().deregister(&pairs[1].0).unwrap();
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.registers, 3);
+        assert_eq!(snapshot.reregisters, 1);
+        assert_eq!(snapshot.deregisters, 2);
+        assert_eq!(snapshot.ctl_errors, 1);
+
+        assert_eq!(snapshot.waits, 3);
+        assert_eq!(snapshot.timeouts, 1);
+        assert_eq!(snapshot.wakeups, 2);
+        assert_eq!(snapshot.saturated, 1);
+        assert_eq!(snapshot.events, 3);
+        assert_eq!(snapshot.events_per_wakeup.buckets[0], 1);
+        assert_eq!(snapshot.events_per_wakeup.buckets[1], 1);
+
+        // the first wait timed out
+        assert!(snapshot.blocked >= Duration::from_millis(10));
+    }
+
+    #[test]
+    fn counts_batched_changes() {
+        let metrics = Metrics::new();
+        let poll = Poll::builder().metrics(metrics.clone()).build().unwrap();
+        let (a, _) = UnixStream::pair().unwrap();
+        let (b, _) = UnixStream::pair().unwrap();
+
+        poll.registry()
+            .batch()
+            .register(&a, Token(0), Interest::READABLE)
+            .register(&b, Token(1), Interest::READABLE)
+            .apply()
+            .unwrap();
+        poll.registry()
+            .batch()
+            .reregister(&a, Token(0), Interest::WRITABLE)
+            .deregister(&b)
+            .apply()
+            .unwrap();
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.registers, 2);
+        assert_eq!(snapshot.reregisters, 1);
+        assert_eq!(snapshot.deregisters, 1);
+        assert_eq!(snapshot.ctl_errors, 0);
+    }
+}
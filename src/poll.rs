@@ -1,14 +1,23 @@
 #![allow(unused)]
 
 use std::{
-    io::{self, Result},
+    collections::HashMap,
+    fmt, io,
+    mem::ManuallyDrop,
     net::TcpStream,
-    os::fd::AsRawFd,
+    ops::ControlFlow,
+    os::fd::{AsRawFd, RawFd},
+    str::FromStr,
+    sync::Mutex,
     time::Duration,
 };
 
-use crate::interests::Interest;
-use crate::interfaces::{Event, Events, SysSelector, Token};
+use crate::error::{BatchError, Context, Error, Operation, Result};
+use crate::interests::{Interest, Trigger};
+use crate::interfaces::{
+    Change, Event, EventBuffer, Events, Sealed, SelectorOptions, SysEvent, SysSelector, Token,
+};
+use crate::signal::SigSet;
 use crate::sys::selectors::Selector;
 
 pub trait Source: AsRawFd {}
@@ -18,13 +27,73 @@ impl<T> Source for T where T: AsRawFd {}
 pub struct Poll {
     /// A Registry is specific to an event queue / Poll instance
     registery: Registry,
+
+    /// Options the queue was built with, including the number of events callers are
+    /// expected to retrieve per poll.
+    options: SelectorOptions,
+
+    /// Process the queue was created, or last reinitialised, in.
+    pid: u32,
+
+    /// Buffer reused by `poll_with`, allocated on first use.
+    events: Events,
+
+    /// Index of the first event in `events` not yet handed to a `poll_with` callback.
+    next: usize,
 }
 
 impl Poll {
+    /// Create an event queue using the default backend and options.
     pub fn new() -> Result<Self> {
-        Ok(Self {
-            registery: Registry::new()?,
-        })
+        Self::builder().build()
+    }
+
+    /// Configure the event queue before creating it.
+    pub fn builder() -> PollBuilder {
+        PollBuilder {
+            options: SelectorOptions::default(),
+            #[cfg(feature = "testing")]
+            mock: false,
+            #[cfg(feature = "testing")]
+            faults: None,
+            #[cfg(feature = "record")]
+            recorder: None,
+            #[cfg(feature = "record")]
+            session: None,
+            #[cfg(feature = "metrics")]
+            metrics: None,
+        }
+    }
+
+    /// The backend in use, which might differ from the one requested if it was not
+    /// available at runtime.
+    pub fn backend(&self) -> Backend {
+        self.registery.selector.backend()
+    }
+
+    /// File descriptor of the event queue, or `None` for the poll(2) backend, and others
+    /// without a kernel side queue.
+    ///
+    /// The file descriptor becomes readable when events are ready, so the queue can be
+    /// registered in another `Poll`, or in a foreign event loop, as any other source.
+    pub fn raw_fd(&self) -> Option<RawFd> {
+        self.registery.selector.raw_fd()
+    }
+
+    /// Event capacity hint this queue was built with.
+    pub fn capacity(&self) -> usize {
+        self.options.capacity
+    }
+
+    /// Create an `Events` buffer sized by the capacity hint.
+    pub fn events(&self) -> Events {
+        Events::with_capacity(self.options.capacity)
+    }
+
+    /// Handle for scripting the event queue, if it was built via `PollBuilder::mock`.
+    #[cfg(feature = "testing")]
+    pub fn mock(&self) -> Option<crate::testing::Mock> {
+        self.registery.selector.mock()
     }
 
     /// return reference to the registry that can be used for registering
@@ -34,18 +103,152 @@ impl Poll {
     }
 
     /// Blocks / parks the current thread it's called on until an event is ready or timeout occurs.
-    pub fn poll(&mut self, events: &mut Events, timeout: Option<Duration>) -> Result<()> {
-        // REVIEW: confirm casting of &mut Events -> &mut SysSelector::OsEvents
-        self.registery.selector.poll(events, timeout)?;
+    ///
+    /// `Events::is_saturated` reports whether the buffer was filled, in which case more
+    /// events might be ready. An `ArrayEvents` polls without allocating.
+    pub fn poll(&mut self, events: &mut impl EventBuffer, timeout: Option<Duration>) -> Result<()> {
+        poll_into(&self.registery, events, timeout, None)
+    }
+
+    /// Poll into a buffer owned by the queue, calling `f` with each ready event until it
+    /// returns `ControlFlow::Break`, and return the number of events `f` was called with.
+    ///
+    /// The buffer holds as many events as the capacity hint, and is reused by every call.
+    /// Events left after a break are handed to `f` by the next call, which returns them
+    /// without polling, so that none are lost in edge-triggered mode. `timeout` is then
+    /// ignored. Those of sources deregistered in between are dropped, and the queue is
+    /// polled if none are left.
+    pub fn poll_with<F>(&mut self, timeout: Option<Duration>, mut f: F) -> Result<usize>
+    where
+        F: FnMut(&Event) -> ControlFlow<()>,
+    {
+        if self.next < self.events.len() {
+            let registry = &self.registery;
+
+            self.events.drain(..self.next);
+            self.events.retain(|event| registry.is_registered(event));
+            self.next = 0;
+        }
+
+        if self.next == self.events.len() {
+            if self.events.capacity() == 0 {
+                self.events = Events::with_capacity(self.options.capacity);
+            }
+
+            self.next = 0;
+            poll_into(&self.registery, &mut self.events, timeout, None)?;
+        }
+
+        let start = self.next;
+
+        while let Some(event) = self.events.get(self.next) {
+            self.next += 1;
+
+            if f(Event::ref_from_sys_event(event)).is_break() {
+                break;
+            }
+        }
+
+        Ok(self.next - start)
+    }
+
+    /// Whether this is a child process forked since the queue was created.
+    ///
+    /// The child shares the kernel side queue with its parent, so that registrations made
+    /// by either affect both. Call `reinit_after_fork` before using the queue in the child.
+    pub fn is_forked(&self) -> bool {
+        self.pid != std::process::id()
+    }
+
+    /// Replace the queue inherited from the parent process by a fresh one, with every
+    /// registration tracked by the `Registry` made again.
+    ///
+    /// Registrations of file descriptors that have since been closed are dropped, as are
+    /// events left over by `poll_with`, which were reported to the parent. Only numbers
+    /// that are free count as closed: one closed without being deregistered, then reused
+    /// by e.g. `open` or `accept`, is registered again with the token and interests of the
+    /// source it replaced. Deregister sources before closing them. Wrappers
+    /// installed via the builder, such as `metrics`, are kept. Fails with `Unsupported` on
+    /// a queue replaying a recorded session.
+    pub fn reinit_after_fork(&mut self) -> Result<()> {
+        let registrations = self.registery.registrations.get_mut().unwrap();
+
+        // Checked up front, as io_uring and poll(2) accept any file descriptor number and
+        // only report a closed one once polled, and before reopening, which could reuse
+        // the number of a closed one.
+        registrations.retain(|fd| !is_closed(fd));
+
+        let selector = self
+            .registery
+            .selector
+            .reopen(self.options)
+            .map_err(open_error)?;
+
+        for (
+            &fd,
+            &Entry {
+                token, interests, ..
+            },
+        ) in &registrations.fds
+        {
+            selector.register(fd, token, interests).map_err(|e| {
+                let context = Context::new(Operation::Register).fd(fd).token(token);
+                Error::new(context, e)
+            })?;
+        }
+
+        self.registery.selector = selector;
+        self.pid = std::process::id();
+        self.events.clear();
+        self.next = 0;
 
         Ok(())
     }
+
+    /// Same as `poll`, but with the calling thread's signal mask replaced by `sigmask`
+    /// while blocked, atomically, via `epoll_pwait`, `ppoll` or `io_uring_enter`.
+    ///
+    /// Keep a signal blocked outside of the wait, and leave it out of `sigmask`, so that
+    /// it can only be delivered while blocked here, interrupting the wait with
+    /// `Error::Interrupted`. See the `signal` module for an example. Fails with an
+    /// `Unsupported` error on kqueue, which cannot swap the mask.
+    pub fn poll_with_sigmask(
+        &mut self,
+        events: &mut impl EventBuffer,
+        timeout: Option<Duration>,
+        sigmask: &SigSet,
+    ) -> Result<()> {
+        poll_into(&self.registery, events, timeout, Some(sigmask))
+    }
+}
+
+/// Error creating or reopening the event queue. Unlike the errors of calls on a source,
+/// there is no errno worth a variant of its own.
+fn open_error(err: io::Error) -> Error {
+    Error::Io(Context::new(Operation::Open), err)
+}
+
+fn is_closed(fd: RawFd) -> bool {
+    let ret = unsafe { libc::fcntl(fd, libc::F_GETFD) };
+
+    ret < 0 && io::Error::last_os_error().raw_os_error() == Some(libc::EBADF)
+}
+
+fn poll_fd(poll: &Poll, context: Context) -> Result<RawFd> {
+    poll.raw_fd().ok_or_else(|| {
+        let msg = format!("{} backend has no file descriptor", poll.backend());
+
+        Error::Io(context, io::Error::new(io::ErrorKind::Unsupported, msg))
+    })
 }
 
 /// Wraps OS specific selector that manages all syscalls
 /// to the OSes event queue abstraction.
 pub struct Registry {
     selector: Selector,
+
+    /// Token and interests of every file descriptor registered via this registry.
+    registrations: Mutex<Registrations>,
 }
 
 impl Registry {
@@ -53,17 +256,885 @@ impl Registry {
     ///
     /// - `interests`: The types of events we want to be notified about.
     /// - `token`: user supplied identifier to keep track of the source.
+    ///
+    /// Fails with `Error::AlreadyRegistered` if the file descriptor is registered via this
+    /// registry already. That includes a source closed without being deregistered, whose
+    /// file descriptor number was then reused: deregister it first.
     pub fn register<S: Source>(&self, source: &S, token: Token, interests: Interest) -> Result<()> {
-        self.selector.register(source.as_raw_fd(), token, interests)
+        self.register_fd(source.as_raw_fd(), token, interests)
+            .map(drop)
+    }
+
+    /// Register a source, returning a guard that deregisters it when dropped.
+    ///
+    /// The guard borrows the source, so that the source cannot be closed while the guard
+    /// is alive, and its file descriptor number cannot be reused by a source that the guard
+    /// would then deregister. The guard only ever acts on its own registration: once the
+    /// source is deregistered via the `Registry` directly, and maybe registered again,
+    /// the guard leaves it alone.
+    ///
+    /// ```compile_fail
+    /// # use mini_mio::{interests::Interest, interfaces::Token, poll::Poll};
+    /// # let poll = Poll::new().unwrap();
+    /// let (a, _b) = std::os::unix::net::UnixStream::pair().unwrap();
+    /// let guard = poll.registry().register_guarded(&a, Token(0), Interest::READABLE).unwrap();
+    ///
+    /// drop(a); // borrowed by the guard
+    /// drop(guard);
+    /// ```
+    pub fn register_guarded<'a, S: Source>(
+        &'a self,
+        source: &'a S,
+        token: Token,
+        interests: Interest,
+    ) -> Result<Registration<'a, S>> {
+        let id = self.register_fd(source.as_raw_fd(), token, interests)?;
+
+        Ok(Registration {
+            registry: self,
+            source,
+            id,
+        })
+    }
+
+    /// Modify the token or interests of an already registered source.
+    ///
+    /// No syscall is made if the source is already registered with the same token and
+    /// interests, the source is only rearmed, as `rearm` would. Fails with `NotRegistered`
+    /// if the source was not registered via this registry. The registry has no way of
+    /// knowing about a source that was closed without being deregistered, so reregistering
+    /// one then succeeds, rather than failing with `NotRegistered`.
+    pub fn reregister<S: Source>(
+        &self,
+        source: &S,
+        token: Token,
+        interests: Interest,
+    ) -> Result<()> {
+        self.reregister_fd(source.as_raw_fd(), token, interests)
+    }
+
+    /// Re-enable notifications for `interests` once the source has been drained.
+    ///
+    /// Should be called after an operation on the source returned `WouldBlock`. This is a
+    /// no-op on epoll and kqueue. The poll(2) backend emulates edge-triggered mode by
+    /// disarming a source once it has been reported, until a poll finds it drained, which
+    /// misses readiness returning in between, unless the source was rearmed.
+    pub fn rearm<S: Source>(&self, source: &S, interests: Interest) -> Result<()> {
+        let fd = source.as_raw_fd();
+
+        self.selector
+            .rearm(fd, interests)
+            .map_err(|e| Error::new(Context::new(Operation::Rearm).fd(fd), e))
+    }
+
+    /// Stop monitoring a source for events.
+    pub fn deregister<S: Source>(&self, source: &S) -> Result<()> {
+        self.deregister_fd(source.as_raw_fd())
+    }
+
+    /// Register another queue, readable when it has events ready, so that queues can be
+    /// nested, e.g. to poll a queue of control-plane sockets ahead of a data-plane one.
+    ///
+    /// Once reported ready, the inner queue must be polled until it returns fewer events
+    /// than its capacity, as an edge-triggered registration is not reported again until
+    /// new events arrive.
+    ///
+    /// Fails with an `Unsupported` error if the inner queue has no file descriptor, see
+    /// `Poll::raw_fd`.
+    pub fn register_poll(&self, poll: &Poll, token: Token, interests: Interest) -> Result<()> {
+        let context = Context::new(Operation::Register).token(token);
+
+        self.register_fd(poll_fd(poll, context)?, token, interests)
+            .map(drop)
+    }
+
+    /// Stop monitoring a queue registered via `register_poll`.
+    pub fn deregister_poll(&self, poll: &Poll) -> Result<()> {
+        self.deregister_fd(poll_fd(poll, Context::new(Operation::Deregister))?)
+    }
+
+    /// Collect registration changes to apply together, with fewer syscalls where the
+    /// backend allows it.
+    pub fn batch(&self) -> Batch<'_> {
+        Batch {
+            registry: self,
+            changes: Vec::new(),
+        }
+    }
+
+    /// Token and interests the source was registered with via this registry.
+    ///
+    /// A source that was closed without being deregistered is still reported, as the
+    /// registry is not told about it.
+    pub fn registered<S: Source>(&self, source: &S) -> Option<(Token, Interest)> {
+        self.registered_fd(source.as_raw_fd())
+    }
+
+    fn new(selector: Selector) -> Self {
+        Registry {
+            selector,
+            registrations: Mutex::default(),
+        }
+    }
+
+    /// Add `interest` to those the source is registered with.
+    pub fn add_interest<S: Source>(&self, source: &S, interest: Interest) -> Result<()> {
+        self.update_interests(source.as_raw_fd(), |interests| {
+            Some(interests.add_interest(interest))
+        })
+    }
+
+    /// Remove `interest` from those the source is registered with.
+    ///
+    /// Fails with an `InvalidInput` error if no interests would be left, deregister the
+    /// source instead.
+    pub fn remove_interest<S: Source>(&self, source: &S, interest: Interest) -> Result<()> {
+        self.update_interests(source.as_raw_fd(), |interests| {
+            interests.remove_interest(interest)
+        })
+    }
+
+    /// Reregister with the interests `update` derives from the registered ones, with the
+    /// table locked from the lookup to the update so that no concurrent change is lost.
+    fn update_interests(
+        &self,
+        fd: RawFd,
+        update: impl FnOnce(Interest) -> Option<Interest>,
+    ) -> Result<()> {
+        let mut registrations = self.registrations.lock().unwrap();
+        let (token, interests) = registered_or_err(&registrations, fd, Operation::Reregister)?;
+
+        match update(interests) {
+            Some(interests) => self.reregister_locked(&mut registrations, fd, token, interests),
+            None => Err(Error::Io(
+                Context::new(Operation::Reregister).fd(fd).token(token),
+                io::Error::new(io::ErrorKind::InvalidInput, "no interests left"),
+            )),
+        }
+    }
+
+    fn reregister_fd(&self, fd: RawFd, token: Token, interests: Interest) -> Result<()> {
+        let mut registrations = self.registrations.lock().unwrap();
+        self.reregister_locked(&mut registrations, fd, token, interests)
+    }
+
+    /// Reregister with the table locked from the lookup to the update, so that concurrent
+    /// changes to the same file descriptor cannot leave it out of step with the kernel.
+    fn reregister_locked(
+        &self,
+        registrations: &mut Registrations,
+        fd: RawFd,
+        token: Token,
+        interests: Interest,
+    ) -> Result<()> {
+        let context = Context::new(Operation::Reregister).fd(fd).token(token);
+
+        match registrations.get(fd) {
+            // checked here rather than left to the kernel, as kqueue's EV_ADD would add it
+            None => return Err(Error::NotRegistered(context)),
+            // nothing to change, but backends emulating edge-triggered mode rearm the source
+            Some(registered) if registered == (token, interests) => {
+                return self
+                    .selector
+                    .rearm(fd, interests)
+                    .map_err(|e| Error::new(context, e));
+            }
+            Some(_) => {}
+        }
+
+        self.selector
+            .reregister(fd, token, interests)
+            .map_err(|e| Error::new(context, e))?;
+        registrations.update(fd, token, interests);
+
+        Ok(())
+    }
+
+    fn deregister_fd(&self, fd: RawFd) -> Result<()> {
+        let mut registrations = self.registrations.lock().unwrap();
+        self.deregister_locked(&mut registrations, fd)
+    }
+
+    fn deregister_locked(&self, registrations: &mut Registrations, fd: RawFd) -> Result<()> {
+        let context = Context::new(Operation::Deregister).fd(fd);
+
+        match self.selector.deregister(fd) {
+            Ok(()) => {
+                registrations.remove(fd);
+                Ok(())
+            }
+            Err(e) => {
+                let err = Error::new(context, e);
+
+                // the kernel has no such registration either
+                if let Error::NotRegistered(_) = err {
+                    registrations.remove(fd);
+                }
+
+                Err(err)
+            }
+        }
+    }
+
+    /// Register the file descriptor, returning the id of the new registration.
+    fn register_fd(&self, fd: RawFd, token: Token, interests: Interest) -> Result<u64> {
+        let context = Context::new(Operation::Register).fd(fd).token(token);
+
+        // Checked here rather than left to the kernel, as kqueue's EV_ADD updates an
+        // existing registration instead of failing. Held until tracked, so that a
+        // concurrent register of the same file descriptor cannot slip in between.
+        let mut registrations = self.registrations.lock().unwrap();
+
+        if let Some((existing, _)) = registrations.get(fd) {
+            return Err(Error::AlreadyRegistered(context.existing(existing)));
+        }
+
+        self.selector
+            .register(fd, token, interests)
+            .map_err(|e| Error::new(context, e))?;
+
+        Ok(registrations.insert(fd, token, interests))
+    }
+
+    /// Whether the source `event` was reported for is still registered, as far as the
+    /// event tells: by token only on epoll, which does not report the file descriptor.
+    fn is_registered(&self, event: &crate::sys::OsEvent) -> bool {
+        let registrations = self.registrations.lock().unwrap();
+        let token = event.token();
+
+        match event.fd() {
+            Some(fd) => registrations.get(fd).is_some_and(|(t, _)| t == token),
+            None => registrations.has_token(token),
+        }
+    }
+
+    fn registered_fd(&self, fd: RawFd) -> Option<(Token, Interest)> {
+        self.registrations.lock().unwrap().get(fd)
+    }
+}
+
+/// The registration of `fd`, or a `NotRegistered` error for `op` if there is none.
+fn registered_or_err(
+    registrations: &Registrations,
+    fd: RawFd,
+    op: Operation,
+) -> Result<(Token, Interest)> {
+    registrations
+        .get(fd)
+        .ok_or_else(|| Error::NotRegistered(Context::new(op).fd(fd)))
+}
+
+/// Registrations made via a `Registry`, by file descriptor.
+#[derive(Default)]
+struct Registrations {
+    fds: HashMap<RawFd, Entry>,
+    /// Number of registrations per token, so that events not telling their file
+    /// descriptor can be checked without going through every registration.
+    tokens: HashMap<Token, usize>,
+    /// Id of the next registration.
+    next_id: u64,
+}
+
+#[derive(Clone, Copy)]
+struct Entry {
+    token: Token,
+    interests: Interest,
+    /// Tells a registration apart from a later one of the same file descriptor, which
+    /// reregistrations keep.
+    id: u64,
+}
+
+impl Registrations {
+    fn get(&self, fd: RawFd) -> Option<(Token, Interest)> {
+        self.fds
+            .get(&fd)
+            .map(|entry| (entry.token, entry.interests))
+    }
+
+    /// Id of the registration of `fd`.
+    fn id(&self, fd: RawFd) -> Option<u64> {
+        self.fds.get(&fd).map(|entry| entry.id)
+    }
+
+    /// Track a new registration, returning its id.
+    fn insert(&mut self, fd: RawFd, token: Token, interests: Interest) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let entry = Entry {
+            token,
+            interests,
+            id,
+        };
+        if let Some(replaced) = self.fds.insert(fd, entry) {
+            untrack(&mut self.tokens, replaced.token);
+        }
+        *self.tokens.entry(token).or_default() += 1;
+
+        id
+    }
+
+    fn update(&mut self, fd: RawFd, token: Token, interests: Interest) {
+        if let Some(entry) = self.fds.get_mut(&fd) {
+            if entry.token != token {
+                untrack(&mut self.tokens, entry.token);
+                *self.tokens.entry(token).or_default() += 1;
+            }
+
+            entry.token = token;
+            entry.interests = interests;
+        }
+    }
+
+    fn remove(&mut self, fd: RawFd) {
+        if let Some(entry) = self.fds.remove(&fd) {
+            untrack(&mut self.tokens, entry.token);
+        }
+    }
+
+    /// Keep only the registrations of the file descriptors `f` returns true for.
+    fn retain(&mut self, mut f: impl FnMut(RawFd) -> bool) {
+        let tokens = &mut self.tokens;
+
+        self.fds.retain(|&fd, entry| {
+            let keep = f(fd);
+            if !keep {
+                untrack(tokens, entry.token);
+            }
+            keep
+        });
+    }
+
+    /// Whether any file descriptor is registered with `token`.
+    fn has_token(&self, token: Token) -> bool {
+        self.tokens.contains_key(&token)
+    }
+}
+
+fn untrack(tokens: &mut HashMap<Token, usize>, token: Token) {
+    if let Some(count) = tokens.get_mut(&token) {
+        *count -= 1;
+        if *count == 0 {
+            tokens.remove(&token);
+        }
+    }
+}
+
+/// Registration changes applied together, returned by `Registry::batch`.
+///
+/// kqueue submits the whole batch in a single `kevent` call. The other backends make one
+/// call per change, without stopping at the first error. Either way, every change is
+/// attempted and the error of each is reported.
+pub struct Batch<'a> {
+    registry: &'a Registry,
+    changes: Vec<Change>,
+}
+
+impl Batch<'_> {
+    pub fn register<S: Source>(
+        &mut self,
+        source: &S,
+        token: Token,
+        interests: Interest,
+    ) -> &mut Self {
+        self.changes.push(Change::Register {
+            fd: source.as_raw_fd(),
+            token,
+            interests,
+        });
+        self
+    }
+
+    pub fn reregister<S: Source>(
+        &mut self,
+        source: &S,
+        token: Token,
+        interests: Interest,
+    ) -> &mut Self {
+        self.changes.push(Change::Reregister {
+            fd: source.as_raw_fd(),
+            token,
+            interests,
+        });
+        self
+    }
+
+    pub fn deregister<S: Source>(&mut self, source: &S) -> &mut Self {
+        self.changes.push(Change::Deregister {
+            fd: source.as_raw_fd(),
+        });
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Apply every change collected so far, in order, leaving the batch empty.
+    ///
+    /// Registering a source that is registered already, and reregistering one that is
+    /// not, fail as they would outside of a batch, without being submitted.
+    pub fn apply(&mut self) -> std::result::Result<(), BatchError> {
+        let changes = std::mem::take(&mut self.changes);
+
+        // Checked here rather than left to the kernel, as kqueue's EV_ADD succeeds either
+        // way. Held until tracked, as for a single register.
+        let mut registrations = self.registry.registrations.lock().unwrap();
+        let rejected = reject(&changes, &registrations);
+
+        let submitted: Vec<Change> = changes
+            .iter()
+            .zip(&rejected)
+            .filter(|(_, rejected)| rejected.is_none())
+            .map(|(change, _)| *change)
+            .collect();
+        let mut results = self.registry.selector.apply(&submitted).into_iter();
+        let mut errors = Vec::new();
+
+        for (i, (change, rejected)) in changes.iter().zip(rejected).enumerate() {
+            let result = match rejected {
+                Some(err) => Err(err),
+                None => results
+                    .next()
+                    .unwrap()
+                    .map_err(|e| Error::new(context(change), e)),
+            };
+
+            match *change {
+                Change::Register {
+                    fd,
+                    token,
+                    interests,
+                } => {
+                    if result.is_ok() {
+                        registrations.insert(fd, token, interests);
+                    }
+                }
+                Change::Reregister {
+                    fd,
+                    token,
+                    interests,
+                } => {
+                    if result.is_ok() {
+                        registrations.update(fd, token, interests);
+                    }
+                }
+                // the kernel has no such registration either, if it is not registered
+                Change::Deregister { fd } => {
+                    if matches!(result, Ok(()) | Err(Error::NotRegistered(_))) {
+                        registrations.remove(fd);
+                    }
+                }
+            }
+
+            if let Err(err) = result {
+                errors.push((i, err));
+            }
+        }
+
+        if errors.is_empty() {
+            return Ok(());
+        }
+
+        Err(BatchError {
+            total: changes.len(),
+            errors,
+        })
+    }
+}
+
+/// Error of each change that is not to be submitted, given the registrations the changes
+/// before it leave behind, assuming those submitted succeed.
+fn reject(changes: &[Change], registrations: &Registrations) -> Vec<Option<Error>> {
+    let mut tokens: HashMap<RawFd, Option<Token>> = HashMap::new();
+
+    changes
+        .iter()
+        .map(|change| {
+            let fd = change.fd();
+            let token = tokens
+                .entry(fd)
+                .or_insert_with(|| registrations.get(fd).map(|(token, _)| token));
+
+            let err = match (*change, *token) {
+                (Change::Register { .. }, Some(existing)) => {
+                    Error::AlreadyRegistered(context(change).existing(existing))
+                }
+                (Change::Reregister { .. }, None) => Error::NotRegistered(context(change)),
+                (
+                    Change::Register { token: new, .. } | Change::Reregister { token: new, .. },
+                    _,
+                ) => {
+                    *token = Some(new);
+                    return None;
+                }
+                (Change::Deregister { .. }, _) => {
+                    *token = None;
+                    return None;
+                }
+            };
+
+            Some(err)
+        })
+        .collect()
+}
+
+/// What a change of a batch was made on.
+fn context(change: &Change) -> Context {
+    match *change {
+        Change::Register { fd, token, .. } => Context::new(Operation::Register).fd(fd).token(token),
+        Change::Reregister { fd, token, .. } => {
+            Context::new(Operation::Reregister).fd(fd).token(token)
+        }
+        Change::Deregister { fd } => Context::new(Operation::Deregister).fd(fd),
+    }
+}
+
+/// Poll the selector of `registry` into `events`, replacing the events it holds, with the
+/// signal mask replaced by `sigmask` while blocked, if given.
+fn poll_into(
+    registry: &Registry,
+    events: &mut impl EventBuffer,
+    timeout: Option<Duration>,
+    sigmask: Option<&SigSet>,
+) -> Result<()> {
+    events.retain_deferred(|event| registry.is_registered(event));
+    let timeout = events.before_poll(timeout);
+
+    match sigmask {
+        Some(sigmask) => events.poll_from_with_sigmask(&registry.selector, timeout, sigmask),
+        None => events.poll_from(&registry.selector, timeout),
+    }
+    .map_err(|e| poll_error(events.capacity(), e))?;
+
+    events.after_poll();
+
+    Ok(())
+}
+
+/// Classify the error of a call to poll, made with a buffer of `capacity` events.
+fn poll_error(capacity: usize, err: io::Error) -> Error {
+    let context = Context::new(Operation::Poll);
+
+    // same error epoll_wait returns for a maxevents of zero
+    if capacity == 0 && err.raw_os_error() == Some(libc::EINVAL) {
+        return Error::InvalidCapacity(context);
+    }
+
+    Error::new(context, err)
+}
+
+/// A registered source, deregistered when dropped.
+///
+/// Returned by `Registry::register_guarded`. Errors from the implicit deregistration are
+/// ignored, call `deregister` to observe them.
+#[must_use = "the source is deregistered as soon as the guard is dropped"]
+pub struct Registration<'a, S: Source> {
+    registry: &'a Registry,
+    source: &'a S,
+    /// Id of the registration made by `register_guarded`.
+    id: u64,
+}
+
+impl<S: Source> Registration<'_, S> {
+    pub fn fd(&self) -> RawFd {
+        self.source.as_raw_fd()
+    }
+
+    pub fn source(&self) -> &S {
+        self.source
+    }
+
+    /// Token and interests the source is currently registered with, or `None` if it was
+    /// deregistered via the `Registry` directly.
+    pub fn get(&self) -> Option<(Token, Interest)> {
+        let registrations = self.registry.registrations.lock().unwrap();
+
+        self.is_current(&registrations)
+            .then(|| registrations.get(self.fd()))
+            .flatten()
+    }
+
+    /// Modify the token or interests of the source, failing with `Error::NotRegistered` if
+    /// it was deregistered via the `Registry` directly.
+    pub fn reregister(&self, token: Token, interests: Interest) -> Result<()> {
+        let mut registrations = self.registry.registrations.lock().unwrap();
+
+        if !self.is_current(&registrations) {
+            let context = Context::new(Operation::Reregister)
+                .fd(self.fd())
+                .token(token);
+            return Err(Error::NotRegistered(context));
+        }
+
+        self.registry
+            .reregister_locked(&mut registrations, self.fd(), token, interests)
+    }
+
+    /// Deregister the source now, failing with `Error::NotRegistered` if it was
+    /// deregistered via the `Registry` directly.
+    pub fn deregister(self) -> Result<()> {
+        let this = ManuallyDrop::new(self);
+        let mut registrations = this.registry.registrations.lock().unwrap();
+
+        if !this.is_current(&registrations) {
+            let context = Context::new(Operation::Deregister).fd(this.fd());
+            return Err(Error::NotRegistered(context));
+        }
+
+        this.registry
+            .deregister_locked(&mut registrations, this.fd())
+    }
+
+    /// Whether the file descriptor is still registered by this guard.
+    fn is_current(&self, registrations: &Registrations) -> bool {
+        registrations.id(self.fd()) == Some(self.id)
+    }
+}
+
+impl fmt::Debug for Batch<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Batch")
+            .field("changes", &self.changes)
+            .finish()
+    }
+}
+
+impl<S: Source> fmt::Debug for Registration<'_, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Registration")
+            .field("fd", &self.fd())
+            .finish()
+    }
+}
+
+impl<S: Source> Drop for Registration<'_, S> {
+    fn drop(&mut self) {
+        let mut registrations = self.registry.registrations.lock().unwrap();
+
+        if self.is_current(&registrations) {
+            let _ = self
+                .registry
+                .deregister_locked(&mut registrations, self.fd());
+        }
+    }
+}
+
+/// Builder for a `Poll` instance, returned by `Poll::builder`.
+#[derive(Debug)]
+pub struct PollBuilder {
+    options: SelectorOptions,
+
+    #[cfg(feature = "testing")]
+    mock: bool,
+
+    #[cfg(feature = "testing")]
+    faults: Option<crate::testing::Faults>,
+
+    #[cfg(feature = "record")]
+    recorder: Option<crate::record::Recorder>,
+
+    #[cfg(feature = "record")]
+    session: Option<crate::record::Session>,
+
+    #[cfg(feature = "metrics")]
+    metrics: Option<crate::metrics::Metrics>,
+}
+
+impl PollBuilder {
+    /// Select the backend, rather than using the platform default.
+    ///
+    /// If io_uring is requested but not available at runtime, the default backend is used
+    /// instead. `Poll::backend` reports the backend actually in use. The mock and replay
+    /// selectors are built via `mock` and `replay` instead, and fail with `InvalidInput`.
+    pub fn backend(mut self, backend: Backend) -> Self {
+        self.options.backend = Some(backend);
+        self
+    }
+
+    /// Set close-on-exec on the event queue file descriptor. Enabled by default.
+    pub fn cloexec(mut self, cloexec: bool) -> Self {
+        self.options.cloexec = cloexec;
+        self
     }
 
-    fn new() -> Result<Self> {
-        Ok(Registry {
-            selector: Selector::new()?,
+    /// Trigger mode used for every registration. Defaults to edge-triggered.
+    pub fn trigger(mut self, trigger: Trigger) -> Self {
+        self.options.trigger = trigger;
+        self
+    }
+
+    /// Number of events expected per poll. Used by `Poll::events`, and to size kernel
+    /// side structures by backends that need it.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.options.capacity = capacity;
+        self
+    }
+
+    /// Fail the calls to the selector configured via `faults`, whichever the backend.
+    #[cfg(feature = "testing")]
+    pub fn faults(mut self, faults: crate::testing::Faults) -> Self {
+        self.faults = Some(faults);
+        self
+    }
+
+    /// Record every call to the selector, and every polled event, via `recorder`.
+    #[cfg(feature = "record")]
+    pub fn record(mut self, recorder: crate::record::Recorder) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
+
+    /// Use the scriptable mock selector rather than a real backend, see `Poll::mock`.
+    #[cfg(feature = "testing")]
+    pub fn mock(mut self) -> Self {
+        self.mock = true;
+        self
+    }
+
+    /// Report the events of a recorded session, rather than those of a real backend.
+    #[cfg(feature = "record")]
+    pub fn replay(mut self, session: crate::record::Session) -> Self {
+        self.session = Some(session);
+        self
+    }
+
+    /// Count every call to the selector in `metrics`.
+    #[cfg(feature = "metrics")]
+    pub fn metrics(mut self, metrics: crate::metrics::Metrics) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    pub fn build(self) -> Result<Poll> {
+        let selector = match () {
+            #[cfg(feature = "record")]
+            _ if self.session.is_some() => Selector::replay(self.session.unwrap()),
+
+            #[cfg(feature = "testing")]
+            _ if self.mock => Selector::mocked(self.options).map_err(open_error)?,
+
+            _ => Selector::with_options(self.options).map_err(open_error)?,
+        };
+
+        #[cfg(feature = "testing")]
+        let selector = match self.faults {
+            Some(faults) => Selector::faulty(selector, faults),
+            None => selector,
+        };
+
+        #[cfg(feature = "metrics")]
+        let selector = match self.metrics {
+            Some(metrics) => Selector::metered(selector, metrics),
+            None => selector,
+        };
+
+        // outermost, so that injected faults are recorded too
+        #[cfg(feature = "record")]
+        let selector = match self.recorder {
+            Some(recorder) => Selector::recording(selector, recorder),
+            None => selector,
+        };
+
+        Ok(Poll {
+            registery: Registry::new(selector),
+            options: self.options,
+            pid: std::process::id(),
+            events: Events::with_capacity(0),
+            next: 0,
         })
     }
 }
 
+/// The OS facility used to wait for events.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Backend {
+    #[cfg(target_os = "linux")]
+    Epoll,
+
+    /// poll(2), with edge-triggered mode emulated in userspace.
+    #[cfg(target_os = "linux")]
+    Poll,
+
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    IoUring,
+
+    #[cfg(target_os = "macos")]
+    Kqueue,
+
+    /// Scripted via `Poll::mock`, for unit tests.
+    #[cfg(feature = "testing")]
+    Mock,
+
+    /// Reports the events of a session recorded earlier, see `PollBuilder::replay`.
+    #[cfg(feature = "record")]
+    Replay,
+}
+
+impl Backend {
+    /// Every OS backend compiled into this build, which can be passed to
+    /// `PollBuilder::backend` or parsed from its name. The mock and replay selectors are
+    /// left out, as they are only built via `PollBuilder::mock` and `PollBuilder::replay`.
+    pub const ALL: &'static [Backend] = &[
+        #[cfg(target_os = "linux")]
+        Backend::Epoll,
+        #[cfg(target_os = "linux")]
+        Backend::Poll,
+        #[cfg(all(target_os = "linux", feature = "io-uring"))]
+        Backend::IoUring,
+        #[cfg(target_os = "macos")]
+        Backend::Kqueue,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            #[cfg(target_os = "linux")]
+            Backend::Epoll => "epoll",
+            #[cfg(target_os = "linux")]
+            Backend::Poll => "poll",
+            #[cfg(all(target_os = "linux", feature = "io-uring"))]
+            Backend::IoUring => "io_uring",
+            #[cfg(target_os = "macos")]
+            Backend::Kqueue => "kqueue",
+            #[cfg(feature = "testing")]
+            Backend::Mock => "mock",
+            #[cfg(feature = "record")]
+            Backend::Replay => "replay",
+        }
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Parse a backend from its name, e.g. when read from a configuration file.
+impl FromStr for Backend {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> io::Result<Self> {
+        let name = s.trim().to_ascii_lowercase().replace('-', "_");
+
+        Backend::ALL
+            .iter()
+            .find(|backend| backend.name() == name)
+            .copied()
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("unknown or unsupported backend: {s}"),
+                )
+            })
+    }
+}
+
 // NOTE: `Selector` uses `OwnedFd`, which will close the file descriptor when dropped.
 // So we can skip implementing `Drop` trait for now.
 //
@@ -75,6 +1146,984 @@ impl Registry {
 
 #[cfg(test)]
 mod tests {
+    use std::io::{Read, Write};
+    use std::os::unix::net::UnixStream;
 
     use super::*;
+    use crate::interfaces::SysEvent;
+
+    const TIMEOUT: Option<Duration> = Some(Duration::from_millis(50));
+
+    fn pair() -> (UnixStream, UnixStream) {
+        let (a, b) = UnixStream::pair().unwrap();
+        a.set_nonblocking(true).unwrap();
+        b.set_nonblocking(true).unwrap();
+        (a, b)
+    }
+
+    /// Every backend compiled in that is backed by the OS.
+    fn os_backends() -> impl Iterator<Item = Backend> {
+        Backend::ALL.iter().copied()
+    }
+
+    /// One event queue per backend compiled in.
+    fn polls() -> impl Iterator<Item = Poll> {
+        os_backends().map(|backend| Poll::builder().backend(backend).build().unwrap())
+    }
+
+    fn drain(mut stream: &UnixStream) {
+        let mut buf = [0u8; 64];
+        while stream.read(&mut buf).is_ok() {}
+    }
+
+    #[test]
+    fn readable_event_has_token() {
+        for mut poll in polls() {
+            let mut events = Events::with_capacity(8);
+            let (a, mut b) = pair();
+
+            poll.registry()
+                .register(&a, Token(7), Interest::READABLE)
+                .unwrap();
+            b.write_all(b"hello").unwrap();
+
+            poll.poll(&mut events, TIMEOUT).unwrap();
+
+            assert_eq!(events.len(), 1);
+            let event = (&events).into_iter().next().unwrap();
+            assert_eq!(event.token(), Token(7));
+            assert!(event.is_readable());
+            assert!(!event.is_writable());
+        }
+    }
+
+    #[test]
+    fn edge_triggered_until_rearmed() {
+        for mut poll in polls() {
+            let mut events = Events::with_capacity(8);
+            let (a, mut b) = pair();
+
+            poll.registry()
+                .register(&a, Token(0), Interest::READABLE)
+                .unwrap();
+            b.write_all(b"one").unwrap();
+
+            poll.poll(&mut events, TIMEOUT).unwrap();
+            assert_eq!(events.len(), 1);
+
+            // not drained, but no new edge either
+            poll.poll(&mut events, TIMEOUT).unwrap();
+            assert!(events.is_empty());
+
+            drain(&a);
+            poll.registry().rearm(&a, Interest::READABLE).unwrap();
+            b.write_all(b"two").unwrap();
+
+            poll.poll(&mut events, TIMEOUT).unwrap();
+            assert_eq!(events.len(), 1);
+        }
+    }
+
+    #[test]
+    fn edge_triggered_after_drain_without_rearm() {
+        for mut poll in polls() {
+            let mut events = Events::with_capacity(8);
+            let (a, mut b) = pair();
+
+            poll.registry()
+                .register(&a, Token(0), Interest::READABLE)
+                .unwrap();
+            b.write_all(b"one").unwrap();
+
+            poll.poll(&mut events, TIMEOUT).unwrap();
+            assert_eq!(events.len(), 1);
+
+            // drained until `WouldBlock`, as epoll expects, seen by the next poll
+            drain(&a);
+            poll.poll(&mut events, TIMEOUT).unwrap();
+            assert!(events.is_empty());
+
+            b.write_all(b"two").unwrap();
+            poll.poll(&mut events, TIMEOUT).unwrap();
+            assert_eq!(events.len(), 1, "{}", poll.backend());
+        }
+    }
+
+    #[test]
+    fn reregister_changes_interests() {
+        for mut poll in polls() {
+            let mut events = Events::with_capacity(8);
+            let (a, _b) = pair();
+
+            poll.registry()
+                .register(&a, Token(0), Interest::READABLE)
+                .unwrap();
+
+            poll.poll(&mut events, TIMEOUT).unwrap();
+            assert!(events.is_empty());
+
+            poll.registry()
+                .reregister(&a, Token(1), Interest::WRITABLE)
+                .unwrap();
+
+            poll.poll(&mut events, TIMEOUT).unwrap();
+            let event = (&events).into_iter().next().unwrap();
+            assert_eq!(event.token(), Token(1));
+            assert!(event.is_writable());
+        }
+    }
+
+    #[test]
+    fn deregister_stops_events() {
+        for mut poll in polls() {
+            let mut events = Events::with_capacity(8);
+            let (a, mut b) = pair();
+
+            poll.registry()
+                .register(&a, Token(0), Interest::READABLE)
+                .unwrap();
+            poll.registry().deregister(&a).unwrap();
+            b.write_all(b"hello").unwrap();
+
+            poll.poll(&mut events, TIMEOUT).unwrap();
+            assert!(events.is_empty());
+        }
+    }
+
+    #[test]
+    fn peer_closed() {
+        for mut poll in polls() {
+            let mut events = Events::with_capacity(8);
+            let (a, b) = pair();
+
+            poll.registry()
+                .register(&a, Token(0), Interest::READABLE)
+                .unwrap();
+            drop(b);
+
+            poll.poll(&mut events, TIMEOUT).unwrap();
+            let event = (&events).into_iter().next().unwrap();
+            assert!(event.is_read_closed());
+        }
+    }
+
+    #[test]
+    fn register_twice_fails() {
+        for poll in polls() {
+            let (a, _b) = pair();
+
+            poll.registry()
+                .register(&a, Token(0), Interest::READABLE)
+                .unwrap();
+
+            let err = poll
+                .registry()
+                .register(&a, Token(0), Interest::READABLE)
+                .unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        }
+    }
+
+    #[test]
+    fn registrations_are_tracked() {
+        for poll in polls() {
+            let (a, _b) = pair();
+            let registry = poll.registry();
+
+            registry.register(&a, Token(1), Interest::READABLE).unwrap();
+            assert_eq!(
+                registry.registered(&a),
+                Some((Token(1), Interest::READABLE))
+            );
+
+            registry
+                .reregister(&a, Token(2), Interest::WRITABLE)
+                .unwrap();
+            assert_eq!(
+                registry.registered(&a),
+                Some((Token(2), Interest::WRITABLE))
+            );
+
+            registry.deregister(&a).unwrap();
+            assert_eq!(registry.registered(&a), None);
+        }
+    }
+
+    #[test]
+    fn guard_deregisters_on_drop() {
+        for mut poll in polls() {
+            let mut events = Events::with_capacity(8);
+            let (a, mut b) = pair();
+
+            let guard = poll
+                .registry()
+                .register_guarded(&a, Token(4), Interest::READABLE)
+                .unwrap();
+            assert_eq!(guard.get(), Some((Token(4), Interest::READABLE)));
+
+            let err = poll
+                .registry()
+                .register_guarded(&a, Token(5), Interest::READABLE)
+                .unwrap_err();
+            assert!(matches!(err, Error::AlreadyRegistered(_)));
+            assert_eq!(err.token(), Some(Token(5)));
+            assert_eq!(err.existing_token(), Some(Token(4)));
+            assert_eq!(err.fd(), Some(a.as_raw_fd()));
+            assert!(err
+                .to_string()
+                .ends_with("token=5 existing=4: already registered"));
+
+            drop(guard);
+            assert_eq!(poll.registry().registered(&a), None);
+
+            b.write_all(b"hello").unwrap();
+            poll.poll(&mut events, TIMEOUT).unwrap();
+            assert!(events.is_empty(), "{}", poll.backend());
+
+            // free to be registered again
+            poll.registry()
+                .register(&a, Token(6), Interest::READABLE)
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn stale_guard_leaves_new_registration() {
+        for mut poll in polls() {
+            let mut events = Events::with_capacity(8);
+            let (a, mut b) = pair();
+
+            let guard = poll
+                .registry()
+                .register_guarded(&a, Token(1), Interest::READABLE)
+                .unwrap();
+            poll.registry().deregister(&a).unwrap();
+            poll.registry()
+                .register(&a, Token(2), Interest::READABLE)
+                .unwrap();
+
+            assert_eq!(guard.get(), None);
+            assert!(matches!(
+                guard.reregister(Token(3), Interest::READABLE),
+                Err(Error::NotRegistered(_))
+            ));
+            drop(guard);
+            assert_eq!(
+                poll.registry().registered(&a),
+                Some((Token(2), Interest::READABLE))
+            );
+
+            b.write_all(b"hello").unwrap();
+            poll.poll(&mut events, TIMEOUT).unwrap();
+            let event = (&events).into_iter().next().unwrap();
+            assert_eq!(event.token(), Token(2), "{}", poll.backend());
+        }
+    }
+
+    #[test]
+    fn guard_deregister_reports_missing_registration() {
+        for poll in polls() {
+            let (a, _b) = pair();
+
+            let guard = poll
+                .registry()
+                .register_guarded(&a, Token(0), Interest::READABLE)
+                .unwrap();
+            poll.registry().deregister(&a).unwrap();
+            assert_eq!(guard.get(), None);
+
+            let err = guard.deregister().unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::NotFound);
+            assert_eq!(
+                err.to_string(),
+                format!("deregister fd={}: not registered", a.as_raw_fd())
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn regular_file_is_unsupported() {
+        let file = std::fs::File::open("Cargo.toml").unwrap();
+        let poll = Poll::builder().backend(Backend::Epoll).build().unwrap();
+
+        let err = poll
+            .registry()
+            .register(&file, Token(2), Interest::READABLE)
+            .unwrap_err();
+        assert!(matches!(err, Error::UnsupportedFileType(_)), "{err}");
+        assert_eq!(err.operation(), Operation::Register);
+        assert_eq!(err.token(), Some(Token(2)));
+    }
+
+    #[test]
+    fn zero_capacity_is_invalid() {
+        for mut poll in polls() {
+            let err = poll
+                .poll(&mut Events::with_capacity(0), TIMEOUT)
+                .unwrap_err();
+            assert!(matches!(err, Error::InvalidCapacity(_)), "{err}");
+        }
+    }
+
+    #[test]
+    fn nested_poll_is_readable() {
+        for mut outer in polls() {
+            let mut events = Events::with_capacity(8);
+
+            for mut inner in polls().filter(|poll| poll.raw_fd().is_some()) {
+                let (a, mut b) = pair();
+
+                inner
+                    .registry()
+                    .register(&a, Token(1), Interest::READABLE)
+                    .unwrap();
+                // io_uring only submits the registration with the next call
+                inner.poll(&mut events, Some(Duration::ZERO)).unwrap();
+
+                outer
+                    .registry()
+                    .register_poll(&inner, Token(100), Interest::READABLE)
+                    .unwrap();
+                b.write_all(b"hello").unwrap();
+
+                outer.poll(&mut events, TIMEOUT).unwrap();
+                let event = (&events).into_iter().next().unwrap();
+                assert_eq!(event.token(), Token(100), "{}", outer.backend());
+                assert!(event.is_readable());
+
+                inner.poll(&mut events, Some(Duration::ZERO)).unwrap();
+                let event = (&events).into_iter().next().unwrap();
+                assert_eq!(event.token(), Token(1), "{}", inner.backend());
+
+                outer.registry().deregister_poll(&inner).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn poll_backend_has_no_fd() {
+        let poll = Poll::builder().backend(Backend::Poll).build().unwrap();
+        assert_eq!(poll.raw_fd(), None);
+
+        let outer = Poll::new().unwrap();
+        let err = outer
+            .registry()
+            .register_poll(&poll, Token(1), Interest::READABLE)
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+        assert_eq!(
+            err.to_string(),
+            "register token=1: poll backend has no file descriptor"
+        );
+    }
+
+    #[test]
+    fn reinit_in_forked_child() {
+        for mut poll in polls() {
+            let mut events = Events::with_capacity(8);
+            let (a, mut b) = pair();
+            let (c, _d) = pair();
+            let (e, mut f) = pair();
+            let (g, mut h) = pair();
+
+            poll.registry()
+                .register(&a, Token(1), Interest::READABLE)
+                .unwrap();
+            poll.registry()
+                .register(&e, Token(3), Interest::READABLE)
+                .unwrap();
+            poll.registry()
+                .register(&g, Token(4), Interest::READABLE)
+                .unwrap();
+            assert!(!poll.is_forked());
+
+            // leave an event over in the poll_with buffer, which the child must not see
+            f.write_all(b"hello").unwrap();
+            h.write_all(b"hello").unwrap();
+            let handled = poll.poll_with(TIMEOUT, |_| ControlFlow::Break(())).unwrap();
+            assert_eq!(handled, 1);
+
+            let pid = unsafe { libc::fork() };
+            assert!(pid >= 0);
+
+            if pid == 0 {
+                // no unwinding into the test harness from the child
+                let mut child = || -> io::Result<bool> {
+                    let forked = poll.is_forked();
+                    // closed without being deregistered, one of them with an event left over
+                    let closed = [e.as_raw_fd(), g.as_raw_fd()];
+                    for fd in closed {
+                        unsafe { libc::close(fd) };
+                    }
+                    poll.reinit_after_fork()?;
+
+                    let registry = poll.registry();
+                    let pruned = closed
+                        .iter()
+                        .all(|&fd| registry.registered_fd(fd).is_none());
+                    poll.registry().register(&c, Token(2), Interest::READABLE)?;
+                    b.write_all(b"hello")?;
+                    poll.poll(&mut events, TIMEOUT)?;
+
+                    let tokens: Vec<_> = (&events).into_iter().map(|e| e.token()).collect();
+                    let mut leftovers = Vec::new();
+                    poll.poll_with(Some(Duration::ZERO), |e| {
+                        leftovers.push(e.token());
+                        ControlFlow::Continue(())
+                    })?;
+
+                    Ok(forked
+                        && !poll.is_forked()
+                        && pruned
+                        && tokens == [Token(1)]
+                        && !leftovers.iter().any(|t| [Token(3), Token(4)].contains(t)))
+                };
+
+                let code = if matches!(child(), Ok(true)) { 0 } else { 1 };
+                unsafe { libc::_exit(code) };
+            }
+
+            let mut status = 0;
+            assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+            assert_eq!(libc::WEXITSTATUS(status), 0, "{}", poll.backend());
+
+            // the child's registration did not reach the parent's queue
+            poll.registry()
+                .register(&c, Token(2), Interest::READABLE)
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn batch_reports_errors_per_change() {
+        for mut poll in polls() {
+            let mut events = Events::with_capacity(8);
+            let pairs: Vec<_> = (0..3).map(|_| pair()).collect();
+            let (unknown, _peer) = pair();
+
+            let mut batch = poll.registry().batch();
+            for (i, (a, _)) in pairs.iter().enumerate() {
+                batch.register(a, Token(i), Interest::READABLE);
+            }
+            batch
+                .register(&pairs[0].0, Token(10), Interest::READABLE)
+                .deregister(&unknown)
+                .reregister(&pairs[1].0, Token(11), Interest::READABLE);
+            assert_eq!(batch.len(), 6);
+
+            let err = batch.apply().unwrap_err();
+            assert!(batch.is_empty());
+            assert_eq!(err.total, 6);
+            assert_eq!(err.errors.len(), 2, "{}", poll.backend());
+            assert!(matches!(err.errors[0], (3, Error::AlreadyRegistered(_))));
+            assert!(matches!(err.errors[1], (4, Error::NotRegistered(_))));
+            assert!(err
+                .to_string()
+                .starts_with("2 of 6 changes failed, first #3: register"));
+
+            let registry = poll.registry();
+            assert_eq!(
+                registry.registered(&pairs[0].0),
+                Some((Token(0), Interest::READABLE))
+            );
+            assert_eq!(
+                registry.registered(&pairs[1].0),
+                Some((Token(11), Interest::READABLE))
+            );
+
+            pairs[2].1.try_clone().unwrap().write_all(b"hello").unwrap();
+            poll.poll(&mut events, TIMEOUT).unwrap();
+            let event = (&events).into_iter().next().unwrap();
+            assert_eq!(event.token(), Token(2));
+
+            let mut batch = poll.registry().batch();
+            for (a, _) in &pairs {
+                batch.deregister(a);
+            }
+            batch.apply().unwrap();
+            assert_eq!(poll.registry().registered(&pairs[0].0), None);
+        }
+    }
+
+    #[test]
+    fn remove_interest_stops_events() {
+        for mut poll in polls() {
+            let mut events = Events::with_capacity(8);
+            let (a, mut b) = pair();
+
+            poll.registry()
+                .register(&a, Token(1), Interest::READABLE | Interest::WRITABLE)
+                .unwrap();
+            poll.poll(&mut events, TIMEOUT).unwrap();
+            assert!((&events).into_iter().any(|e| e.is_writable()));
+
+            poll.registry()
+                .remove_interest(&a, Interest::WRITABLE)
+                .unwrap();
+            b.write_all(b"hello").unwrap();
+
+            // a new edge, which must not carry the writable interest along
+            poll.poll(&mut events, TIMEOUT).unwrap();
+            let event = (&events).into_iter().next().unwrap();
+            assert!(event.is_readable());
+            assert!(
+                (&events).into_iter().all(|e| !e.is_writable()),
+                "{}",
+                poll.backend()
+            );
+        }
+    }
+
+    #[test]
+    fn registrations_count_tokens() {
+        let mut registrations = Registrations::default();
+
+        registrations.insert(3, Token(1), Interest::READABLE);
+        registrations.insert(4, Token(1), Interest::READABLE);
+        registrations.remove(3);
+        assert!(registrations.has_token(Token(1)));
+
+        registrations.update(4, Token(2), Interest::READABLE);
+        assert!(!registrations.has_token(Token(1)));
+        assert!(registrations.has_token(Token(2)));
+
+        registrations.retain(|fd| fd != 4);
+        assert!(registrations.tokens.is_empty());
+    }
+
+    #[test]
+    fn concurrent_interest_changes_are_not_lost() {
+        let poll = Poll::new().unwrap();
+        let registry = poll.registry();
+
+        for _ in 0..100 {
+            let (a, _b) = pair();
+            registry.register(&a, Token(0), Interest::READABLE).unwrap();
+
+            // adding an interest already there must not undo the other thread's change
+            std::thread::scope(|s| {
+                s.spawn(|| registry.add_interest(&a, Interest::WRITABLE).unwrap());
+                s.spawn(|| registry.add_interest(&a, Interest::READABLE).unwrap());
+            });
+
+            assert_eq!(
+                registry.registered(&a),
+                Some((Token(0), Interest::READABLE | Interest::WRITABLE))
+            );
+            registry.deregister(&a).unwrap();
+        }
+    }
+
+    #[test]
+    fn batch_reregister_drops_interests() {
+        for mut poll in polls() {
+            let mut events = Events::with_capacity(8);
+            let (a, _b) = pair();
+
+            poll.registry()
+                .register(&a, Token(1), Interest::READABLE | Interest::WRITABLE)
+                .unwrap();
+            poll.registry()
+                .batch()
+                .reregister(&a, Token(1), Interest::READABLE)
+                .apply()
+                .unwrap();
+
+            // always writable, but no longer of interest
+            poll.poll(&mut events, TIMEOUT).unwrap();
+            assert!(events.is_empty(), "{}", poll.backend());
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "testing")]
+    fn batch_checks_registrations_before_submitting() {
+        use crate::testing::{Call, FakeSource};
+
+        let poll = Poll::builder().mock().build().unwrap();
+        let mock = poll.mock().unwrap();
+        let registry = poll.registry();
+        let (a, b) = (FakeSource(3), FakeSource(4));
+
+        registry.register(&a, Token(1), Interest::READABLE).unwrap();
+
+        let mut batch = registry.batch();
+        batch
+            .register(&a, Token(2), Interest::READABLE)
+            .reregister(&b, Token(3), Interest::READABLE)
+            .register(&b, Token(4), Interest::READABLE)
+            .reregister(&b, Token(5), Interest::WRITABLE)
+            .deregister(&a);
+
+        let err = batch.apply().unwrap_err();
+        assert_eq!(err.errors.len(), 2);
+        assert!(matches!(err.errors[0], (0, Error::AlreadyRegistered(_))));
+        assert_eq!(err.errors[0].1.existing_token(), Some(Token(1)));
+        assert!(matches!(err.errors[1], (1, Error::NotRegistered(_))));
+
+        assert_eq!(
+            mock.calls()[1..],
+            [
+                Call::Register {
+                    fd: 4,
+                    token: Token(4),
+                    interests: Interest::READABLE,
+                },
+                Call::Reregister {
+                    fd: 4,
+                    token: Token(5),
+                    interests: Interest::WRITABLE,
+                },
+                Call::Deregister { fd: 3 },
+            ]
+        );
+        assert_eq!(registry.registered(&a), None);
+        assert_eq!(
+            registry.registered(&b),
+            Some((Token(5), Interest::WRITABLE))
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "testing")]
+    fn unchanged_interests_skip_reregister() {
+        use crate::testing::{Call, FakeSource};
+
+        let poll = Poll::builder().mock().build().unwrap();
+        let mock = poll.mock().unwrap();
+        let registry = poll.registry();
+        let source = FakeSource(3);
+
+        registry
+            .register(&source, Token(1), Interest::READABLE)
+            .unwrap();
+
+        registry.add_interest(&source, Interest::WRITABLE).unwrap();
+        registry.add_interest(&source, Interest::WRITABLE).unwrap();
+        registry
+            .reregister(&source, Token(1), Interest::READABLE | Interest::WRITABLE)
+            .unwrap();
+        registry
+            .remove_interest(&source, Interest::WRITABLE)
+            .unwrap();
+
+        let err = registry
+            .remove_interest(&source, Interest::READABLE)
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+        let reregisters: Vec<_> = mock
+            .calls()
+            .into_iter()
+            .filter_map(|call| match call {
+                Call::Reregister { interests, .. } => Some(interests),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            reregisters,
+            [Interest::READABLE | Interest::WRITABLE, Interest::READABLE]
+        );
+
+        let err = registry
+            .add_interest(&FakeSource(4), Interest::READABLE)
+            .unwrap_err();
+        assert!(matches!(err, Error::NotRegistered(_)));
+    }
+
+    #[test]
+    fn token_limit_defers_events() {
+        for mut poll in polls() {
+            let mut events = Events::with_capacity(8).with_token_limit(1);
+            let (a, mut b) = pair();
+            let (c, mut d) = pair();
+            let (e, mut f) = pair();
+
+            // two sources sharing a token, which is over the limit when both are ready
+            let registry = poll.registry();
+            registry.register(&a, Token(1), Interest::READABLE).unwrap();
+            registry.register(&c, Token(1), Interest::READABLE).unwrap();
+            registry.register(&e, Token(2), Interest::READABLE).unwrap();
+
+            for stream in [&mut b, &mut d, &mut f] {
+                stream.write_all(b"hello").unwrap();
+            }
+
+            poll.poll(&mut events, TIMEOUT).unwrap();
+            let mut tokens: Vec<_> = (&events).into_iter().map(|e| e.token()).collect();
+            tokens.sort_by_key(|token| token.0);
+            assert_eq!(tokens, [Token(1), Token(2)], "{}", poll.backend());
+            assert_eq!(events.deferred(), 1);
+            assert!(!events.is_saturated());
+
+            // reported straight away, even though nothing else is ready
+            poll.poll(&mut events, None).unwrap();
+            let tokens: Vec<_> = (&events).into_iter().map(|e| e.token()).collect();
+            assert_eq!(tokens, [Token(1)]);
+            assert_eq!(events.deferred(), 0);
+        }
+    }
+
+    #[test]
+    fn token_limit_defers_one_event_per_source() {
+        for backend in os_backends() {
+            let mut poll = Poll::builder()
+                .backend(backend)
+                .trigger(Trigger::Level)
+                .build()
+                .unwrap();
+            let mut events = Events::with_capacity(8).with_token_limit(1);
+            let (a, mut b) = pair();
+            let (c, mut d) = pair();
+
+            // never drained, so reported again by every poll
+            let registry = poll.registry();
+            registry.register(&a, Token(1), Interest::READABLE).unwrap();
+            registry.register(&c, Token(1), Interest::READABLE).unwrap();
+            b.write_all(b"hello").unwrap();
+            d.write_all(b"hello").unwrap();
+
+            for _ in 0..4 {
+                poll.poll(&mut events, TIMEOUT).unwrap();
+                assert_eq!(events.len(), 1);
+                assert_eq!(events.deferred(), 1, "{backend}");
+            }
+
+            // not reported once deregistered, although still deferred
+            poll.registry().deregister(&a).unwrap();
+            poll.registry().deregister(&c).unwrap();
+            poll.poll(&mut events, TIMEOUT).unwrap();
+            assert!(events.is_empty(), "{backend}");
+            assert_eq!(events.deferred(), 0);
+        }
+    }
+
+    #[test]
+    fn poll_with_resumes_after_break() {
+        for mut poll in polls() {
+            let streams: Vec<_> = (0..3).map(|_| pair()).collect();
+
+            for (i, (a, b)) in streams.iter().enumerate() {
+                poll.registry()
+                    .register(a, Token(i), Interest::READABLE)
+                    .unwrap();
+                (&*b).write_all(b"hello").unwrap();
+            }
+
+            let mut tokens = Vec::new();
+            let mut handle = |event: &Event| {
+                drain(&streams[event.token().0].0);
+                tokens.push(event.token().0);
+            };
+
+            let n = poll
+                .poll_with(TIMEOUT, |event| {
+                    handle(event);
+                    ControlFlow::Break(())
+                })
+                .unwrap();
+            assert_eq!(n, 1, "{}", poll.backend());
+
+            // the rest of the events polled above, without another poll
+            let n = poll
+                .poll_with(None, |event| {
+                    handle(event);
+                    ControlFlow::Continue(())
+                })
+                .unwrap();
+            assert_eq!(n, 2);
+
+            tokens.sort();
+            assert_eq!(tokens, [0, 1, 2]);
+
+            let n = poll
+                .poll_with(Some(Duration::ZERO), |_| ControlFlow::Continue(()))
+                .unwrap();
+            assert_eq!(n, 0);
+        }
+    }
+
+    #[test]
+    fn poll_with_drops_leftovers_of_deregistered_sources() {
+        for mut poll in polls() {
+            let streams: Vec<_> = (0..3).map(|_| pair()).collect();
+
+            for (i, (a, b)) in streams.iter().enumerate() {
+                poll.registry()
+                    .register(a, Token(i), Interest::READABLE)
+                    .unwrap();
+                (&*b).write_all(b"hello").unwrap();
+            }
+
+            let mut first = None;
+            poll.poll_with(TIMEOUT, |event| {
+                first = Some(event.token().0);
+                ControlFlow::Break(())
+            })
+            .unwrap();
+
+            let first = first.unwrap();
+            drain(&streams[first].0);
+
+            // the other two left over, of which one is deregistered
+            let (kept, dropped) = match first {
+                0 => (1, 2),
+                _ => (0, 3 - first),
+            };
+            drain(&streams[kept].0);
+            poll.registry().deregister(&streams[dropped].0).unwrap();
+
+            let mut tokens = Vec::new();
+            poll.poll_with(TIMEOUT, |event| {
+                tokens.push(event.token().0);
+                ControlFlow::Continue(())
+            })
+            .unwrap();
+            assert_eq!(tokens, [kept], "{}", poll.backend());
+        }
+    }
+
+    #[test]
+    fn deregister_unknown_fails() {
+        for poll in polls() {
+            let (a, _b) = pair();
+
+            let err = poll.registry().deregister(&a).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        }
+    }
+
+    #[test]
+    fn level_triggered_until_drained() {
+        for backend in os_backends() {
+            let mut poll = Poll::builder()
+                .backend(backend)
+                .trigger(Trigger::Level)
+                .build()
+                .unwrap();
+            let mut events = poll.events();
+            let (a, mut b) = pair();
+
+            poll.registry()
+                .register(&a, Token(0), Interest::READABLE)
+                .unwrap();
+            b.write_all(b"one").unwrap();
+
+            poll.poll(&mut events, TIMEOUT).unwrap();
+            assert_eq!(events.len(), 1, "{backend}");
+
+            // not drained, so reported again
+            poll.poll(&mut events, TIMEOUT).unwrap();
+            assert_eq!(events.len(), 1, "{backend}");
+
+            drain(&a);
+            poll.poll(&mut events, TIMEOUT).unwrap();
+            assert!(events.is_empty(), "{backend}");
+        }
+    }
+
+    #[test]
+    #[cfg(all(target_os = "linux", feature = "libc-ffi", target_env = "gnu"))]
+    fn sub_millisecond_timeout_blocks() {
+        let mut poll = Poll::builder().backend(Backend::Epoll).build().unwrap();
+        let mut events = Events::with_capacity(8);
+        let timeout = Duration::from_micros(500);
+
+        // epoll_wait would round the timeout down to 0ms, and return straight away
+        let start = std::time::Instant::now();
+        poll.poll(&mut events, Some(timeout)).unwrap();
+        assert!(start.elapsed() >= timeout);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn sigmask_unblocks_signal_while_waiting() {
+        extern "C" fn handler(_: i32) {}
+
+        unsafe {
+            let mut action: libc::sigaction = std::mem::zeroed();
+            action.sa_sigaction = handler as extern "C" fn(i32) as usize;
+            libc::sigaction(libc::SIGUSR1, &action, std::ptr::null_mut());
+        }
+
+        let mut sigusr1 = SigSet::empty();
+        sigusr1.add(libc::SIGUSR1).unwrap();
+        let previous = sigusr1.block().unwrap();
+
+        let mut waiting = previous;
+        waiting.remove(libc::SIGUSR1).unwrap();
+
+        for mut poll in polls() {
+            let mut events = Events::with_capacity(8);
+
+            // stays pending until the wait unblocks it
+            unsafe { libc::pthread_kill(libc::pthread_self(), libc::SIGUSR1) };
+            poll.poll(&mut events, Some(Duration::ZERO)).unwrap();
+
+            let err = poll
+                .poll_with_sigmask(&mut events, None, &waiting)
+                .unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::Interrupted, "{}", poll.backend());
+        }
+
+        previous.set_thread_mask().unwrap();
+    }
+
+    #[test]
+    fn builder_selects_backend() {
+        for &backend in Backend::ALL {
+            let poll = Poll::builder()
+                .backend(backend)
+                .capacity(16)
+                .cloexec(false)
+                .build()
+                .unwrap();
+
+            assert_eq!(poll.capacity(), 16);
+            assert_eq!(poll.events().capacity(), 16);
+
+            #[cfg(all(target_os = "linux", feature = "io-uring"))]
+            if backend == Backend::IoUring && poll.backend() != backend {
+                // io_uring is disabled, and the default backend is used instead
+                continue;
+            }
+
+            assert_eq!(poll.backend(), backend);
+        }
+    }
+
+    #[test]
+    fn backend_from_str() {
+        for &backend in Backend::ALL {
+            assert_eq!(backend.to_string().parse::<Backend>().unwrap(), backend);
+        }
+
+        #[cfg(target_os = "linux")]
+        assert_eq!(" EPOLL ".parse::<Backend>().unwrap(), Backend::Epoll);
+
+        #[cfg(all(target_os = "linux", feature = "io-uring"))]
+        assert_eq!("io-uring".parse::<Backend>().unwrap(), Backend::IoUring);
+
+        let err = "select".parse::<Backend>().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+        // test-only selectors have builder methods of their own
+        #[cfg(feature = "record")]
+        {
+            assert!("replay".parse::<Backend>().is_err());
+
+            let err = Poll::builder()
+                .backend(Backend::Replay)
+                .build()
+                .err()
+                .unwrap();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+            assert_eq!(err.operation(), Operation::Open);
+            assert!(err.to_string().starts_with("open: "));
+        }
+
+        #[cfg(feature = "testing")]
+        assert!("mock".parse::<Backend>().is_err());
+    }
 }
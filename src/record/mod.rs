@@ -0,0 +1,354 @@
+//! Record an event loop session to a file, and replay it through `Poll::poll`.
+//!
+//! `PollBuilder::record` wraps the selector, so that every registration change and every
+//! polled event is written to a line-oriented log. `PollBuilder::replay` builds an event
+//! queue that reports the recorded events again, one recorded call to `poll` at a time,
+//! without touching the kernel.
+//!
+//! # Format
+//!
+//! A header, then one line per call or event. Every line after the header starts with
+//! the number of microseconds since recording started.
+//!
+//! ```text
+//! #mini-mio-session v1 os=linux backend=epoll start=1760000000.123456
+//! 15 register fd=5 token=1 interests=READABLE
+//! 40 reregister fd=5 token=1 interests=READABLE|WRITABLE
+//! 52 deregister fd=6 err=2
+//! 50210 poll timeout=50000us n=1
+//! 50210 event token=1 ready=readable|read_closed raw=0x2001
+//! 90477 poll timeout=none err=4
+//! ```
+//!
+//! `raw` holds the event as returned by the kernel: the `events` bits of an
+//! `epoll_event`, or `filter:flags:fflags:data` of a `kevent`. A session recorded on
+//! another OS is replayed from the decoded `ready` flags instead.
+
+mod recorder;
+mod replay;
+
+pub use recorder::{Recorder, RecordingSelector};
+pub use replay::{ReplaySelector, Session};
+
+use std::io;
+use std::time::Duration;
+
+use crate::interests::Interest;
+use crate::interfaces::{SysEvent, Token};
+use crate::sys::OsEvent;
+
+/// First word of the header line.
+const MAGIC: &str = "#mini-mio-session";
+
+/// Format version written to, and accepted from, the header line.
+const VERSION: &str = "v1";
+
+/// OS the session was recorded on, which determines the meaning of `raw`.
+#[cfg(target_os = "linux")]
+const OS: &str = "linux";
+
+#[cfg(target_os = "macos")]
+const OS: &str = "macos";
+
+/// Reports whether a decoded readiness flag is set on an event.
+type IsSet = fn(&OsEvent) -> bool;
+
+/// Decoded readiness of an event, which is independent of the OS.
+const READY_FLAGS: [(&str, IsSet); 5] = [
+    ("readable", SysEvent::is_readable),
+    ("writable", SysEvent::is_writable),
+    ("read_closed", SysEvent::is_read_closed),
+    ("write_closed", SysEvent::is_write_closed),
+    ("error", SysEvent::is_error),
+];
+
+fn format_interests(interests: Interest) -> String {
+    match (interests.is_readable(), interests.is_writable()) {
+        (true, true) => "READABLE|WRITABLE",
+        (true, false) => "READABLE",
+        (false, true) => "WRITABLE",
+        (false, false) => "-",
+    }
+    .to_string()
+}
+
+fn parse_interests(s: &str) -> io::Result<Interest> {
+    s.split('|')
+        .map(|name| match name {
+            "READABLE" => Ok(Interest::READABLE),
+            "WRITABLE" => Ok(Interest::WRITABLE),
+            _ => Err(invalid(format!("unknown interest: {name}"))),
+        })
+        .reduce(|a, b| Ok(a? | b?))
+        .unwrap_or_else(|| Err(invalid("missing interests")))
+}
+
+fn format_ready(event: &OsEvent) -> String {
+    let names: Vec<_> = READY_FLAGS
+        .iter()
+        .filter(|(_, is_set)| is_set(event))
+        .map(|(name, _)| *name)
+        .collect();
+
+    if names.is_empty() {
+        "-".to_string()
+    } else {
+        names.join("|")
+    }
+}
+
+fn format_timeout(timeout: Option<Duration>) -> String {
+    match timeout {
+        Some(timeout) => format!("{}us", timeout.as_micros()),
+        None => "none".to_string(),
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn format_raw(event: &OsEvent) -> String {
+    format!("{:#x}", { event.events })
+}
+
+#[cfg(target_os = "macos")]
+fn format_raw(event: &OsEvent) -> String {
+    format!(
+        "{}:{:#x}:{:#x}:{}",
+        event.filter, event.flags, event.fflags, event.data
+    )
+}
+
+#[cfg(target_os = "linux")]
+fn parse_raw(s: &str, token: Token) -> io::Result<OsEvent> {
+    let bits = s
+        .strip_prefix("0x")
+        .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+        .ok_or_else(|| invalid(format!("invalid raw event: {s}")))?;
+
+    Ok(OsEvent {
+        events: bits as i32,
+        epoll_data: token.0,
+    })
+}
+
+#[cfg(target_os = "macos")]
+fn parse_raw(s: &str, token: Token) -> io::Result<OsEvent> {
+    let err = || invalid(format!("invalid raw event: {s}"));
+    let hex = |field| strip_hex(field).ok_or_else(err);
+
+    let mut fields = s.split(':');
+    let mut next = || fields.next().ok_or_else(err);
+
+    Ok(OsEvent {
+        ident: 0,
+        filter: next()?.parse().map_err(|_| err())?,
+        flags: u16::from_str_radix(hex(next()?)?, 16).map_err(|_| err())?,
+        fflags: u32::from_str_radix(hex(next()?)?, 16).map_err(|_| err())?,
+        data: next()?.parse().map_err(|_| err())?,
+        udata: token.0,
+    })
+}
+
+/// Best effort reconstruction of an event recorded on another OS.
+#[cfg(target_os = "linux")]
+fn event_from_ready(ready: &str, token: Token) -> io::Result<OsEvent> {
+    use crate::sys::constants::epoll::events::*;
+
+    let mut bits = 0;
+
+    for name in ready.split('|').filter(|name| *name != "-") {
+        bits |= match name {
+            "readable" => EPOLLIN,
+            "writable" => EPOLLOUT,
+            "read_closed" => EPOLLIN | EPOLLRDHUP,
+            "write_closed" => EPOLLOUT | EPOLLERR,
+            "error" => EPOLLERR,
+            _ => return Err(invalid(format!("unknown readiness: {name}"))),
+        };
+    }
+
+    Ok(OsEvent {
+        events: bits,
+        epoll_data: token.0,
+    })
+}
+
+/// Best effort reconstruction of an event recorded on another OS.
+///
+/// kqueue reports one event per filter, so the write filter is only used when the event
+/// was not readable.
+#[cfg(target_os = "macos")]
+fn event_from_ready(ready: &str, token: Token) -> io::Result<OsEvent> {
+    use crate::sys::constants::kqueue::{filters, flags};
+
+    let mut filter = filters::EVFILT_WRITE;
+    let mut event_flags = 0;
+
+    for name in ready.split('|').filter(|name| *name != "-") {
+        match name {
+            "readable" => filter = filters::EVFILT_READ,
+            "writable" => {}
+            "read_closed" => {
+                filter = filters::EVFILT_READ;
+                event_flags |= flags::EV_EOF;
+            }
+            "write_closed" => event_flags |= flags::EV_EOF,
+            "error" => event_flags |= flags::EV_ERROR,
+            _ => return Err(invalid(format!("unknown readiness: {name}"))),
+        }
+    }
+
+    Ok(OsEvent {
+        ident: 0,
+        filter,
+        flags: event_flags,
+        fflags: 0,
+        data: 0,
+        udata: token.0,
+    })
+}
+
+#[cfg(target_os = "macos")]
+fn strip_hex(s: &str) -> Option<&str> {
+    s.strip_prefix("0x")
+}
+
+fn invalid(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::os::unix::net::UnixStream;
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::interfaces::Events;
+    use crate::poll::{Backend, Poll};
+
+    /// In-memory log, which can still be read once the recorder has taken a clone.
+    #[derive(Clone, Default)]
+    struct Log(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Log {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Log {
+        fn contents(&self) -> String {
+            String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+        }
+    }
+
+    fn tokens(events: &Events) -> Vec<(usize, bool, bool)> {
+        events
+            .iter()
+            .map(|event| (event.token().0, event.is_readable(), event.is_read_closed()))
+            .collect()
+    }
+
+    #[test]
+    fn record_and_replay() {
+        let log = Log::default();
+        let timeout = Some(Duration::from_millis(50));
+        let mut recorded = Vec::new();
+
+        {
+            let mut poll = Poll::builder()
+                .record(Recorder::new(log.clone()))
+                .build()
+                .unwrap();
+            let mut events = Events::with_capacity(8);
+            let (a, mut b) = UnixStream::pair().unwrap();
+            let (unknown, _peer) = UnixStream::pair().unwrap();
+
+            poll.registry()
+                .register(&a, Token(1), Interest::READABLE)
+                .unwrap();
+            poll.registry().deregister(&unknown).unwrap_err();
+
+            b.write_all(b"hello").unwrap();
+            poll.poll(&mut events, timeout).unwrap();
+            recorded.push(tokens(&events));
+
+            drop(b);
+            poll.poll(&mut events, timeout).unwrap();
+            recorded.push(tokens(&events));
+        }
+
+        let contents = log.contents();
+        assert!(contents.starts_with(MAGIC), "{contents}");
+        assert!(contents.contains("register fd="), "{contents}");
+        assert!(contents.contains("err=2"), "{contents}");
+        assert!(contents.contains("ready=readable"), "{contents}");
+
+        let session = Session::read(contents.as_bytes()).unwrap();
+        assert_eq!(session.os(), OS);
+        assert_eq!(session.remaining(), 2);
+
+        let mut poll = Poll::builder().replay(session).build().unwrap();
+        let mut events = Events::with_capacity(8);
+        let source = std::io::stdin();
+
+        assert_eq!(poll.backend(), Backend::Replay);
+
+        poll.registry()
+            .register(&source, Token(1), Interest::READABLE)
+            .unwrap();
+        let err = poll.registry().deregister(&source).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+
+        for expected in recorded {
+            poll.poll(&mut events, None).unwrap();
+            assert_eq!(tokens(&events), expected);
+        }
+
+        let err = poll.poll(&mut events, None).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn replay_from_another_os() {
+        let log = "\
+#mini-mio-session v1 os=elsewhere backend=other start=0.000000
+10 poll timeout=none n=2
+10 event token=4 ready=readable|read_closed raw=garbage
+10 event token=5 ready=writable raw=garbage
+20 poll timeout=1000us err=4
+";
+
+        let session = Session::read(log.as_bytes()).unwrap();
+        assert_eq!(session.backend(), "other");
+
+        let mut poll = Poll::builder().replay(session).build().unwrap();
+        let mut events = Events::with_capacity(1);
+
+        // carried over to a second call, as the capacity is smaller than recorded with
+        poll.poll(&mut events, None).unwrap();
+        assert_eq!(tokens(&events), [(4, true, true)]);
+
+        poll.poll(&mut events, None).unwrap();
+        let event = events.iter().next().unwrap();
+        assert_eq!(event.token(), Token(5));
+        assert!(event.is_writable());
+
+        let err = poll.poll(&mut events, None).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Interrupted);
+    }
+
+    #[test]
+    fn rejects_invalid_sessions() {
+        assert!(Session::read("".as_bytes()).is_err());
+        assert!(Session::read("#other-format v1\n".as_bytes()).is_err());
+
+        let log = format!("{MAGIC} {VERSION} os={OS}\n10 event token=1 ready=readable\n");
+        let err = Session::read(log.as_bytes()).unwrap_err();
+        assert!(err.to_string().contains("line 2"), "{err}");
+    }
+}
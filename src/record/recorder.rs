@@ -0,0 +1,273 @@
+//! Selector wrapper writing every call, and every polled event, to a session log.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::os::fd::RawFd;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::interests::Interest;
+use crate::interfaces::{Change, SelectorOptions, SysEvent, SysSelector, Token};
+use crate::poll::Backend;
+use crate::signal::SigSet;
+use crate::sys::{OsEvent, OsEvents};
+
+use super::{format_interests, format_raw, format_ready, format_timeout, MAGIC, OS, VERSION};
+
+struct Inner {
+    out: Box<dyn Write + Send>,
+    start: Instant,
+    /// Whether the header has been written, which needs to know the backend.
+    started: bool,
+}
+
+/// Destination of a recorded session. Clones write to the same destination.
+#[derive(Clone)]
+pub struct Recorder {
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl Recorder {
+    /// Record to a new file at `path`, truncating it if it exists.
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(Self::new(BufWriter::new(File::create(path)?)))
+    }
+
+    /// Record to any writer, which is flushed after every call to `poll`.
+    pub fn new<W: Write + Send + 'static>(out: W) -> Self {
+        Recorder {
+            inner: Arc::new(Mutex::new(Inner {
+                out: Box::new(out),
+                start: Instant::now(),
+                started: false,
+            })),
+        }
+    }
+
+    /// Write a single line, prefixed with the time since recording started.
+    ///
+    /// Errors are ignored: a full disk should not take the event loop down with it.
+    fn line(&self, args: fmt::Arguments<'_>) {
+        let mut inner = self.inner.lock().unwrap();
+        let micros = inner.start.elapsed().as_micros();
+        let _ = writeln!(inner.out, "{micros} {args}");
+    }
+
+    fn header(&self, backend: Backend) {
+        let mut inner = self.inner.lock().unwrap();
+
+        if inner.started {
+            return;
+        }
+
+        let start = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+
+        let _ = writeln!(
+            inner.out,
+            "{MAGIC} {VERSION} os={OS} backend={backend} start={}.{:06}",
+            start.as_secs(),
+            start.subsec_micros()
+        );
+
+        inner.start = Instant::now();
+        inner.started = true;
+    }
+
+    fn flush(&self) {
+        let _ = self.inner.lock().unwrap().out.flush();
+    }
+}
+
+impl fmt::Debug for Recorder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Recorder").finish_non_exhaustive()
+    }
+}
+
+/// Appended to a line when the call failed.
+fn outcome<T>(result: &io::Result<T>) -> String {
+    match result {
+        Ok(_) => String::new(),
+        Err(e) => match e.raw_os_error() {
+            Some(errno) => format!(" err={errno}"),
+            None => format!(" err={:?}", e.kind()),
+        },
+    }
+}
+
+/// Wraps a selector, recording every call made to it.
+pub struct RecordingSelector<S> {
+    inner: S,
+    recorder: Recorder,
+}
+
+impl<S: SysSelector> RecordingSelector<S> {
+    pub fn new(inner: S, recorder: Recorder) -> Self {
+        recorder.header(inner.backend());
+        Self { inner, recorder }
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    pub(crate) fn recorder(&self) -> &Recorder {
+        &self.recorder
+    }
+}
+
+impl<S> SysSelector for RecordingSelector<S>
+where
+    S: SysSelector<OsEvent = OsEvent, OsEvents = OsEvents>,
+{
+    type OsEvent = OsEvent;
+    type OsEvents = OsEvents;
+
+    /// Records to stderr, as there is nowhere else to record to.
+    fn with_options(options: SelectorOptions) -> io::Result<Self> {
+        Ok(Self::new(
+            S::with_options(options)?,
+            Recorder::new(io::stderr()),
+        ))
+    }
+
+    fn backend(&self) -> Backend {
+        self.inner.backend()
+    }
+
+    fn raw_fd(&self) -> Option<RawFd> {
+        self.inner.raw_fd()
+    }
+
+    fn register(&self, fd: RawFd, token: Token, interests: Interest) -> io::Result<()> {
+        let result = self.inner.register(fd, token, interests);
+        self.change_done(
+            &Change::Register {
+                fd,
+                token,
+                interests,
+            },
+            &result,
+        );
+        result
+    }
+
+    fn reregister(&self, fd: RawFd, token: Token, interests: Interest) -> io::Result<()> {
+        let result = self.inner.reregister(fd, token, interests);
+        self.change_done(
+            &Change::Reregister {
+                fd,
+                token,
+                interests,
+            },
+            &result,
+        );
+        result
+    }
+
+    fn rearm(&self, fd: RawFd, interests: Interest) -> io::Result<()> {
+        let result = self.inner.rearm(fd, interests);
+
+        self.recorder.line(format_args!(
+            "rearm fd={fd} interests={}{}",
+            format_interests(interests),
+            outcome(&result)
+        ));
+
+        result
+    }
+
+    fn deregister(&self, fd: RawFd) -> io::Result<()> {
+        let result = self.inner.deregister(fd);
+        self.change_done(&Change::Deregister { fd }, &result);
+        result
+    }
+
+    /// Recorded as one line per change, so that a replay can apply them one by one.
+    fn apply(&self, changes: &[Change]) -> Vec<io::Result<()>> {
+        let results = self.inner.apply(changes);
+
+        for (change, result) in changes.iter().zip(&results) {
+            self.change_done(change, result);
+        }
+
+        results
+    }
+
+    fn poll(&self, events: &mut Self::OsEvents, timeout: Option<Duration>) -> io::Result<usize> {
+        let result = self.inner.poll(events, timeout);
+        self.poll_done(events, timeout, &result);
+        result
+    }
+
+    /// Recorded as a plain `poll`, as the mask makes no difference to a replay.
+    fn poll_with_sigmask(
+        &self,
+        events: &mut Self::OsEvents,
+        timeout: Option<Duration>,
+        sigmask: &SigSet,
+    ) -> io::Result<usize> {
+        let result = self.inner.poll_with_sigmask(events, timeout, sigmask);
+        self.poll_done(events, timeout, &result);
+        result
+    }
+}
+
+impl<S> RecordingSelector<S> {
+    fn change_done(&self, change: &Change, result: &io::Result<()>) {
+        match *change {
+            Change::Register {
+                fd,
+                token,
+                interests,
+            } => self.recorder.line(format_args!(
+                "register fd={fd} token={} interests={}{}",
+                token.0,
+                format_interests(interests),
+                outcome(result)
+            )),
+            Change::Reregister {
+                fd,
+                token,
+                interests,
+            } => self.recorder.line(format_args!(
+                "reregister fd={fd} token={} interests={}{}",
+                token.0,
+                format_interests(interests),
+                outcome(result)
+            )),
+            Change::Deregister { fd } => self
+                .recorder
+                .line(format_args!("deregister fd={fd}{}", outcome(result))),
+        }
+    }
+
+    fn poll_done(&self, events: &OsEvents, timeout: Option<Duration>, result: &io::Result<usize>) {
+        let timeout = format_timeout(timeout);
+
+        match &result {
+            Ok(n) => {
+                self.recorder
+                    .line(format_args!("poll timeout={timeout} n={n}"));
+
+                for event in events.iter() {
+                    self.recorder.line(format_args!(
+                        "event token={} ready={} raw={}",
+                        { event.token().0 },
+                        format_ready(event),
+                        format_raw(event)
+                    ));
+                }
+            }
+            Err(_) => self
+                .recorder
+                .line(format_args!("poll timeout={timeout}{}", outcome(result))),
+        }
+
+        self.recorder.flush();
+    }
+}
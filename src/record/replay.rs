@@ -0,0 +1,289 @@
+//! Selector reporting the events of a recorded session.
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::os::fd::RawFd;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::interests::Interest;
+use crate::interfaces::{SelectorOptions, SysSelector, Token};
+use crate::poll::Backend;
+use crate::signal::SigSet;
+use crate::sys::{OsEvent, OsEvents};
+
+use super::{event_from_ready, invalid, parse_interests, parse_raw, MAGIC, OS, VERSION};
+
+/// Registration change, as recorded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Op {
+    Register,
+    Reregister,
+    Rearm,
+    Deregister,
+}
+
+/// Error a recorded call failed with.
+#[derive(Debug)]
+enum Failure {
+    Errno(i32),
+    /// Errors that did not come from the kernel are recorded by kind.
+    Other(String),
+}
+
+impl Failure {
+    fn parse(s: &str) -> Self {
+        match s.parse() {
+            Ok(errno) => Failure::Errno(errno),
+            Err(_) => Failure::Other(s.to_string()),
+        }
+    }
+
+    fn to_error(&self) -> io::Error {
+        match self {
+            Failure::Errno(errno) => io::Error::from_raw_os_error(*errno),
+            Failure::Other(kind) => io::Error::other(format!("recorded error: {kind}")),
+        }
+    }
+}
+
+/// A recorded call to `poll`.
+#[derive(Debug)]
+struct Wait {
+    result: Result<Vec<OsEvent>, Failure>,
+}
+
+/// A recorded session, read back from its log.
+#[derive(Debug, Default)]
+pub struct Session {
+    os: String,
+    backend: String,
+    ctl: VecDeque<(Op, Option<Failure>)>,
+    waits: VecDeque<Wait>,
+}
+
+impl Session {
+    /// Read a session from the file written by `Recorder::create`.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::read(BufReader::new(File::open(path)?))
+    }
+
+    pub fn read<R: BufRead>(reader: R) -> io::Result<Self> {
+        let mut lines = reader.lines();
+
+        let header = lines.next().ok_or_else(|| invalid("empty session"))??;
+        let header: Vec<_> = header.split_whitespace().collect();
+
+        if header.first() != Some(&MAGIC) || header.get(1) != Some(&VERSION) {
+            return Err(invalid("not a session recorded by this version"));
+        }
+
+        let mut session = Session {
+            os: field(&header, "os").unwrap_or_default().to_string(),
+            backend: field(&header, "backend").unwrap_or_default().to_string(),
+            ..Default::default()
+        };
+
+        for (number, line) in lines.enumerate() {
+            let line = line?;
+
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            session
+                .parse_line(&line)
+                .map_err(|e| invalid(format!("line {}: {e}", number + 2)))?;
+        }
+
+        Ok(session)
+    }
+
+    /// Name of the backend the session was recorded with.
+    pub fn backend(&self) -> &str {
+        &self.backend
+    }
+
+    /// Name of the OS the session was recorded on.
+    pub fn os(&self) -> &str {
+        &self.os
+    }
+
+    /// Number of recorded calls to `poll` that have not been replayed yet.
+    pub fn remaining(&self) -> usize {
+        self.waits.len()
+    }
+
+    fn parse_line(&mut self, line: &str) -> io::Result<()> {
+        let fields: Vec<_> = line.split_whitespace().collect();
+        let failure = field(&fields, "err").map(Failure::parse);
+
+        // first field is the timestamp, which replay does not need
+        let op = match fields.get(1).copied() {
+            Some("register") => Op::Register,
+            Some("reregister") => Op::Reregister,
+            Some("rearm") => Op::Rearm,
+            Some("deregister") => Op::Deregister,
+
+            Some("poll") => {
+                let result = match failure {
+                    Some(failure) => Err(failure),
+                    None => Ok(Vec::new()),
+                };
+
+                self.waits.push_back(Wait { result });
+                return Ok(());
+            }
+
+            Some("event") => {
+                let event = self.parse_event(&fields)?;
+
+                return match self.waits.back_mut() {
+                    Some(Wait { result: Ok(events) }) => {
+                        events.push(event);
+                        Ok(())
+                    }
+                    _ => Err(invalid("event outside of a successful poll")),
+                };
+            }
+
+            Some(other) => return Err(invalid(format!("unknown entry: {other}"))),
+            None => return Err(invalid("missing entry")),
+        };
+
+        if let Some(interests) = field(&fields, "interests") {
+            // validated, even though replay does not need them
+            parse_interests(interests)?;
+        }
+
+        self.ctl.push_back((op, failure));
+        Ok(())
+    }
+
+    fn parse_event(&self, fields: &[&str]) -> io::Result<OsEvent> {
+        let token = field(fields, "token")
+            .and_then(|token| token.parse().ok())
+            .map(Token)
+            .ok_or_else(|| invalid("event without a token"))?;
+
+        match (field(fields, "raw"), field(fields, "ready")) {
+            (Some(raw), _) if self.os == OS => parse_raw(raw, token),
+            (_, Some(ready)) => event_from_ready(ready, token),
+            _ => Err(invalid("event without readiness")),
+        }
+    }
+}
+
+/// Value of a `key=value` field.
+fn field<'a>(fields: &[&'a str], key: &str) -> Option<&'a str> {
+    fields.iter().find_map(|field| {
+        field
+            .strip_prefix(key)
+            .and_then(|rest| rest.strip_prefix('='))
+    })
+}
+
+/// Reports the events of a recorded session, one recorded call to `poll` at a time.
+///
+/// `poll` never blocks and returns `UnexpectedEof` once the session has been replayed.
+/// Registration changes are not checked against the recording, as file descriptors
+/// differ between runs. A change fails only when the next recorded change is the same
+/// operation and failed when recorded.
+pub struct ReplaySelector {
+    session: Mutex<Session>,
+}
+
+impl ReplaySelector {
+    pub fn new(session: Session) -> Self {
+        ReplaySelector {
+            session: Mutex::new(session),
+        }
+    }
+
+    fn ctl(&self, op: Op) -> io::Result<()> {
+        let mut session = self.session.lock().unwrap();
+
+        match session.ctl.front() {
+            Some((recorded, _)) if *recorded == op => {}
+            // diverged from the recording
+            _ => return Ok(()),
+        }
+
+        match session.ctl.pop_front() {
+            Some((_, Some(failure))) => Err(failure.to_error()),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl SysSelector for ReplaySelector {
+    type OsEvent = OsEvent;
+    type OsEvents = OsEvents;
+
+    /// Replays an empty session, use `PollBuilder::replay` instead.
+    fn with_options(_options: SelectorOptions) -> io::Result<Self> {
+        Ok(Self::new(Session::default()))
+    }
+
+    fn backend(&self) -> Backend {
+        Backend::Replay
+    }
+
+    fn register(&self, _fd: RawFd, _token: Token, _interests: Interest) -> io::Result<()> {
+        self.ctl(Op::Register)
+    }
+
+    fn reregister(&self, _fd: RawFd, _token: Token, _interests: Interest) -> io::Result<()> {
+        self.ctl(Op::Reregister)
+    }
+
+    fn rearm(&self, _fd: RawFd, _interests: Interest) -> io::Result<()> {
+        self.ctl(Op::Rearm)
+    }
+
+    fn deregister(&self, _fd: RawFd) -> io::Result<()> {
+        self.ctl(Op::Deregister)
+    }
+
+    /// Nothing blocks, so there is no wait for the mask to apply to.
+    fn poll_with_sigmask(
+        &self,
+        events: &mut Self::OsEvents,
+        timeout: Option<Duration>,
+        _sigmask: &SigSet,
+    ) -> io::Result<usize> {
+        self.poll(events, timeout)
+    }
+
+    fn poll(&self, events: &mut Self::OsEvents, _timeout: Option<Duration>) -> io::Result<usize> {
+        events.clear();
+
+        // same error epoll_wait returns for a maxevents of zero
+        if events.capacity() == 0 {
+            return Err(io::Error::from_raw_os_error(libc::EINVAL));
+        }
+
+        let mut session = self.session.lock().unwrap();
+
+        let wait = session.waits.pop_front().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::UnexpectedEof, "end of recorded session")
+        })?;
+
+        let mut batch = match wait.result {
+            Ok(batch) => batch,
+            Err(failure) => return Err(failure.to_error()),
+        };
+
+        if batch.len() > events.capacity() {
+            // only possible if replayed with a smaller capacity than recorded with
+            let rest = batch.split_off(events.capacity());
+            session.waits.push_front(Wait { result: Ok(rest) });
+        }
+
+        events.extend(batch);
+
+        Ok(events.len())
+    }
+}
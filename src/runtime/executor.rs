@@ -0,0 +1,319 @@
+//! The executor half of the runtime.
+//!
+//! Tasks are stored on the thread that called `block_on` and are identified by an id.
+//! Wakers only carry that id and push it onto a shared run queue, which makes them safe
+//! to send to and wake from other threads. If the executor is blocked in the reactor at
+//! the time, a byte is written to a socket registered with the reactor to unblock it.
+
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::io::Write;
+use std::os::unix::net::UnixStream;
+use std::pin::{pin, Pin};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Wake, Waker};
+use std::time::Duration;
+
+use super::reactor::Reactor;
+
+/// Id used for the future passed to `block_on`.
+const MAIN_TASK: usize = usize::MAX;
+
+type Task = Pin<Box<dyn Future<Output = ()>>>;
+
+thread_local! {
+    static CURRENT: RefCell<Option<Rc<Runtime>>> = const { RefCell::new(None) };
+}
+
+/// State shared with every `Waker`, potentially across threads.
+struct Shared {
+    /// Ids of tasks that have been woken and need to be polled.
+    queue: Mutex<VecDeque<usize>>,
+    /// Set once a byte has been written to `wake_tx` and not yet consumed.
+    notified: AtomicBool,
+    /// Write half of the socket the reactor listens on.
+    wake_tx: UnixStream,
+}
+
+impl Shared {
+    fn schedule(&self, id: usize) {
+        self.queue.lock().unwrap().push_back(id);
+
+        if !self.notified.swap(true, Ordering::AcqRel) {
+            // a full buffer means the reactor will be woken anyway
+            let _ = (&self.wake_tx).write(&[1]);
+        }
+    }
+
+    fn take_ready(&self) -> VecDeque<usize> {
+        std::mem::take(&mut *self.queue.lock().unwrap())
+    }
+
+    fn is_idle(&self) -> bool {
+        self.queue.lock().unwrap().is_empty()
+    }
+}
+
+struct TaskWaker {
+    id: usize,
+    shared: Arc<Shared>,
+}
+
+impl Wake for TaskWaker {
+    fn wake(self: Arc<Self>) {
+        self.shared.schedule(self.id)
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.shared.schedule(self.id)
+    }
+}
+
+pub(crate) struct Runtime {
+    pub(crate) reactor: Rc<Reactor>,
+    shared: Arc<Shared>,
+    tasks: RefCell<HashMap<usize, Task>>,
+    next_id: Cell<usize>,
+}
+
+impl Runtime {
+    fn new() -> std::io::Result<Self> {
+        let (wake_tx, wake_rx) = UnixStream::pair()?;
+        wake_tx.set_nonblocking(true)?;
+
+        Ok(Self {
+            reactor: Rc::new(Reactor::new(wake_rx)?),
+            shared: Arc::new(Shared {
+                queue: Mutex::new(VecDeque::new()),
+                notified: AtomicBool::new(false),
+                wake_tx,
+            }),
+            tasks: RefCell::new(HashMap::new()),
+            next_id: Cell::new(0),
+        })
+    }
+
+    /// Return the runtime of the `block_on` call currently running on this thread.
+    pub(crate) fn current() -> Option<Rc<Runtime>> {
+        CURRENT.with(|current| current.borrow().clone())
+    }
+
+    fn waker(&self, id: usize) -> Waker {
+        Waker::from(Arc::new(TaskWaker {
+            id,
+            shared: self.shared.clone(),
+        }))
+    }
+
+    fn spawn(&self, task: Task) {
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+
+        self.tasks.borrow_mut().insert(id, task);
+        self.shared.schedule(id);
+    }
+
+    fn run_task(&self, id: usize) {
+        // Remove the task while it is polled, so it can spawn other tasks.
+        // A missing task has already completed, and this is a stale wake up.
+        let Some(mut task) = self.tasks.borrow_mut().remove(&id) else {
+            return;
+        };
+
+        let waker = self.waker(id);
+        let mut cx = Context::from_waker(&waker);
+
+        if task.as_mut().poll(&mut cx).is_pending() {
+            self.tasks.borrow_mut().insert(id, task);
+        }
+    }
+
+    /// Drive the reactor, blocking only if no task is waiting to run.
+    fn park(&self) {
+        let timeout = if self.shared.is_idle() {
+            None
+        } else {
+            Some(Duration::ZERO)
+        };
+
+        match self.reactor.turn(timeout) {
+            Ok(true) => self.shared.notified.store(false, Ordering::Release),
+            Ok(false) => {}
+            Err(e) => panic!("runtime reactor failed: {e}"),
+        }
+    }
+}
+
+/// Resets the thread local runtime when `block_on` returns or unwinds.
+struct EnterGuard;
+
+impl Drop for EnterGuard {
+    fn drop(&mut self) {
+        // Take the runtime out first, dropping tasks can access the thread local.
+        let runtime = CURRENT.with(|current| current.borrow_mut().take());
+        drop(runtime);
+    }
+}
+
+/// Run a future to completion on the current thread.
+///
+/// Tasks started via `spawn` are polled alongside `future` and are dropped once it
+/// completes, whether they have finished or not.
+///
+/// # Panics
+///
+/// When called from within another `block_on` call, or if the reactor fails.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let runtime = Rc::new(Runtime::new().expect("failed to create runtime"));
+
+    CURRENT.with(|current| {
+        let mut current = current.borrow_mut();
+        assert!(current.is_none(), "cannot call `block_on` within a runtime");
+        *current = Some(runtime.clone());
+    });
+
+    let _guard = EnterGuard;
+
+    let mut future = pin!(future);
+    let waker = runtime.waker(MAIN_TASK);
+    let mut cx = Context::from_waker(&waker);
+
+    runtime.shared.schedule(MAIN_TASK);
+
+    loop {
+        for id in runtime.shared.take_ready() {
+            if id != MAIN_TASK {
+                runtime.run_task(id);
+            } else if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+        }
+
+        runtime.park();
+    }
+}
+
+/// Spawn a task onto the runtime of the current thread.
+///
+/// The returned `JoinHandle` can be awaited for the task's output. Dropping it detaches
+/// the task, which keeps running in the background.
+///
+/// # Panics
+///
+/// When called outside of `block_on`.
+pub fn spawn<F>(future: F) -> JoinHandle<F::Output>
+where
+    F: Future + 'static,
+{
+    let runtime = Runtime::current().expect("`spawn` called outside of `block_on`");
+
+    let state = Rc::new(RefCell::new(JoinState {
+        output: None,
+        waker: None,
+    }));
+
+    let task_state = state.clone();
+
+    runtime.spawn(Box::pin(async move {
+        let output = future.await;
+
+        let mut state = task_state.borrow_mut();
+        state.output = Some(output);
+
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+    }));
+
+    JoinHandle { state }
+}
+
+struct JoinState<T> {
+    output: Option<T>,
+    waker: Option<Waker>,
+}
+
+/// Handle to a spawned task, resolving to the task's output.
+pub struct JoinHandle<T> {
+    state: Rc<RefCell<JoinState<T>>>,
+}
+
+impl<T> Future for JoinHandle<T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        let mut state = self.state.borrow_mut();
+
+        match state.output.take() {
+            Some(output) => Poll::Ready(output),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns `Pending` once, waking itself from another thread.
+    struct WakeFromThread(bool);
+
+    impl Future for WakeFromThread {
+        type Output = ();
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+            if self.0 {
+                return Poll::Ready(());
+            }
+
+            self.0 = true;
+            let waker = cx.waker().clone();
+            std::thread::spawn(move || {
+                std::thread::sleep(Duration::from_millis(10));
+                waker.wake();
+            });
+
+            Poll::Pending
+        }
+    }
+
+    #[test]
+    fn block_on_returns_output() {
+        assert_eq!(block_on(async { 1 + 1 }), 2);
+    }
+
+    #[test]
+    fn spawned_tasks_can_be_joined() {
+        let output = block_on(async {
+            let handles: Vec<_> = (0..10).map(|i| spawn(async move { i * 2 })).collect();
+
+            let mut sum = 0;
+            for handle in handles {
+                sum += handle.await;
+            }
+            sum
+        });
+
+        assert_eq!(output, 90);
+    }
+
+    #[test]
+    fn wake_from_another_thread() {
+        block_on(async {
+            spawn(WakeFromThread(false)).await;
+            WakeFromThread(false).await;
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "outside of `block_on`")]
+    fn spawn_outside_runtime() {
+        spawn(async {});
+    }
+}
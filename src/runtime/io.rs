@@ -0,0 +1,233 @@
+//! Attach any `Source` to the reactor and await its readiness.
+
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use crate::interfaces::Token;
+use crate::poll::Source;
+
+use super::executor::Runtime;
+use super::reactor::{Direction, Reactor, ScheduledIo};
+
+/// A source registered with the reactor of the current runtime.
+///
+/// The source is registered for both read and write readiness in edge-triggered mode.
+/// Once a readiness future has resolved, the source stays ready until the matching
+/// `clear_*` method is called. This should only be done after an operation on the
+/// source has returned `WouldBlock`, otherwise the next edge might never come.
+///
+/// The source is deregistered when this is dropped.
+pub struct AsyncFd<S: Source> {
+    inner: Option<S>,
+    token: Token,
+    io: Rc<ScheduledIo>,
+    reactor: Rc<Reactor>,
+}
+
+impl<S: Source> AsyncFd<S> {
+    /// Register `inner` with the reactor of the current runtime.
+    ///
+    /// The source should be set to non-blocking mode beforehand. Returns an error when
+    /// called outside of `block_on`.
+    pub fn new(inner: S) -> io::Result<Self> {
+        let runtime = Runtime::current()
+            .ok_or_else(|| io::Error::other("`AsyncFd` must be created from within `block_on`"))?;
+
+        let reactor = runtime.reactor.clone();
+        let (token, io) = reactor.register(&inner)?;
+
+        Ok(Self {
+            inner: Some(inner),
+            token,
+            io,
+            reactor,
+        })
+    }
+
+    /// Token the source has been registered with.
+    pub fn token(&self) -> Token {
+        self.token
+    }
+
+    pub fn get_ref(&self) -> &S {
+        self.inner.as_ref().unwrap()
+    }
+
+    pub fn get_mut(&mut self) -> &mut S {
+        self.inner.as_mut().unwrap()
+    }
+
+    /// Deregister the source and return it.
+    pub fn into_inner(mut self) -> S {
+        let inner = self.inner.take().unwrap();
+        let _ = self.reactor.deregister(&inner, self.token);
+        inner
+    }
+
+    /// Resolves once the source is readable, or has been closed for reading.
+    pub fn readable(&self) -> Readiness<'_> {
+        Readiness {
+            io: &self.io,
+            direction: Direction::Read,
+        }
+    }
+
+    /// Resolves once the source is writable, or has been closed for writing.
+    pub fn writable(&self) -> Readiness<'_> {
+        Readiness {
+            io: &self.io,
+            direction: Direction::Write,
+        }
+    }
+
+    pub fn poll_read_ready(&self, cx: &mut Context<'_>) -> Poll<()> {
+        poll_ready(&self.io, Direction::Read, cx)
+    }
+
+    pub fn poll_write_ready(&self, cx: &mut Context<'_>) -> Poll<()> {
+        poll_ready(&self.io, Direction::Write, cx)
+    }
+
+    /// Run `op` on the source until it stops returning `WouldBlock`, waiting for read
+    /// readiness before each attempt.
+    ///
+    /// Readiness is cleared every time `op` returns `WouldBlock`, as in edge-triggered
+    /// mode the source must be drained before a new event is delivered.
+    pub async fn read_with<R>(&self, mut op: impl FnMut(&S) -> io::Result<R>) -> io::Result<R> {
+        loop {
+            self.readable().await;
+
+            match op(self.get_ref()) {
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => self.clear_readable(),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                res => return res,
+            }
+        }
+    }
+
+    /// Run `op` on the source until it stops returning `WouldBlock`, waiting for write
+    /// readiness before each attempt.
+    pub async fn write_with<R>(&self, mut op: impl FnMut(&S) -> io::Result<R>) -> io::Result<R> {
+        loop {
+            self.writable().await;
+
+            match op(self.get_ref()) {
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => self.clear_writable(),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                res => return res,
+            }
+        }
+    }
+
+    /// Forget the cached read readiness, the next `readable` waits for a new event.
+    pub fn clear_readable(&self) {
+        self.io.clear(Direction::Read);
+        self.reactor.rearm(self.get_ref(), Direction::Read);
+    }
+
+    /// Forget the cached write readiness, the next `writable` waits for a new event.
+    pub fn clear_writable(&self) {
+        self.io.clear(Direction::Write);
+        self.reactor.rearm(self.get_ref(), Direction::Write);
+    }
+}
+
+impl<S: Source> Drop for AsyncFd<S> {
+    fn drop(&mut self) {
+        if let Some(inner) = self.inner.take() {
+            let _ = self.reactor.deregister(&inner, self.token);
+        }
+    }
+}
+
+fn poll_ready(io: &ScheduledIo, direction: Direction, cx: &mut Context<'_>) -> Poll<()> {
+    if io.is_ready(direction) {
+        return Poll::Ready(());
+    }
+
+    io.set_waker(direction, cx.waker());
+    Poll::Pending
+}
+
+/// Future returned by `AsyncFd::readable` and `AsyncFd::writable`.
+pub struct Readiness<'a> {
+    io: &'a ScheduledIo,
+    direction: Direction,
+}
+
+impl Future for Readiness<'_> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        poll_ready(self.io, self.direction, cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::os::unix::net::UnixStream;
+
+    use super::*;
+    use crate::runtime::{block_on, spawn};
+
+    #[test]
+    fn new_outside_runtime() {
+        let (a, _b) = UnixStream::pair().unwrap();
+        assert!(AsyncFd::new(a).is_err());
+    }
+
+    #[test]
+    fn readable_after_peer_writes() {
+        let received = block_on(async {
+            let (a, b) = UnixStream::pair().unwrap();
+            a.set_nonblocking(true).unwrap();
+            b.set_nonblocking(true).unwrap();
+
+            let reader = AsyncFd::new(a).unwrap();
+            let writer = AsyncFd::new(b).unwrap();
+
+            let handle = spawn(async move {
+                let mut buf = [0u8; 5];
+                reader.readable().await;
+                reader.get_ref().read_exact(&mut buf).unwrap();
+                buf
+            });
+
+            writer.writable().await;
+            writer.get_ref().write_all(b"hello").unwrap();
+
+            handle.await
+        });
+
+        assert_eq!(&received, b"hello");
+    }
+
+    #[test]
+    fn readiness_is_cached_until_cleared() {
+        block_on(async {
+            let (a, mut b) = UnixStream::pair().unwrap();
+            a.set_nonblocking(true).unwrap();
+            b.write_all(b"x").unwrap();
+
+            let a = AsyncFd::new(a).unwrap();
+
+            a.readable().await;
+            // still ready, without a new edge from the kernel
+            a.readable().await;
+
+            let mut buf = [0u8; 8];
+            assert_eq!(a.get_ref().read(&mut buf).unwrap(), 1);
+            let err = a.get_ref().read(&mut buf).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::WouldBlock);
+            a.clear_readable();
+
+            assert!(a
+                .poll_read_ready(&mut Context::from_waker(std::task::Waker::noop()))
+                .is_pending());
+        });
+    }
+}
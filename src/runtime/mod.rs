@@ -0,0 +1,33 @@
+//! Minimal single-threaded async runtime built on top of `Poll`.
+//!
+//! Enabled via the `runtime` cargo feature. It is made up of two halves:
+//!
+//! - `executor`: runs futures to completion on the current thread via [`block_on`], and allows
+//!   additional tasks to be started with [`spawn`].
+//! - `reactor`: owns the `Poll` instance and maps every `Token` to the `Waker`s of the tasks
+//!   waiting on that source. It is only driven when no task is runnable.
+//!
+//! Sources are attached to the reactor by wrapping them in an [`AsyncFd`], which exposes
+//! futures that resolve once the source is readable or writable. The [`net`] module builds
+//! async TCP and Unix socket types on top of it.
+//!
+//! ```no_run
+//! use mini_mio::runtime::{self, AsyncFd};
+//! use std::os::unix::net::UnixStream;
+//!
+//! runtime::block_on(async {
+//!     let (a, _b) = UnixStream::pair().unwrap();
+//!     a.set_nonblocking(true).unwrap();
+//!
+//!     let a = AsyncFd::new(a).unwrap();
+//!     a.writable().await;
+//! });
+//! ```
+
+mod executor;
+mod io;
+pub mod net;
+mod reactor;
+
+pub use executor::{block_on, spawn, JoinHandle};
+pub use io::{AsyncFd, Readiness};
@@ -0,0 +1,15 @@
+//! Async TCP and Unix domain sockets.
+//!
+//! Every type wraps its `std` counterpart in an `AsyncFd`. Operations first wait for the
+//! reactor to report the socket ready, then keep being attempted while it stays ready.
+//! Once the socket returns `WouldBlock`, the cached readiness is cleared and the next
+//! attempt waits for a new event. This is the same drain loop the blocking examples
+//! write out by hand for edge-triggered mode.
+
+mod socket;
+mod stream;
+mod tcp;
+mod unix;
+
+pub use tcp::{TcpListener, TcpStream};
+pub use unix::{UnixListener, UnixStream};
@@ -0,0 +1,110 @@
+//! Non-blocking socket creation and connect.
+//!
+//! `std` only offers a blocking `connect`, so sockets that need to connect
+//! asynchronously are created here via `libc` and switched to non-blocking mode before
+//! `connect` is called.
+
+use std::io;
+use std::mem;
+use std::net::SocketAddr;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+/// Create a non-blocking, close-on-exec socket and start connecting it to `addr`.
+///
+/// Returns the socket once `connect` has either completed or is in progress, the caller
+/// must wait for the socket to become writable and check `SO_ERROR` in the latter case.
+pub(super) fn connect_inet(addr: &SocketAddr) -> io::Result<OwnedFd> {
+    match addr {
+        SocketAddr::V4(addr) => {
+            let mut raw: libc::sockaddr_in = unsafe { mem::zeroed() };
+            raw.sin_family = libc::AF_INET as libc::sa_family_t;
+            raw.sin_port = addr.port().to_be();
+            raw.sin_addr = libc::in_addr {
+                s_addr: u32::from_ne_bytes(addr.ip().octets()),
+            };
+
+            connect(libc::AF_INET, &raw)
+        }
+        SocketAddr::V6(addr) => {
+            let mut raw: libc::sockaddr_in6 = unsafe { mem::zeroed() };
+            raw.sin6_family = libc::AF_INET6 as libc::sa_family_t;
+            raw.sin6_port = addr.port().to_be();
+            raw.sin6_flowinfo = addr.flowinfo();
+            raw.sin6_addr.s6_addr = addr.ip().octets();
+            raw.sin6_scope_id = addr.scope_id();
+
+            connect(libc::AF_INET6, &raw)
+        }
+    }
+}
+
+/// Unix domain socket equivalent of `connect_inet`.
+pub(super) fn connect_unix(path: &Path) -> io::Result<OwnedFd> {
+    let mut raw: libc::sockaddr_un = unsafe { mem::zeroed() };
+    raw.sun_family = libc::AF_UNIX as libc::sa_family_t;
+
+    let bytes = path.as_os_str().as_bytes();
+
+    // leave room for the trailing nul byte
+    if bytes.len() >= raw.sun_path.len() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "path must be shorter than SUN_LEN",
+        ));
+    }
+
+    for (dst, src) in raw.sun_path.iter_mut().zip(bytes) {
+        *dst = *src as libc::c_char;
+    }
+
+    connect(libc::AF_UNIX, &raw)
+}
+
+fn connect<T>(domain: libc::c_int, addr: &T) -> io::Result<OwnedFd> {
+    let ret = unsafe { libc::socket(domain, libc::SOCK_STREAM, 0) };
+
+    if ret < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    // closed on any of the early returns below
+    let fd = unsafe { OwnedFd::from_raw_fd(ret) };
+
+    set_flag(&fd, libc::F_GETFD, libc::F_SETFD, libc::FD_CLOEXEC)?;
+    set_flag(&fd, libc::F_GETFL, libc::F_SETFL, libc::O_NONBLOCK)?;
+
+    let ret = unsafe {
+        libc::connect(
+            fd.as_raw_fd(),
+            addr as *const T as *const libc::sockaddr,
+            mem::size_of::<T>() as libc::socklen_t,
+        )
+    };
+
+    if ret < 0 {
+        let err = io::Error::last_os_error();
+
+        // connection will complete asynchronously
+        if err.raw_os_error() != Some(libc::EINPROGRESS) {
+            return Err(err);
+        }
+    }
+
+    Ok(fd)
+}
+
+fn set_flag(fd: &OwnedFd, get: libc::c_int, set: libc::c_int, flag: libc::c_int) -> io::Result<()> {
+    let flags = unsafe { libc::fcntl(fd.as_raw_fd(), get) };
+
+    if flags < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    if unsafe { libc::fcntl(fd.as_raw_fd(), set, flags | flag) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,71 @@
+//! Read and write operations shared by `TcpStream` and `UnixStream`.
+
+use std::io::{self, Read, Write};
+
+use crate::poll::Source;
+use crate::runtime::AsyncFd;
+
+pub(super) async fn read<S>(io: &AsyncFd<S>, buf: &mut [u8]) -> io::Result<usize>
+where
+    S: Source,
+    for<'a> &'a S: Read,
+{
+    io.read_with(|mut inner| inner.read(buf)).await
+}
+
+pub(super) async fn write<S>(io: &AsyncFd<S>, buf: &[u8]) -> io::Result<usize>
+where
+    S: Source,
+    for<'a> &'a S: Write,
+{
+    io.write_with(|mut inner| inner.write(buf)).await
+}
+
+pub(super) async fn read_exact<S>(io: &AsyncFd<S>, mut buf: &mut [u8]) -> io::Result<()>
+where
+    S: Source,
+    for<'a> &'a S: Read,
+{
+    while !buf.is_empty() {
+        match read(io, buf).await? {
+            0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+            n => buf = &mut buf[n..],
+        }
+    }
+
+    Ok(())
+}
+
+pub(super) async fn write_all<S>(io: &AsyncFd<S>, mut buf: &[u8]) -> io::Result<()>
+where
+    S: Source,
+    for<'a> &'a S: Write,
+{
+    while !buf.is_empty() {
+        match write(io, buf).await? {
+            0 => return Err(io::ErrorKind::WriteZero.into()),
+            n => buf = &buf[n..],
+        }
+    }
+
+    Ok(())
+}
+
+/// Wait for an in progress `connect` to complete.
+///
+/// The socket becomes writable once the connection has been established or has failed,
+/// the outcome is then read from `SO_ERROR`.
+pub(super) async fn connected<S>(
+    io: &AsyncFd<S>,
+    take_error: impl Fn(&S) -> io::Result<Option<io::Error>>,
+) -> io::Result<()>
+where
+    S: Source,
+{
+    io.writable().await;
+
+    match take_error(io.get_ref())? {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
+}
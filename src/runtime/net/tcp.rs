@@ -0,0 +1,173 @@
+use std::io;
+use std::net::{self, Shutdown, SocketAddr, ToSocketAddrs};
+
+use crate::runtime::AsyncFd;
+
+use super::{socket, stream};
+
+/// Async TCP listener.
+pub struct TcpListener {
+    io: AsyncFd<net::TcpListener>,
+}
+
+impl TcpListener {
+    /// Bind a new listener to `addr`.
+    ///
+    /// Binding never blocks, so this is not an async function.
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        Self::from_std(net::TcpListener::bind(addr)?)
+    }
+
+    /// Register a `std` listener with the current runtime.
+    pub fn from_std(listener: net::TcpListener) -> io::Result<Self> {
+        listener.set_nonblocking(true)?;
+
+        Ok(Self {
+            io: AsyncFd::new(listener)?,
+        })
+    }
+
+    /// Accept a new incoming connection.
+    pub async fn accept(&self) -> io::Result<(TcpStream, SocketAddr)> {
+        let (stream, addr) = self.io.read_with(|listener| listener.accept()).await?;
+
+        Ok((TcpStream::from_std(stream)?, addr))
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.io.get_ref().local_addr()
+    }
+}
+
+/// Async TCP stream.
+pub struct TcpStream {
+    io: AsyncFd<net::TcpStream>,
+}
+
+impl TcpStream {
+    /// Open a connection to `addr`.
+    pub async fn connect(addr: SocketAddr) -> io::Result<Self> {
+        let stream = net::TcpStream::from(socket::connect_inet(&addr)?);
+        let io = AsyncFd::new(stream)?;
+
+        stream::connected(&io, net::TcpStream::take_error).await?;
+
+        Ok(Self { io })
+    }
+
+    /// Register a `std` stream with the current runtime.
+    pub fn from_std(stream: net::TcpStream) -> io::Result<Self> {
+        stream.set_nonblocking(true)?;
+
+        Ok(Self {
+            io: AsyncFd::new(stream)?,
+        })
+    }
+
+    /// Read into `buf`, returning the number of bytes read. Zero means end of stream.
+    pub async fn read(&self, buf: &mut [u8]) -> io::Result<usize> {
+        stream::read(&self.io, buf).await
+    }
+
+    /// Write from `buf`, returning the number of bytes written.
+    pub async fn write(&self, buf: &[u8]) -> io::Result<usize> {
+        stream::write(&self.io, buf).await
+    }
+
+    /// Read exactly enough bytes to fill `buf`.
+    pub async fn read_exact(&self, buf: &mut [u8]) -> io::Result<()> {
+        stream::read_exact(&self.io, buf).await
+    }
+
+    /// Write the whole of `buf`.
+    pub async fn write_all(&self, buf: &[u8]) -> io::Result<()> {
+        stream::write_all(&self.io, buf).await
+    }
+
+    pub fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        self.io.get_ref().shutdown(how)
+    }
+
+    pub fn set_nodelay(&self, nodelay: bool) -> io::Result<()> {
+        self.io.get_ref().set_nodelay(nodelay)
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.io.get_ref().local_addr()
+    }
+
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.io.get_ref().peer_addr()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::{block_on, spawn};
+
+    #[test]
+    fn echo() {
+        let reply = block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let addr = listener.local_addr().unwrap();
+
+            spawn(async move {
+                let (stream, _) = listener.accept().await.unwrap();
+                let mut buf = [0u8; 4];
+                stream.read_exact(&mut buf).await.unwrap();
+                stream.write_all(&buf).await.unwrap();
+            });
+
+            let stream = TcpStream::connect(addr).await.unwrap();
+            stream.write_all(b"ping").await.unwrap();
+
+            let mut buf = [0u8; 4];
+            stream.read_exact(&mut buf).await.unwrap();
+
+            // server task dropped its end, so the stream is now at eof
+            assert_eq!(stream.read(&mut [0u8; 1]).await.unwrap(), 0);
+            buf
+        });
+
+        assert_eq!(&reply, b"ping");
+    }
+
+    #[test]
+    fn write_all_larger_than_socket_buffer() {
+        const LEN: usize = 8 * 1024 * 1024;
+
+        let received = block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let addr = listener.local_addr().unwrap();
+
+            let reader = spawn(async move {
+                let (stream, _) = listener.accept().await.unwrap();
+                let mut buf = vec![0u8; LEN];
+                stream.read_exact(&mut buf).await.unwrap();
+                buf
+            });
+
+            let stream = TcpStream::connect(addr).await.unwrap();
+            stream.write_all(&vec![7u8; LEN]).await.unwrap();
+
+            reader.await
+        });
+
+        assert!(received.iter().all(|b| *b == 7));
+    }
+
+    #[test]
+    fn connect_refused() {
+        block_on(async {
+            // bind and drop to get a port that nothing listens on
+            let addr = net::TcpListener::bind("127.0.0.1:0")
+                .unwrap()
+                .local_addr()
+                .unwrap();
+
+            let err = TcpStream::connect(addr).await.err().unwrap();
+            assert_eq!(err.kind(), io::ErrorKind::ConnectionRefused);
+        });
+    }
+}
@@ -0,0 +1,146 @@
+use std::io;
+use std::net::Shutdown;
+use std::os::unix::net::{self, SocketAddr};
+use std::path::Path;
+
+use crate::runtime::AsyncFd;
+
+use super::{socket, stream};
+
+/// Async Unix domain socket listener.
+pub struct UnixListener {
+    io: AsyncFd<net::UnixListener>,
+}
+
+impl UnixListener {
+    /// Bind a new listener to the socket at `path`.
+    pub fn bind<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::from_std(net::UnixListener::bind(path)?)
+    }
+
+    /// Register a `std` listener with the current runtime.
+    pub fn from_std(listener: net::UnixListener) -> io::Result<Self> {
+        listener.set_nonblocking(true)?;
+
+        Ok(Self {
+            io: AsyncFd::new(listener)?,
+        })
+    }
+
+    /// Accept a new incoming connection.
+    pub async fn accept(&self) -> io::Result<(UnixStream, SocketAddr)> {
+        let (stream, addr) = self.io.read_with(|listener| listener.accept()).await?;
+
+        Ok((UnixStream::from_std(stream)?, addr))
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.io.get_ref().local_addr()
+    }
+}
+
+/// Async Unix domain stream socket.
+pub struct UnixStream {
+    io: AsyncFd<net::UnixStream>,
+}
+
+impl UnixStream {
+    /// Connect to the socket at `path`.
+    pub async fn connect<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let stream = net::UnixStream::from(socket::connect_unix(path.as_ref())?);
+        let io = AsyncFd::new(stream)?;
+
+        stream::connected(&io, net::UnixStream::take_error).await?;
+
+        Ok(Self { io })
+    }
+
+    /// Create a pair of connected sockets.
+    pub fn pair() -> io::Result<(Self, Self)> {
+        let (a, b) = net::UnixStream::pair()?;
+        Ok((Self::from_std(a)?, Self::from_std(b)?))
+    }
+
+    /// Register a `std` stream with the current runtime.
+    pub fn from_std(stream: net::UnixStream) -> io::Result<Self> {
+        stream.set_nonblocking(true)?;
+
+        Ok(Self {
+            io: AsyncFd::new(stream)?,
+        })
+    }
+
+    /// Read into `buf`, returning the number of bytes read. Zero means end of stream.
+    pub async fn read(&self, buf: &mut [u8]) -> io::Result<usize> {
+        stream::read(&self.io, buf).await
+    }
+
+    /// Write from `buf`, returning the number of bytes written.
+    pub async fn write(&self, buf: &[u8]) -> io::Result<usize> {
+        stream::write(&self.io, buf).await
+    }
+
+    /// Read exactly enough bytes to fill `buf`.
+    pub async fn read_exact(&self, buf: &mut [u8]) -> io::Result<()> {
+        stream::read_exact(&self.io, buf).await
+    }
+
+    /// Write the whole of `buf`.
+    pub async fn write_all(&self, buf: &[u8]) -> io::Result<()> {
+        stream::write_all(&self.io, buf).await
+    }
+
+    pub fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        self.io.get_ref().shutdown(how)
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.io.get_ref().local_addr()
+    }
+
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.io.get_ref().peer_addr()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::{block_on, spawn};
+
+    #[test]
+    fn connect_and_accept() {
+        let dir = tempdir::TempDir::new("mini-mio").unwrap();
+        let path = dir.path().join("sock");
+
+        let reply = block_on(async move {
+            let listener = UnixListener::bind(&path).unwrap();
+
+            spawn(async move {
+                let (stream, _) = listener.accept().await.unwrap();
+                stream.write_all(b"hello").await.unwrap();
+            });
+
+            let stream = UnixStream::connect(&path).await.unwrap();
+            let mut buf = [0u8; 5];
+            stream.read_exact(&mut buf).await.unwrap();
+            buf
+        });
+
+        assert_eq!(&reply, b"hello");
+    }
+
+    #[test]
+    fn read_exact_on_early_eof() {
+        block_on(async {
+            let (a, b) = UnixStream::pair().unwrap();
+
+            a.write_all(b"abc").await.unwrap();
+            a.shutdown(Shutdown::Write).unwrap();
+
+            let mut buf = [0u8; 8];
+            let err = b.read_exact(&mut buf).await.unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        });
+    }
+}
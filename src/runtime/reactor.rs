@@ -0,0 +1,232 @@
+//! The reactor half of the runtime.
+//!
+//! Owns the `Poll` instance and a table of `ScheduledIo` entries, one per registered
+//! source. When the executor runs out of work it calls `Reactor::turn`, which blocks in
+//! `Poll::poll`, records the returned readiness against each token and wakes the tasks
+//! waiting on it.
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::io::{self, Read};
+use std::os::unix::net::UnixStream;
+use std::rc::Rc;
+use std::task::Waker;
+use std::time::Duration;
+
+use crate::error::Error;
+use crate::interests::Interest;
+use crate::interfaces::{Events, SysEvent, Token};
+use crate::poll::{Poll, Source};
+
+/// Token reserved for the executor's wake-up socket.
+pub(crate) const WAKE_TOKEN: Token = Token(usize::MAX);
+
+/// Number of events retrieved per call to `Poll::poll`.
+const EVENTS_CAPACITY: usize = 1024;
+
+/// Readiness bit for the read direction.
+pub(crate) const READ: u8 = 1;
+/// Readiness bit for the write direction.
+pub(crate) const WRITE: u8 = 1 << 1;
+
+/// Direction a task is waiting on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Direction {
+    Read,
+    Write,
+}
+
+impl Direction {
+    fn mask(self) -> u8 {
+        match self {
+            Direction::Read => READ,
+            Direction::Write => WRITE,
+        }
+    }
+}
+
+/// Per source state shared between the reactor and the `AsyncFd` that owns the source.
+///
+/// All registrations are edge-triggered, so the kernel only reports a transition once.
+/// The readiness is therefore cached here until the owner observes `WouldBlock` and
+/// clears it. As the reactor only runs in between task polls, a readiness event can
+/// never arrive while a task is between its `WouldBlock` and the call to `clear`.
+#[derive(Default)]
+pub(crate) struct ScheduledIo {
+    readiness: Cell<u8>,
+    reader: RefCell<Option<Waker>>,
+    writer: RefCell<Option<Waker>>,
+}
+
+impl ScheduledIo {
+    pub(crate) fn is_ready(&self, direction: Direction) -> bool {
+        self.readiness.get() & direction.mask() != 0
+    }
+
+    pub(crate) fn clear(&self, direction: Direction) {
+        self.readiness.set(self.readiness.get() & !direction.mask());
+    }
+
+    /// Store the waker to be woken when `direction` becomes ready.
+    ///
+    /// Only a single task can wait on each direction, any previous waker is replaced.
+    pub(crate) fn set_waker(&self, direction: Direction, waker: &Waker) {
+        let mut slot = match direction {
+            Direction::Read => self.reader.borrow_mut(),
+            Direction::Write => self.writer.borrow_mut(),
+        };
+
+        match slot.as_ref() {
+            Some(current) if current.will_wake(waker) => {}
+            _ => *slot = Some(waker.clone()),
+        }
+    }
+
+    /// Record new readiness and return the wakers that should be woken.
+    fn set_readiness(&self, ready: u8, wakers: &mut Vec<Waker>) {
+        self.readiness.set(self.readiness.get() | ready);
+
+        if ready & READ != 0 {
+            wakers.extend(self.reader.borrow_mut().take());
+        }
+
+        if ready & WRITE != 0 {
+            wakers.extend(self.writer.borrow_mut().take());
+        }
+    }
+}
+
+pub(crate) struct Reactor {
+    poll: RefCell<Poll>,
+    events: RefCell<Events>,
+    sources: RefCell<HashMap<Token, Rc<ScheduledIo>>>,
+    next_token: Cell<usize>,
+    /// Read half of the executor's wake-up socket.
+    wake_rx: UnixStream,
+}
+
+impl Reactor {
+    pub(crate) fn new(wake_rx: UnixStream) -> io::Result<Self> {
+        let poll = Poll::new()?;
+
+        wake_rx.set_nonblocking(true)?;
+        poll.registry()
+            .register(&wake_rx, WAKE_TOKEN, Interest::READABLE)?;
+
+        Ok(Self {
+            poll: RefCell::new(poll),
+            events: RefCell::new(Events::with_capacity(EVENTS_CAPACITY)),
+            sources: RefCell::new(HashMap::new()),
+            next_token: Cell::new(0),
+            wake_rx,
+        })
+    }
+
+    /// Register a source for both read and write readiness.
+    pub(crate) fn register<S: Source>(&self, source: &S) -> io::Result<(Token, Rc<ScheduledIo>)> {
+        let token = Token(self.next_token.get());
+        self.next_token.set(token.0 + 1);
+
+        self.poll.borrow().registry().register(
+            source,
+            token,
+            Interest::READABLE | Interest::WRITABLE,
+        )?;
+
+        let io = Rc::new(ScheduledIo::default());
+        self.sources.borrow_mut().insert(token, io.clone());
+
+        Ok((token, io))
+    }
+
+    /// Let the selector know `source` has been drained in the given direction.
+    pub(crate) fn rearm<S: Source>(&self, source: &S, direction: Direction) {
+        let interests = match direction {
+            Direction::Read => Interest::READABLE,
+            Direction::Write => Interest::WRITABLE,
+        };
+
+        // can only fail if the source is not registered, which AsyncFd guarantees it is
+        let _ = self.poll.borrow().registry().rearm(source, interests);
+    }
+
+    pub(crate) fn deregister<S: Source>(&self, source: &S, token: Token) -> io::Result<()> {
+        self.sources.borrow_mut().remove(&token);
+        Ok(self.poll.borrow().registry().deregister(source)?)
+    }
+
+    /// Block on the event queue and wake every task whose source became ready.
+    ///
+    /// Returns true if the executor's wake-up socket was signalled.
+    pub(crate) fn turn(&self, timeout: Option<Duration>) -> io::Result<bool> {
+        let mut events = self.events.borrow_mut();
+
+        match self.poll.borrow_mut().poll(&mut *events, timeout) {
+            Ok(()) => {}
+            Err(Error::Interrupted(_)) => return Ok(false),
+            Err(e) => return Err(e.into()),
+        }
+
+        let mut woken = false;
+        let mut wakers = Vec::new();
+        let sources = self.sources.borrow();
+
+        for event in &*events {
+            let token = event.token();
+
+            if token == WAKE_TOKEN {
+                woken = true;
+                continue;
+            }
+
+            // source might have been deregistered by a task since the poll began
+            let Some(io) = sources.get(&token) else {
+                continue;
+            };
+
+            let mut ready = 0;
+
+            if event.is_readable() || event.is_read_closed() || event.is_error() {
+                ready |= READ;
+            }
+
+            if event.is_writable() || event.is_write_closed() || event.is_error() {
+                ready |= WRITE;
+            }
+
+            io.set_readiness(ready, &mut wakers);
+        }
+
+        drop(sources);
+        drop(events);
+
+        if woken {
+            self.drain_wake_socket();
+        }
+
+        // wake outside of any borrows, as waking can re-enter the runtime
+        wakers.into_iter().for_each(Waker::wake);
+
+        Ok(woken)
+    }
+
+    /// Edge-triggered mode: the socket must be drained for it to notify us again.
+    fn drain_wake_socket(&self) {
+        let mut buf = [0u8; 64];
+
+        loop {
+            match (&self.wake_rx).read(&mut buf) {
+                Ok(0) => break,
+                Ok(_) => continue,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(_) => break,
+            }
+        }
+
+        let _ = self
+            .poll
+            .borrow()
+            .registry()
+            .rearm(&self.wake_rx, Interest::READABLE);
+    }
+}
@@ -0,0 +1,151 @@
+//! Signal sets, for `Poll::poll_with_sigmask`.
+//!
+//! Blocking a signal, and only unblocking it for the duration of the wait, guarantees the
+//! signal either interrupts the wait or stays pending until the next one. Checking a flag
+//! set by the handler right before waiting is then free of races.
+//!
+//! ```no_run
+//! use mini_mio::interfaces::Events;
+//! use mini_mio::poll::Poll;
+//! use mini_mio::signal::SigSet;
+//!
+//! let mut poll = Poll::new().unwrap();
+//! let mut events = Events::with_capacity(128);
+//!
+//! // block SIGCHLD everywhere but in the wait, keeping the previous mask to wait with
+//! let mut sigchld = SigSet::empty();
+//! sigchld.add(libc::SIGCHLD).unwrap();
+//! let mut waiting = sigchld.block().unwrap();
+//! waiting.remove(libc::SIGCHLD).unwrap();
+//!
+//! loop {
+//!     // reap any children that exited since the last wait here, the handler only
+//!     // sets a flag
+//!
+//!     match poll.poll_with_sigmask(&mut events, None, &waiting) {
+//!         Ok(_) => {}
+//!         Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+//!         Err(e) => panic!("{e}"),
+//!     }
+//! }
+//! ```
+
+use std::fmt;
+use std::io;
+use std::mem::MaybeUninit;
+
+/// Highest signal number checked by `Debug`, covering every real-time signal.
+const MAX_SIGNAL: i32 = 64;
+
+/// A set of signals, wrapping `sigset_t`.
+#[derive(Clone, Copy)]
+pub struct SigSet(libc::sigset_t);
+
+impl SigSet {
+    /// A set without any signals.
+    pub fn empty() -> Self {
+        let mut set = MaybeUninit::uninit();
+        // sigemptyset can only fail for a null pointer
+        unsafe { libc::sigemptyset(set.as_mut_ptr()) };
+        Self(unsafe { set.assume_init() })
+    }
+
+    /// A set with every signal.
+    pub fn all() -> Self {
+        let mut set = MaybeUninit::uninit();
+        unsafe { libc::sigfillset(set.as_mut_ptr()) };
+        Self(unsafe { set.assume_init() })
+    }
+
+    /// Fails with `EINVAL` when `signal` is not a valid signal number.
+    pub fn add(&mut self, signal: i32) -> io::Result<()> {
+        cvt(unsafe { libc::sigaddset(&mut self.0, signal) })
+    }
+
+    /// Fails with `EINVAL` when `signal` is not a valid signal number.
+    pub fn remove(&mut self, signal: i32) -> io::Result<()> {
+        cvt(unsafe { libc::sigdelset(&mut self.0, signal) })
+    }
+
+    pub fn contains(&self, signal: i32) -> bool {
+        unsafe { libc::sigismember(&self.0, signal) == 1 }
+    }
+
+    /// The signal mask of the calling thread.
+    pub fn thread_mask() -> io::Result<Self> {
+        sigmask(libc::SIG_BLOCK, None)
+    }
+
+    /// Block the signals in this set for the calling thread, returning its previous mask.
+    pub fn block(&self) -> io::Result<Self> {
+        sigmask(libc::SIG_BLOCK, Some(self))
+    }
+
+    /// Unblock the signals in this set for the calling thread, returning its previous
+    /// mask.
+    pub fn unblock(&self) -> io::Result<Self> {
+        sigmask(libc::SIG_UNBLOCK, Some(self))
+    }
+
+    /// Replace the mask of the calling thread with this set, returning its previous mask.
+    pub fn set_thread_mask(&self) -> io::Result<Self> {
+        sigmask(libc::SIG_SETMASK, Some(self))
+    }
+
+    pub(crate) fn as_ptr(&self) -> *const libc::sigset_t {
+        &self.0
+    }
+}
+
+impl Default for SigSet {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+impl fmt::Debug for SigSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set()
+            .entries((1..=MAX_SIGNAL).filter(|signal| self.contains(*signal)))
+            .finish()
+    }
+}
+
+fn sigmask(how: i32, set: Option<&SigSet>) -> io::Result<SigSet> {
+    let mut previous = SigSet::empty();
+    let set = set.map_or(std::ptr::null(), SigSet::as_ptr);
+
+    // returns the error number, rather than setting errno
+    match unsafe { libc::pthread_sigmask(how, set, &mut previous.0) } {
+        0 => Ok(previous),
+        errno => Err(io::Error::from_raw_os_error(errno)),
+    }
+}
+
+fn cvt(ret: i32) -> io::Result<()> {
+    if ret < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_and_remove() {
+        let mut set = SigSet::empty();
+        set.add(libc::SIGCHLD).unwrap();
+        set.add(libc::SIGUSR1).unwrap();
+        assert!(set.contains(libc::SIGCHLD));
+
+        set.remove(libc::SIGCHLD).unwrap();
+        assert!(!set.contains(libc::SIGCHLD));
+        assert_eq!(format!("{set:?}"), format!("{{{}}}", libc::SIGUSR1));
+
+        assert!(set.add(-1).is_err());
+        assert!(SigSet::all().contains(libc::SIGCHLD));
+    }
+}
@@ -0,0 +1,204 @@
+//! Cross-checks of the hand-written constants and structs against `libc`.
+//!
+//! Only compiled with the `abi-check` feature. Every check here is a constant, so a
+//! mismatch fails the build for the target being compiled, instead of showing up as
+//! wrong events at runtime. Checks of the structs private to a backend's `ffi` module,
+//! and tests making real syscalls through both declarations, live in that backend's
+//! `abi_check` module.
+//!
+//! io_uring is not covered by `libc`, apart from its syscall numbers. Its structs are
+//! checked against the sizes in `linux/io_uring.h` instead.
+
+#![allow(unused_imports)]
+
+use std::mem::{align_of, offset_of, size_of};
+
+/// Fails the build unless each hand-written constant has the same value as its `libc`
+/// counterpart.
+macro_rules! assert_same_value {
+    ($($ours:expr => $libc:expr),* $(,)?) => {
+        $(
+            const _: () = assert!(
+                $ours as i128 == $libc as i128,
+                concat!(stringify!($ours), " differs from ", stringify!($libc)),
+            );
+        )*
+    };
+}
+
+/// Fails the build unless a hand-written struct has the same size and alignment as its
+/// `libc` counterpart, with each listed field at the same offset.
+macro_rules! assert_same_layout {
+    ($ours:ty => $libc:ty { $($field:ident => $libc_field:ident),* $(,)? }) => {
+        const _: () = {
+            use std::mem::{align_of, offset_of, size_of};
+
+            assert!(
+                size_of::<$ours>() == size_of::<$libc>(),
+                concat!("size of ", stringify!($ours), " differs from ", stringify!($libc)),
+            );
+            assert!(
+                align_of::<$ours>() == align_of::<$libc>(),
+                concat!("alignment of ", stringify!($ours), " differs from ", stringify!($libc)),
+            );
+            $(
+                assert!(
+                    offset_of!($ours, $field) == offset_of!($libc, $libc_field),
+                    concat!(
+                        "offset of ", stringify!($ours), "::", stringify!($field),
+                        " differs from ", stringify!($libc), "::", stringify!($libc_field),
+                    ),
+                );
+            )*
+        };
+    };
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use super::*;
+    use crate::sys::constants::epoll::{events, flags, ops};
+    use crate::sys::constants::poll;
+    use crate::sys::events::epoll::OsEvent;
+
+    assert_same_value! {
+        ops::EPOLL_CTL_ADD => libc::EPOLL_CTL_ADD,
+        ops::EPOLL_CTL_DEL => libc::EPOLL_CTL_DEL,
+        ops::EPOLL_CTL_MOD => libc::EPOLL_CTL_MOD,
+        flags::EPOLL_CLOEXEC => libc::EPOLL_CLOEXEC,
+        events::EPOLLIN => libc::EPOLLIN,
+        events::EPOLLOUT => libc::EPOLLOUT,
+        events::EPOLLPRI => libc::EPOLLPRI,
+        events::EPOLLRDNORM => libc::EPOLLRDNORM,
+        events::EPOLLRDBAND => libc::EPOLLRDBAND,
+        events::EPOLLWRNORM => libc::EPOLLWRNORM,
+        events::EPOLLWRBAND => libc::EPOLLWRBAND,
+        events::EPOLLMSG => libc::EPOLLMSG,
+        events::EPOLLERR => libc::EPOLLERR,
+        events::EPOLLHUP => libc::EPOLLHUP,
+        events::EPOLLRDHUP => libc::EPOLLRDHUP,
+        events::EPOLLEXCLUSIVE => libc::EPOLLEXCLUSIVE,
+        events::EPOLLWAKEUP => libc::EPOLLWAKEUP,
+        events::EPOLLONESHOT => libc::EPOLLONESHOT,
+        events::EPOLLET => libc::EPOLLET,
+        poll::events::POLLIN => libc::POLLIN,
+        poll::events::POLLPRI => libc::POLLPRI,
+        poll::events::POLLOUT => libc::POLLOUT,
+        poll::events::POLLERR => libc::POLLERR,
+        poll::events::POLLHUP => libc::POLLHUP,
+        poll::events::POLLNVAL => libc::POLLNVAL,
+        poll::events::POLLRDHUP => libc::POLLRDHUP,
+    }
+
+    // the poll(2) backend reports readiness through the epoll `OsEvent` unchanged
+    assert_same_value! {
+        poll::events::POLLIN => events::EPOLLIN,
+        poll::events::POLLPRI => events::EPOLLPRI,
+        poll::events::POLLOUT => events::EPOLLOUT,
+        poll::events::POLLERR => events::EPOLLERR,
+        poll::events::POLLHUP => events::EPOLLHUP,
+        poll::events::POLLRDHUP => events::EPOLLRDHUP,
+    }
+
+    assert_same_layout!(OsEvent => libc::epoll_event {
+        events => events,
+        epoll_data => u64,
+    });
+
+    // See ISSUE #5 on `OsEvent`, the struct is only packed on x86_64
+    #[cfg(target_arch = "x86_64")]
+    const _: () = assert!(
+        size_of::<OsEvent>() == 12
+            && align_of::<OsEvent>() == 1
+            && offset_of!(OsEvent, epoll_data) == 4
+    );
+
+    #[cfg(all(not(target_arch = "x86_64"), target_pointer_width = "64"))]
+    const _: () = assert!(
+        size_of::<OsEvent>() == 16
+            && align_of::<OsEvent>() == 8
+            && offset_of!(OsEvent, epoll_data) == 8
+    );
+
+    #[cfg(feature = "io-uring")]
+    mod io_uring {
+        use crate::sys::constants::io_uring::syscalls;
+
+        assert_same_value! {
+            syscalls::SYS_IO_URING_SETUP => libc::SYS_io_uring_setup,
+            syscalls::SYS_IO_URING_ENTER => libc::SYS_io_uring_enter,
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod macos {
+    use super::*;
+    use crate::sys::constants::kqueue::{fflags, filters, flags};
+    use crate::sys::events::kqueue::OsEvent;
+
+    assert_same_value! {
+        filters::EVFILT_READ => libc::EVFILT_READ,
+        filters::EVFILT_WRITE => libc::EVFILT_WRITE,
+        filters::EVFILT_AIO => libc::EVFILT_AIO,
+        filters::EVFILT_VNODE => libc::EVFILT_VNODE,
+        filters::EVFILT_PROC => libc::EVFILT_PROC,
+        filters::EVFILT_SIGNAL => libc::EVFILT_SIGNAL,
+        filters::EVFILT_TIMER => libc::EVFILT_TIMER,
+        filters::EVFILT_MACHPORT => libc::EVFILT_MACHPORT,
+        filters::EVFILT_FS => libc::EVFILT_FS,
+        filters::EVFILT_USER => libc::EVFILT_USER,
+        filters::EVFILT_VM => libc::EVFILT_VM,
+        flags::EV_ADD => libc::EV_ADD,
+        flags::EV_DELETE => libc::EV_DELETE,
+        flags::EV_ENABLE => libc::EV_ENABLE,
+        flags::EV_DISABLE => libc::EV_DISABLE,
+        flags::EV_ONESHOT => libc::EV_ONESHOT,
+        flags::EV_CLEAR => libc::EV_CLEAR,
+        flags::EV_RECEIPT => libc::EV_RECEIPT,
+        flags::EV_DISPATCH => libc::EV_DISPATCH,
+        flags::EV_FLAG0 => libc::EV_FLAG0,
+        flags::EV_POLL => libc::EV_POLL,
+        flags::EV_FLAG1 => libc::EV_FLAG1,
+        flags::EV_OOBAND => libc::EV_OOBAND,
+        flags::EV_ERROR => libc::EV_ERROR,
+        flags::EV_EOF => libc::EV_EOF,
+        flags::EV_SYSFLAGS => libc::EV_SYSFLAGS,
+        fflags::NOTE_TRIGGER => libc::NOTE_TRIGGER,
+        fflags::NOTE_FFNOP => libc::NOTE_FFNOP,
+        fflags::NOTE_FFAND => libc::NOTE_FFAND,
+        fflags::NOTE_FFOR => libc::NOTE_FFOR,
+        fflags::NOTE_FFCOPY => libc::NOTE_FFCOPY,
+        fflags::NOTE_FFCTRLMASK => libc::NOTE_FFCTRLMASK,
+        fflags::NOTE_FFLAGSMASK => libc::NOTE_FFLAGSMASK,
+        fflags::NOTE_LOWAT => libc::NOTE_LOWAT,
+        fflags::NOTE_DELETE => libc::NOTE_DELETE,
+        fflags::NOTE_WRITE => libc::NOTE_WRITE,
+        fflags::NOTE_EXTEND => libc::NOTE_EXTEND,
+        fflags::NOTE_ATTRIB => libc::NOTE_ATTRIB,
+        fflags::NOTE_LINK => libc::NOTE_LINK,
+        fflags::NOTE_RENAME => libc::NOTE_RENAME,
+        fflags::NOTE_REVOKE => libc::NOTE_REVOKE,
+        fflags::NOTE_NONE => libc::NOTE_NONE,
+        fflags::NOTE_EXIT => libc::NOTE_EXIT,
+        fflags::NOTE_FORK => libc::NOTE_FORK,
+        fflags::NOTE_EXEC => libc::NOTE_EXEC,
+    }
+
+    assert_same_layout!(OsEvent => libc::kevent {
+        ident => ident,
+        filter => filter,
+        flags => flags,
+        fflags => fflags,
+        data => data,
+        udata => udata,
+    });
+
+    #[cfg(target_pointer_width = "64")]
+    const _: () = assert!(
+        size_of::<OsEvent>() == 32
+            && align_of::<OsEvent>() == 8
+            && offset_of!(OsEvent, filter) == 8
+            && offset_of!(OsEvent, udata) == 24
+    );
+}
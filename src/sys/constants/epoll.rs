@@ -106,3 +106,10 @@ pub(crate) mod events {
 
     pub use inputs::*;
 }
+
+/// Flags accepted by `epoll_create1`.
+pub(crate) mod flags {
+    /// Set the close-on-exec flag on the new file descriptor, so that it is closed
+    /// automatically in any program started via execve(2).
+    pub const EPOLL_CLOEXEC: i32 = 0o2000000;
+}
@@ -0,0 +1,65 @@
+#![allow(unused)]
+
+/// Syscall numbers. These are shared by every architecture apart from alpha and mips.
+/// taken from : /usr/include/asm-generic/unistd.h
+pub(crate) mod syscalls {
+    pub const SYS_IO_URING_SETUP: i64 = 425;
+    pub const SYS_IO_URING_ENTER: i64 = 426;
+}
+
+/// Offsets passed to mmap(2) to map the rings and the submission queue entries.
+pub(crate) mod offsets {
+    pub const IORING_OFF_SQ_RING: i64 = 0;
+    pub const IORING_OFF_CQ_RING: i64 = 0x8000000;
+    pub const IORING_OFF_SQES: i64 = 0x10000000;
+}
+
+/// Operation codes set in `io_uring_sqe.opcode`.
+pub(crate) mod ops {
+    /// Poll the file descriptor for the events in `poll32_events`.
+    pub const IORING_OP_POLL_ADD: u8 = 6;
+
+    /// Remove an existing poll request, identified by its `user_data` passed in `addr`.
+    pub const IORING_OP_POLL_REMOVE: u8 = 7;
+}
+
+/// Flags set in `io_uring_sqe.len` of an `IORING_OP_POLL_ADD` request.
+pub(crate) mod poll_flags {
+    /// Keep the request armed after each completion, rather than completing once.
+    ///
+    /// Unless `IORING_POLL_ADD_LEVEL` is also set, the kernel adds `EPOLLET` to the
+    /// request, so completions are posted on edges only.
+    pub const IORING_POLL_ADD_MULTI: u32 = 1 << 0;
+}
+
+/// Flags passed to io_uring_enter(2).
+pub(crate) mod enter_flags {
+    /// Wait for `min_complete` completions before returning.
+    pub const IORING_ENTER_GETEVENTS: u32 = 1 << 0;
+
+    /// The `sig` argument points to a `io_uring_getevents_arg`, which carries a timeout.
+    pub const IORING_ENTER_EXT_ARG: u32 = 1 << 3;
+}
+
+/// Flags set on a completion queue entry.
+pub(crate) mod cqe_flags {
+    /// The request will post more completions. A multishot poll without this flag has
+    /// terminated and must be submitted again.
+    pub const IORING_CQE_F_MORE: u32 = 1 << 1;
+}
+
+/// Features reported by the kernel in `io_uring_params.features`.
+pub(crate) mod features {
+    /// The submission and completion rings can be mapped with a single mmap(2).
+    pub const IORING_FEAT_SINGLE_MMAP: u32 = 1 << 0;
+
+    /// Completions are never dropped, even when the completion queue overflows.
+    pub const IORING_FEAT_NODROP: u32 = 1 << 1;
+
+    /// io_uring_enter(2) accepts `IORING_ENTER_EXT_ARG` (Linux 5.11).
+    pub const IORING_FEAT_EXT_ARG: u32 = 1 << 8;
+
+    /// Resource tagging (Linux 5.13). Multishot poll landed in the same release, and
+    /// there is no dedicated feature bit for it.
+    pub const IORING_FEAT_RSRC_TAGS: u32 = 1 << 10;
+}
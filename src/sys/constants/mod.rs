@@ -3,4 +3,11 @@
 //! These can technically all be replaced via
 //! use of `libc` constants if required.
 pub(crate) mod epoll;
+
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+pub(crate) mod io_uring;
+
 pub(crate) mod kqueue;
+
+#[cfg(target_os = "linux")]
+pub(crate) mod poll;
@@ -0,0 +1,30 @@
+#![allow(unused)]
+
+/// Bits used in the `events` and `revents` members of `pollfd`.
+///
+/// On Linux these share their values with the matching `EPOLL*` constants, which allows
+/// the poll(2) backend to report readiness through the epoll `OsEvent` type unchanged.
+/// taken from : /usr/include/asm-generic/poll.h
+pub(crate) mod events {
+    /// There is data to read.
+    pub const POLLIN: i16 = 0x001;
+
+    /// There is some exceptional condition on the file descriptor, e.g. out-of-band data
+    /// on a TCP socket.
+    pub const POLLPRI: i16 = 0x002;
+
+    /// Writing is now possible.
+    pub const POLLOUT: i16 = 0x004;
+
+    /// Error condition (only returned in revents; ignored in events).
+    pub const POLLERR: i16 = 0x008;
+
+    /// Hang up (only returned in revents; ignored in events).
+    pub const POLLHUP: i16 = 0x010;
+
+    /// Invalid request: fd not open (only returned in revents; ignored in events).
+    pub const POLLNVAL: i16 = 0x020;
+
+    /// Stream socket peer closed connection, or shut down writing half of connection.
+    pub const POLLRDHUP: i16 = 0x2000;
+}
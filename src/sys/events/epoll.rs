@@ -1,4 +1,7 @@
 //! epoll based structures and types
+use std::fmt;
+
+use crate::flags::EpollFlags;
 use crate::interfaces::SysEvent;
 use crate::sys::constants::epoll::events;
 
@@ -16,7 +19,7 @@ use crate::sys::constants::epoll::events;
 /// `repr(packed)` on `x86-64` systems due to backwards compatibility with 32
 /// bit arch syscalls. Fixed by conditionally compiling the #[repr(packed)]
 /// attribute.
-#[derive(Debug, Default)]
+#[derive(Default)]
 #[repr(C)]
 #[cfg_attr(target_arch = "x86_64", repr(packed))] // only use packed on x86_64
 pub struct OsEvent {
@@ -27,8 +30,25 @@ pub struct OsEvent {
     pub(crate) epoll_data: usize,
 }
 
+#[allow(unused)]
 pub type OsEvents = Vec<OsEvent>;
 
+/// What tells the registrations events were reported for apart: only the token on epoll.
+#[allow(unused)]
+pub(crate) type EventKey = crate::interfaces::Token;
+
+impl fmt::Debug for OsEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // copy the fields out first, references to packed fields are not allowed
+        let (events, epoll_data) = (self.events, self.epoll_data);
+
+        f.debug_struct("OsEvent")
+            .field("events", &EpollFlags::from_bits(events as u32))
+            .field("epoll_data", &epoll_data)
+            .finish()
+    }
+}
+
 impl SysEvent for OsEvent {
     fn token(&self) -> crate::interfaces::Token {
         crate::interfaces::Token(self.epoll_data)
@@ -74,3 +94,26 @@ impl SysEvent for OsEvent {
         (self.events & events::EPOLLERR) != 0
     }
 }
+
+impl OsEvent {
+    /// File descriptor the event was reported for, unknown as `epoll_data` only holds the
+    /// token.
+    pub(crate) fn fd(&self) -> Option<std::os::fd::RawFd> {
+        None
+    }
+
+    /// Merge the readiness of `other` into this event, if both are for the same token,
+    /// the most epoll can tell about the registration they were reported for.
+    pub(crate) fn merge(&mut self, other: &Self) -> bool {
+        if self.key() != other.key() {
+            return false;
+        }
+
+        self.events |= other.events;
+        true
+    }
+
+    pub(crate) fn key(&self) -> EventKey {
+        self.token()
+    }
+}
@@ -1,4 +1,7 @@
 //! kqueue based structures and types
+use std::fmt;
+
+use crate::flags::{kqueue_filter_name, KqueueFlags};
 use crate::interfaces::SysEvent;
 use crate::sys::constants::kqueue::{filters, flags};
 
@@ -6,7 +9,7 @@ use crate::sys::constants::kqueue::{filters, flags};
 ///
 /// Can also view a similar struct in the `libc` crate on ios/darwin
 /// Does not need to be packed. It is 32 bytes anyway, with no padding.
-#[derive(Debug, Default)]
+#[derive(Default)]
 #[repr(C)]
 pub struct OsEvent {
     /// Typically the file descriptor we are
@@ -24,8 +27,32 @@ pub struct OsEvent {
     pub udata: usize, // *mut ::c_void,
 }
 
+#[allow(unused)]
 pub type OsEvents = Vec<OsEvent>;
 
+/// What tells the registrations events were reported for apart: the identifier, filter
+/// and user data.
+#[allow(unused)]
+pub(crate) type EventKey = (usize, i16, usize);
+
+impl fmt::Debug for OsEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut s = f.debug_struct("OsEvent");
+        s.field("ident", &self.ident);
+
+        match kqueue_filter_name(self.filter) {
+            Some(name) => s.field("filter", &format_args!("{name}")),
+            None => s.field("filter", &self.filter),
+        };
+
+        s.field("flags", &KqueueFlags::from_bits(self.flags))
+            .field("fflags", &format_args!("{:#x}", self.fflags))
+            .field("data", &self.data)
+            .field("udata", &self.udata)
+            .finish()
+    }
+}
+
 /// Filters place an event on the kqueue for the user to retrieve, hence
 /// the filter should be checked on the event to determine what sort of event has occurred.
 ///
@@ -68,3 +95,28 @@ impl SysEvent for OsEvent {
         (self.flags & flags::EV_ERROR) != 0 || (self.flags & flags::EV_EOF != 0)
     }
 }
+
+#[allow(unused)]
+impl OsEvent {
+    /// File descriptor the event was reported for.
+    pub(crate) fn fd(&self) -> Option<std::os::fd::RawFd> {
+        Some(self.ident as std::os::fd::RawFd)
+    }
+
+    /// Take the flags and data of `other`, if both are for the same filter of the same
+    /// registration, as the latest event carries the current ones.
+    pub(crate) fn merge(&mut self, other: &Self) -> bool {
+        if self.key() != other.key() {
+            return false;
+        }
+
+        self.flags = other.flags;
+        self.fflags = other.fflags;
+        self.data = other.data;
+        true
+    }
+
+    pub(crate) fn key(&self) -> EventKey {
+        (self.ident, self.filter, self.udata)
+    }
+}
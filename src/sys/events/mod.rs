@@ -5,15 +5,20 @@
 //!     - Linux: epoll_event
 //!     - MacOS: kevent
 //! - `OsEvents`: a collection of "`Event`"s
+//!
+//! Both structs are compiled on every OS, so that `decode` can make sense of events dumped
+//! on another OS. Only the re-exported one is ever passed to the kernel.
 
-#[cfg(target_os = "linux")]
 pub mod epoll;
 
+#[cfg(target_os = "linux")]
+pub(crate) use epoll::EventKey;
 #[cfg(target_os = "linux")]
 pub use epoll::{OsEvent, OsEvents};
 
-#[cfg(target_os = "macos")]
 pub mod kqueue;
 
+#[cfg(target_os = "macos")]
+pub(crate) use kqueue::EventKey;
 #[cfg(target_os = "macos")]
 pub use kqueue::{OsEvent, OsEvents};
@@ -9,14 +9,20 @@
 //!     - MacOS: kevent
 //! - `Events`: a collection of "`Event`"s
 //! - `Selector`: used for interacting with the event queue. This will be
-//!    used by the Registry for executing the lower level OS specific syscalls.
+//!   used by the Registry for executing the lower level OS specific syscalls.
 
 #[allow(unused_imports)]
 pub(crate) mod constants;
 
+// declared before `selectors`, whose backends use its macros
+#[cfg(feature = "abi-check")]
+#[macro_use]
+mod abi;
+
 #[allow(unused_imports)]
-mod events;
+pub(crate) mod events;
 
+pub(crate) use events::EventKey;
 #[allow(unused_imports)]
 pub use events::{OsEvent, OsEvents};
 
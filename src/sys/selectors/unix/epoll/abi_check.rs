@@ -0,0 +1,58 @@
+//! Runs epoll syscalls through both the hand-written declarations in `ffi` and those of
+//! `libc`, checking that each side reads what the other wrote.
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::os::fd::AsRawFd;
+    use std::os::unix::net::UnixStream;
+
+    use super::super::ffi;
+    use crate::sys::constants::epoll::{events, flags, ops};
+    use crate::sys::events::epoll::OsEvent;
+
+    /// Uses the upper bits, which a layout mismatch would truncate or shift.
+    const TOKEN: usize = 0x0123_4567_89ab_cdef;
+
+    #[test]
+    fn ctl_and_wait_through_both_declarations() {
+        let (a, mut b) = UnixStream::pair().unwrap();
+        b.write_all(b"hello").unwrap();
+
+        unsafe {
+            let epfd = ffi::epoll_create1(flags::EPOLL_CLOEXEC);
+            assert!(epfd >= 0);
+
+            // registered via ffi, waited on via libc
+            let mut event = OsEvent {
+                events: events::EPOLLIN,
+                epoll_data: TOKEN,
+            };
+            let ret = ffi::epoll_ctl(epfd, ops::EPOLL_CTL_ADD, a.as_raw_fd(), &mut event);
+            assert_eq!(ret, 0);
+
+            let mut theirs = [libc::epoll_event { events: 0, u64: 0 }; 4];
+            let n = libc::epoll_wait(epfd, theirs.as_mut_ptr(), 4, 0);
+            assert_eq!(n, 1);
+            assert_eq!({ theirs[0].events }, libc::EPOLLIN as u32);
+            assert_eq!({ theirs[0].u64 }, TOKEN as u64);
+
+            // modified via libc, waited on via ffi
+            let mut event = libc::epoll_event {
+                events: (libc::EPOLLIN | libc::EPOLLOUT) as u32,
+                u64: !TOKEN as u64,
+            };
+            let ret = libc::epoll_ctl(epfd, libc::EPOLL_CTL_MOD, a.as_raw_fd(), &mut event);
+            assert_eq!(ret, 0);
+
+            let mut ours: Vec<OsEvent> = Vec::with_capacity(4);
+            let n = ffi::epoll_wait(epfd, ours.as_mut_ptr(), 4, 0);
+            assert_eq!(n, 1);
+            ours.set_len(n as usize);
+            assert_eq!({ ours[0].events }, events::EPOLLIN | events::EPOLLOUT);
+            assert_eq!({ ours[0].epoll_data }, !TOKEN);
+
+            assert_eq!(ffi::close(epfd), 0);
+        }
+    }
+}
@@ -19,6 +19,7 @@ use std::ops::{BitAnd, BitOr};
 use crate::sys::events::epoll::OsEvent;
 
 // #[cfg(target_os = "linux")]
+#[cfg(not(feature = "libc-ffi"))]
 #[link(name = "c")] // link to C standard library / libc
 extern "C" {
 
@@ -38,6 +39,16 @@ extern "C" {
     /// io::Error::last_os_error()
     pub fn epoll_create(size: i32) -> i32;
 
+    /// open an epoll file descriptor, with flags
+    ///
+    /// https://man7.org/linux/man-pages/man2/epoll_create.2.html
+    ///
+    /// int epoll_create1(int flags);
+    ///
+    /// Same as `epoll_create`, but without the obsolete size argument. If flags is 0 it
+    /// behaves the same as `epoll_create`. The only flag is EPOLL_CLOEXEC.
+    pub fn epoll_create1(flags: i32) -> i32;
+
     /// close a file descriptor we get when we create an epoll instance.
     ///
     /// This is simply to release resources correctly.
@@ -75,4 +86,88 @@ extern "C" {
     ///
     /// https://man7.org/linux/man-pages/man2/epoll_wait.2.html
     pub fn epoll_wait(epfd: i32, events: *mut OsEvent, max_events: i32, timeout: i32) -> i32;
+
+    /// wait for an I/O event on an epoll file descriptor, with `sigmask` as the thread's
+    /// signal mask while blocked
+    ///
+    /// The mask is swapped in and out atomically with the wait, so a signal blocked
+    /// everywhere else either interrupts the wait with EINTR, or stays pending.
+    ///
+    /// https://man7.org/linux/man-pages/man2/epoll_pwait.2.html
+    ///
+    /// int epoll_pwait(int epfd, struct epoll_event *events, int maxevents, int timeout,
+    ///                 const sigset_t *_Nullable sigmask);
+    pub fn epoll_pwait(
+        epfd: i32,
+        events: *mut OsEvent,
+        max_events: i32,
+        timeout: i32,
+        sigmask: *const libc::sigset_t,
+    ) -> i32;
+}
+
+/// The same calls made via the declarations of the `libc` crate, enabled by the `libc-ffi`
+/// feature instead of the hand-written ones above.
+///
+/// `OsEvent` has the same layout as `libc::epoll_event`, as checked by the `abi-check`
+/// feature, so pointers to it are passed on unchanged.
+#[cfg(feature = "libc-ffi")]
+mod via_libc {
+    use super::OsEvent;
+
+    pub unsafe fn epoll_create(size: i32) -> i32 {
+        libc::epoll_create(size)
+    }
+
+    pub unsafe fn epoll_create1(flags: i32) -> i32 {
+        libc::epoll_create1(flags)
+    }
+
+    pub unsafe fn close(fd: i32) -> i32 {
+        libc::close(fd)
+    }
+
+    pub unsafe fn epoll_ctl(epfd: i32, op: i32, fd: i32, event: *mut OsEvent) -> i32 {
+        libc::epoll_ctl(epfd, op, fd, event.cast())
+    }
+
+    pub unsafe fn epoll_wait(
+        epfd: i32,
+        events: *mut OsEvent,
+        max_events: i32,
+        timeout: i32,
+    ) -> i32 {
+        libc::epoll_wait(epfd, events.cast(), max_events, timeout)
+    }
+
+    /// wait for an I/O event on an epoll file descriptor, with `sigmask` as the thread's
+    /// signal mask while blocked
+    ///
+    /// https://man7.org/linux/man-pages/man2/epoll_pwait.2.html
+    pub unsafe fn epoll_pwait(
+        epfd: i32,
+        events: *mut OsEvent,
+        max_events: i32,
+        timeout: i32,
+        sigmask: *const libc::sigset_t,
+    ) -> i32 {
+        libc::epoll_pwait(epfd, events.cast(), max_events, timeout, sigmask)
+    }
+
+    /// Same as `epoll_pwait`, with a nanosecond timeout, or null to block indefinitely.
+    ///
+    /// Added in Linux 5.11, and only declared by `libc` for glibc.
+    #[cfg(target_env = "gnu")]
+    pub unsafe fn epoll_pwait2(
+        epfd: i32,
+        events: *mut OsEvent,
+        max_events: i32,
+        timeout: *const libc::timespec,
+        sigmask: *const libc::sigset_t,
+    ) -> i32 {
+        libc::epoll_pwait2(epfd, events.cast(), max_events, timeout, sigmask)
+    }
 }
+
+#[cfg(feature = "libc-ffi")]
+pub use via_libc::*;
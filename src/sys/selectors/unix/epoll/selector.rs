@@ -7,12 +7,17 @@
 use std::io;
 use std::mem::MaybeUninit;
 use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+#[cfg(all(feature = "libc-ffi", target_env = "gnu"))]
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
 
-use crate::interests::Interest;
-use crate::interfaces::{SysSelector, Token};
+use crate::interests::{Interest, Trigger};
+use crate::interfaces::{SelectorOptions, SysSelector, Token};
+use crate::poll::Backend;
+use crate::signal::SigSet;
 
 // types used for interfacing with epoll syscalls
-use crate::sys::constants::epoll::{events, ops};
+use crate::sys::constants::epoll::{events, flags, ops};
 use crate::sys::events::{OsEvent, OsEvents};
 
 // epoll syscalls
@@ -23,16 +28,28 @@ pub struct Selector {
     /// OwnedFd is a wrapper around an i32.
     /// It closes the file descriptor when dropped: no need for `close` syscall.
     epfd: OwnedFd,
+
+    /// Trigger mode applied to every registration.
+    trigger: Trigger,
 }
 
 impl SysSelector for Selector {
     type OsEvent = OsEvent;
     type OsEvents = Vec<Self::OsEvent>;
 
-    fn new() -> io::Result<Self> {
-        // TODO: need to set close on execute on the file descriptor, to stop
-        // any forked processes from inheriting a clone of the file descriptor.
-        let ret = unsafe { ffi::epoll_create(1) };
+    fn with_options(options: SelectorOptions) -> io::Result<Self> {
+        // close on exec stops programs started via `exec` from inheriting a clone of
+        // the file descriptor.
+        let flags = if options.cloexec {
+            flags::EPOLL_CLOEXEC
+        } else {
+            0
+        };
+
+        let ret = unsafe { ffi::epoll_create1(flags) };
+
+        #[cfg(feature = "trace")]
+        crate::trace::syscall("epoll_create1", ret as i64, |_| {});
 
         if ret < 0 {
             return Err(std::io::Error::last_os_error());
@@ -40,13 +57,22 @@ impl SysSelector for Selector {
 
         Ok(Selector {
             epfd: unsafe { OwnedFd::from_raw_fd(ret) },
+            trigger: options.trigger,
         })
     }
 
+    fn backend(&self) -> Backend {
+        Backend::Epoll
+    }
+
+    fn raw_fd(&self) -> Option<RawFd> {
+        Some(self.epfd.as_raw_fd())
+    }
+
     fn register(&self, fd: RawFd, token: Token, interests: Interest) -> io::Result<()> {
         // create a new event (dropped at end of this method)
         let mut event = OsEvent {
-            events: interest_to_epoll(interests),
+            events: interest_to_epoll(interests, self.trigger),
             epoll_data: token.0,
         };
 
@@ -55,6 +81,9 @@ impl SysSelector for Selector {
 
         let res = unsafe { ffi::epoll_ctl(self.epfd.as_raw_fd(), op, fd, &mut event) };
 
+        #[cfg(feature = "trace")]
+        trace_ctl("EPOLL_CTL_ADD", res, fd, Some((token, interests)));
+
         if res < 0 {
             return Err(io::Error::last_os_error());
         }
@@ -69,7 +98,7 @@ impl SysSelector for Selector {
         interests: Interest,
     ) -> io::Result<()> {
         let mut event = OsEvent {
-            events: interest_to_epoll(interests),
+            events: interest_to_epoll(interests, self.trigger),
             epoll_data: token.0,
         };
 
@@ -78,6 +107,9 @@ impl SysSelector for Selector {
 
         let res = unsafe { ffi::epoll_ctl(self.epfd.as_raw_fd(), op, fd, &mut event) };
 
+        #[cfg(feature = "trace")]
+        trace_ctl("EPOLL_CTL_MOD", res, fd, Some((token, interests)));
+
         if res < 0 {
             return Err(io::Error::last_os_error());
         }
@@ -91,6 +123,9 @@ impl SysSelector for Selector {
 
         let res = unsafe { ffi::epoll_ctl(self.epfd.as_raw_fd(), op, fd, std::ptr::null_mut()) };
 
+        #[cfg(feature = "trace")]
+        trace_ctl("EPOLL_CTL_DEL", res, fd, None);
+
         if res < 0 {
             return Err(io::Error::last_os_error());
         }
@@ -102,36 +137,174 @@ impl SysSelector for Selector {
         events: &mut Self::OsEvents,
         timeout: Option<std::time::Duration>,
     ) -> io::Result<usize> {
+        events.clear();
+        let n = self.select(events.spare_capacity_mut(), timeout, None)?;
+        unsafe { events.set_len(n) };
+
+        Ok(n)
+    }
+
+    fn poll_uninit(
+        &self,
+        events: &mut [MaybeUninit<OsEvent>],
+        timeout: Option<Duration>,
+    ) -> io::Result<usize> {
+        self.select(events, timeout, None)
+    }
+
+    fn poll_with_sigmask(
+        &self,
+        events: &mut Self::OsEvents,
+        timeout: Option<Duration>,
+        sigmask: &SigSet,
+    ) -> io::Result<usize> {
+        events.clear();
+        let n = self.select(events.spare_capacity_mut(), timeout, Some(sigmask))?;
+        unsafe { events.set_len(n) };
+
+        Ok(n)
+    }
+
+    fn poll_uninit_with_sigmask(
+        &self,
+        events: &mut [MaybeUninit<OsEvent>],
+        timeout: Option<Duration>,
+        sigmask: &SigSet,
+    ) -> io::Result<usize> {
+        self.select(events, timeout, Some(sigmask))
+    }
+}
+
+impl Selector {
+    /// Wait for events, written to the front of `events`, returning how many there are.
+    fn select(
+        &self,
+        events: &mut [MaybeUninit<OsEvent>],
+        timeout: Option<Duration>,
+        sigmask: Option<&SigSet>,
+    ) -> io::Result<usize> {
+        #[cfg(feature = "trace")]
+        let requested = timeout;
+
+        let (_name, ret) = self.wait(events, timeout, sigmask);
+
+        #[cfg(feature = "trace")]
+        crate::trace::syscall(_name, ret as i64, |call| {
+            call.fd = Some(self.epfd.as_raw_fd());
+            call.timeout = requested;
+        });
+
+        if ret < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+
+        Ok(ret as usize)
+    }
+
+    /// Wait via `epoll_wait`, or `epoll_pwait` when given a signal mask, returning the
+    /// name of the syscall made and its result.
+    fn epoll_wait(
+        &self,
+        events: &mut [MaybeUninit<OsEvent>],
+        timeout: Option<Duration>,
+        sigmask: Option<&SigSet>,
+    ) -> (&'static str, i32) {
         /// A timeout of -1 means block indefinitely
         /// WARNING: below can truncate on sub-millisecond timeouts
         let timeout = timeout
             .map(|duration| duration.as_millis() as i32)
             .unwrap_or(-1);
 
-        events.clear();
+        let epfd = self.epfd.as_raw_fd();
+        let max_events = events.len() as i32;
+
+        match sigmask {
+            None => {
+                let ret = unsafe {
+                    ffi::epoll_wait(epfd, events.as_mut_ptr().cast(), max_events, timeout)
+                };
+                ("epoll_wait", ret)
+            }
+            Some(sigmask) => {
+                let ret = unsafe {
+                    ffi::epoll_pwait(
+                        epfd,
+                        events.as_mut_ptr().cast(),
+                        max_events,
+                        timeout,
+                        sigmask.as_ptr(),
+                    )
+                };
+                ("epoll_pwait", ret)
+            }
+        }
+    }
+
+    #[cfg(not(all(feature = "libc-ffi", target_env = "gnu")))]
+    fn wait(
+        &self,
+        events: &mut [MaybeUninit<OsEvent>],
+        timeout: Option<Duration>,
+        sigmask: Option<&SigSet>,
+    ) -> (&'static str, i32) {
+        self.epoll_wait(events, timeout, sigmask)
+    }
+
+    /// Wait via `epoll_pwait2`, which takes a nanosecond timeout, falling back to
+    /// `epoll_wait` on kernels older than 5.11.
+    #[cfg(all(feature = "libc-ffi", target_env = "gnu"))]
+    fn wait(
+        &self,
+        events: &mut [MaybeUninit<OsEvent>],
+        timeout: Option<Duration>,
+        sigmask: Option<&SigSet>,
+    ) -> (&'static str, i32) {
+        static UNSUPPORTED: AtomicBool = AtomicBool::new(false);
+
+        if UNSUPPORTED.load(Ordering::Relaxed) {
+            return self.epoll_wait(events, timeout, sigmask);
+        }
+
+        let ts = timeout.map(|duration| libc::timespec {
+            tv_sec: duration.as_secs() as libc::time_t,
+            tv_nsec: duration.subsec_nanos() as libc::c_long,
+        });
 
         let ret = unsafe {
-            ffi::epoll_wait(
+            ffi::epoll_pwait2(
                 self.epfd.as_raw_fd(),
-                events.as_mut_ptr(),
-                events.capacity() as i32,
-                timeout,
+                events.as_mut_ptr().cast(),
+                events.len() as i32,
+                ts.as_ref().map_or(std::ptr::null(), |ts| ts),
+                sigmask.map_or(std::ptr::null(), SigSet::as_ptr),
             )
         };
 
-        if ret < 0 {
-            return Err(std::io::Error::last_os_error());
+        if ret < 0 && io::Error::last_os_error().raw_os_error() == Some(libc::ENOSYS) {
+            UNSUPPORTED.store(true, Ordering::Relaxed);
+            return self.epoll_wait(events, timeout, sigmask);
         }
 
-        unsafe { events.set_len(ret as usize) };
-
-        Ok(ret as usize)
+        ("epoll_pwait2", ret)
     }
 }
 
-fn interest_to_epoll(interests: Interest) -> i32 {
-    // set event to edge-triggered mode by default
-    let mut events: i32 = events::EPOLLET;
+#[cfg(feature = "trace")]
+fn trace_ctl(op: &'static str, res: i32, fd: RawFd, registration: Option<(Token, Interest)>) {
+    crate::trace::syscall("epoll_ctl", res as i64, |call| {
+        call.op = Some(op);
+        call.fd = Some(fd);
+        call.token = registration.map(|(token, _)| token);
+        call.interests = registration.map(|(_, interests)| interests);
+    });
+}
+
+fn interest_to_epoll(interests: Interest, trigger: Trigger) -> i32 {
+    // epoll is level-triggered unless asked otherwise
+    let mut events: i32 = match trigger {
+        Trigger::Edge => events::EPOLLET,
+        Trigger::Level => 0,
+    };
 
     if interests.is_readable() {
         events |= events::EPOLLIN | events::EPOLLRDHUP;
@@ -0,0 +1,55 @@
+//! `libc` declares no io_uring structs, so the ones in `ffi` are checked against the sizes
+//! in `linux/io_uring.h`, and filled in by io_uring_setup(2) made through both `ffi` and
+//! `libc::syscall`. The mmap(2) flags used to map the rings are checked against `libc`.
+
+use std::mem::size_of;
+
+use super::ffi::*;
+
+const _: () = assert!(size_of::<io_sqring_offsets>() == 40);
+const _: () = assert!(size_of::<io_cqring_offsets>() == 40);
+const _: () = assert!(size_of::<io_uring_params>() == 120);
+const _: () = assert!(size_of::<io_uring_sqe>() == 64);
+const _: () = assert!(size_of::<io_uring_cqe>() == 16);
+const _: () = assert!(size_of::<io_uring_getevents_arg>() == 24);
+const _: () = assert!(size_of::<kernel_timespec>() == 16);
+
+assert_same_value! {
+    PROT_READ => libc::PROT_READ,
+    PROT_WRITE => libc::PROT_WRITE,
+    MAP_SHARED => libc::MAP_SHARED,
+    MAP_POPULATE => libc::MAP_POPULATE,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::is_available;
+    use super::*;
+
+    #[test]
+    fn setup_through_both_declarations() {
+        if !is_available() {
+            return;
+        }
+
+        let mut ours = io_uring_params::default();
+        let mut theirs = io_uring_params::default();
+
+        unsafe {
+            let fd = io_uring_setup(8, &mut ours);
+            assert!(fd >= 0);
+            libc::close(fd);
+
+            let fd = libc::syscall(libc::SYS_io_uring_setup, 8u32, &mut theirs as *mut _);
+            assert!(fd >= 0);
+            libc::close(fd as i32);
+        }
+
+        assert_eq!(ours.sq_entries, theirs.sq_entries);
+        assert_eq!(ours.cq_entries, theirs.cq_entries);
+        assert_eq!(ours.features, theirs.features);
+        assert_eq!(ours.sq_off.array, theirs.sq_off.array);
+        assert_eq!(ours.cq_off.cqes, theirs.cq_off.cqes);
+        assert_eq!(ours.cq_off.overflow, theirs.cq_off.overflow);
+    }
+}
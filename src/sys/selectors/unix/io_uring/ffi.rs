@@ -0,0 +1,228 @@
+//! This module contains the code related to the io_uring syscalls.
+//!
+//! glibc does not provide wrappers for io_uring, so the syscalls are made via
+//! syscall(2) directly. The rings themselves are shared with the kernel via mmap(2).
+//!
+//! # Other Resources
+//!
+//! - [io_uring_setup man page][1]
+//! - [io_uring_enter man page][2]
+//! - [/usr/include/linux/io_uring.h][3]
+//!
+//! [1]: https://man7.org/linux/man-pages/man2/io_uring_setup.2.html
+//! [2]: https://man7.org/linux/man-pages/man2/io_uring_enter.2.html
+//! [3]: https://github.com/torvalds/linux/blob/master/include/uapi/linux/io_uring.h
+
+#![allow(dead_code, non_camel_case_types)]
+
+use std::ffi::c_void;
+
+use crate::sys::constants::io_uring::syscalls;
+
+/// Offsets of the submission ring fields, relative to the start of its mapping.
+#[derive(Debug, Default)]
+#[repr(C)]
+pub(super) struct io_sqring_offsets {
+    pub head: u32,
+    pub tail: u32,
+    pub ring_mask: u32,
+    pub ring_entries: u32,
+    pub flags: u32,
+    pub dropped: u32,
+    pub array: u32,
+    pub resv1: u32,
+    pub user_addr: u64,
+}
+
+/// Offsets of the completion ring fields, relative to the start of its mapping.
+#[derive(Debug, Default)]
+#[repr(C)]
+pub(super) struct io_cqring_offsets {
+    pub head: u32,
+    pub tail: u32,
+    pub ring_mask: u32,
+    pub ring_entries: u32,
+    pub overflow: u32,
+    pub cqes: u32,
+    pub flags: u32,
+    pub resv1: u32,
+    pub user_addr: u64,
+}
+
+/// Passed to `io_uring_setup`, which fills in the ring sizes, offsets and features.
+#[derive(Debug, Default)]
+#[repr(C)]
+pub(super) struct io_uring_params {
+    pub sq_entries: u32,
+    pub cq_entries: u32,
+    pub flags: u32,
+    pub sq_thread_cpu: u32,
+    pub sq_thread_idle: u32,
+    pub features: u32,
+    pub wq_fd: u32,
+    pub resv: [u32; 3],
+    pub sq_off: io_sqring_offsets,
+    pub cq_off: io_cqring_offsets,
+}
+
+/// Submission queue entry. 64 bytes, of which only the fields used for poll requests
+/// are named; the rest are covered by `_pad`.
+#[derive(Clone, Copy, Debug, Default)]
+#[repr(C)]
+pub(super) struct io_uring_sqe {
+    pub opcode: u8,
+    pub flags: u8,
+    pub ioprio: u16,
+    pub fd: i32,
+    /// union with `addr2`
+    pub off: u64,
+    /// union with `splice_off_in`. The `user_data` of the request to remove for
+    /// `IORING_OP_POLL_REMOVE`.
+    pub addr: u64,
+    /// `IORING_POLL_ADD_*` flags for `IORING_OP_POLL_ADD`.
+    pub len: u32,
+    /// union of the per opcode flags, `poll32_events` for poll requests.
+    pub poll32_events: u32,
+    /// Returned unchanged in the completion of this request.
+    pub user_data: u64,
+    pub _pad: [u64; 3],
+}
+
+/// Completion queue entry.
+#[derive(Clone, Copy, Debug, Default)]
+#[repr(C)]
+pub(super) struct io_uring_cqe {
+    pub user_data: u64,
+    /// Result of the request: the ready poll mask for polls, or a negated errno.
+    pub res: i32,
+    pub flags: u32,
+}
+
+/// Passed via the `sig` argument of `io_uring_enter` with `IORING_ENTER_EXT_ARG`.
+#[derive(Debug, Default)]
+#[repr(C)]
+pub(super) struct io_uring_getevents_arg {
+    pub sigmask: u64,
+    pub sigmask_sz: u32,
+    pub pad: u32,
+    /// pointer to a `__kernel_timespec`
+    pub ts: u64,
+}
+
+/// `struct __kernel_timespec`, which is 64 bit on every architecture.
+#[derive(Debug, Default)]
+#[repr(C)]
+pub(super) struct kernel_timespec {
+    pub tv_sec: i64,
+    pub tv_nsec: i64,
+}
+
+impl From<std::time::Duration> for kernel_timespec {
+    fn from(timeout: std::time::Duration) -> Self {
+        kernel_timespec {
+            tv_sec: timeout.as_secs() as i64,
+            tv_nsec: timeout.subsec_nanos() as i64,
+        }
+    }
+}
+
+#[cfg(not(feature = "libc-ffi"))]
+#[link(name = "c")] // link to C standard library / libc
+extern "C" {
+    /// indirect system call
+    ///
+    /// https://man7.org/linux/man-pages/man2/syscall.2.html
+    ///
+    /// long syscall(long number, ...);
+    fn syscall(number: i64, ...) -> i64;
+
+    /// map the rings into our address space
+    ///
+    /// https://man7.org/linux/man-pages/man2/mmap.2.html
+    ///
+    /// void *mmap(void addr[.length], size_t length, int prot, int flags, int fd, off_t offset);
+    pub(super) fn mmap(
+        addr: *mut c_void,
+        length: usize,
+        prot: i32,
+        flags: i32,
+        fd: i32,
+        offset: i64,
+    ) -> *mut c_void;
+
+    /// int munmap(void addr[.length], size_t length);
+    pub(super) fn munmap(addr: *mut c_void, length: usize) -> i32;
+}
+
+// The same calls via the declarations of the `libc` crate, enabled by the `libc-ffi`
+// feature instead of the hand-written ones above.
+#[cfg(feature = "libc-ffi")]
+use libc::syscall;
+
+#[cfg(feature = "libc-ffi")]
+pub(super) use libc::{mmap, munmap};
+
+/// setup a context for performing asynchronous I/O
+///
+/// int io_uring_setup(u32 entries, struct io_uring_params *p);
+///
+/// Returns the ring file descriptor, or -1 with errno set.
+pub(super) unsafe fn io_uring_setup(entries: u32, params: *mut io_uring_params) -> i32 {
+    // variadic arguments are widened to `long`, as that is what syscall(2) reads them as
+    syscall(syscalls::SYS_IO_URING_SETUP, entries as i64, params) as i32
+}
+
+/// initiate and/or complete asynchronous I/O
+///
+/// int io_uring_enter(unsigned int fd, unsigned int to_submit, unsigned int min_complete,
+///                    unsigned int flags, const void *arg, size_t argsz);
+///
+/// Returns the number of submission queue entries consumed, or -1 with errno set.
+pub(super) unsafe fn io_uring_enter(
+    fd: i32,
+    to_submit: u32,
+    min_complete: u32,
+    flags: u32,
+    arg: *const c_void,
+    argsz: usize,
+) -> i32 {
+    syscall(
+        syscalls::SYS_IO_URING_ENTER,
+        fd as i64,
+        to_submit as i64,
+        min_complete as i64,
+        flags as i64,
+        arg,
+        argsz as i64,
+    ) as i32
+}
+
+#[cfg(not(feature = "libc-ffi"))]
+mod mmap_flags {
+    use std::ffi::c_void;
+
+    pub(in super::super) const PROT_READ: i32 = 0x1;
+    pub(in super::super) const PROT_WRITE: i32 = 0x2;
+    pub(in super::super) const MAP_SHARED: i32 = 0x01;
+    pub(in super::super) const MAP_POPULATE: i32 = 0x08000;
+    pub(in super::super) const MAP_FAILED: *mut c_void = !0 as *mut c_void;
+}
+
+#[cfg(not(feature = "libc-ffi"))]
+pub(super) use mmap_flags::*;
+
+#[cfg(feature = "libc-ffi")]
+pub(super) use libc::{MAP_FAILED, MAP_POPULATE, MAP_SHARED, PROT_READ, PROT_WRITE};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn struct_sizes_match_kernel() {
+        assert_eq!(std::mem::size_of::<io_uring_params>(), 120);
+        assert_eq!(std::mem::size_of::<io_uring_sqe>(), 64);
+        assert_eq!(std::mem::size_of::<io_uring_cqe>(), 16);
+        assert_eq!(std::mem::size_of::<io_uring_getevents_arg>(), 24);
+    }
+}
@@ -0,0 +1,12 @@
+//! io_uring implementation for Linux.
+//!
+//! Exports following types:
+//! - `Selector`
+#![allow(unused, dead_code)]
+mod ffi;
+mod selector;
+
+#[cfg(feature = "abi-check")]
+mod abi_check;
+
+pub use selector::{is_available, is_unavailable_error, Selector};
@@ -0,0 +1,653 @@
+//! Interface to io_uring, using it purely as a readiness notification queue.
+//!
+//! Each registered file descriptor gets a multishot `IORING_OP_POLL_ADD` request, whose
+//! `user_data` identifies the registration. Multishot polls are edge-triggered unless
+//! asked otherwise, which matches the behaviour of the epoll selector. In level-triggered
+//! mode single-shot polls are used instead, re-armed after every completion, so that a
+//! source still ready is reported again by the next call to `poll`.
+//!
+//! Registration changes are only queued on the submission ring and are submitted together
+//! with the next call to `poll`, in the same `io_uring_enter` syscall that waits for
+//! completions. Errors from a registration, e.g. `EBADF`, are therefore reported as an
+//! error event for its token rather than from `register`.
+
+use std::collections::HashMap;
+use std::ffi::c_void;
+use std::io;
+use std::mem::size_of;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::interests::{Interest, Trigger};
+use crate::interfaces::{SelectorOptions, SysSelector, Token};
+use crate::poll::Backend;
+use crate::signal::SigSet;
+
+// types used for interfacing with io_uring syscalls
+use crate::sys::constants::epoll::events;
+use crate::sys::constants::io_uring::{cqe_flags, enter_flags, features, offsets, ops, poll_flags};
+use crate::sys::events::{OsEvent, OsEvents};
+
+// io_uring syscalls
+use super::ffi::{self, io_uring_cqe, io_uring_params, io_uring_sqe};
+
+/// Bounds on the number of submission queue entries requested from the kernel, which is
+/// otherwise derived from the capacity hint. The upper bound is the kernel's own limit.
+const MIN_RING_ENTRIES: u32 = 256;
+const MAX_RING_ENTRIES: u32 = 32768;
+
+/// `user_data` of `IORING_OP_POLL_REMOVE` requests, whose completions are ignored.
+const REMOVE_USER_DATA: u64 = u64::MAX;
+
+/// Features the selector cannot work without.
+const REQUIRED_FEATURES: u32 =
+    features::IORING_FEAT_NODROP | features::IORING_FEAT_EXT_ARG | features::IORING_FEAT_RSRC_TAGS;
+
+/// Size of the kernel's `sigset_t`, `_NSIG / 8`, which is smaller than the one of glibc.
+/// The kernel rejects any other size with `EINVAL`.
+const KERNEL_SIGSET_SIZE: u32 = 8;
+
+/// Whether io_uring can be used by this process.
+///
+/// io_uring is often disabled in hardened environments, either via the
+/// `kernel.io_uring_disabled` sysctl or by a seccomp filter returning `ENOSYS` or `EPERM`.
+pub fn is_available() -> bool {
+    Ring::setup(2).is_ok()
+}
+
+/// Whether an error from `Selector::new` means io_uring is unavailable, rather than the
+/// process running out of resources.
+pub fn is_unavailable_error(err: &io::Error) -> bool {
+    err.kind() == io::ErrorKind::Unsupported
+        || matches!(
+            err.raw_os_error(),
+            Some(libc::ENOSYS) | Some(libc::EPERM) | Some(libc::EACCES)
+        )
+}
+
+/// The mapped submission and completion rings.
+struct Ring {
+    fd: OwnedFd,
+
+    /// mapping of the submission ring, which also holds the completion ring when the
+    /// kernel supports `IORING_FEAT_SINGLE_MMAP`
+    sq_ring: *mut c_void,
+    sq_ring_len: usize,
+    cq_ring: *mut c_void,
+    cq_ring_len: usize,
+    sqes: *mut io_uring_sqe,
+    sqes_len: usize,
+
+    sq_head: *const AtomicU32,
+    sq_tail: *const AtomicU32,
+    sq_mask: u32,
+    sq_entries: u32,
+    sq_array: *mut u32,
+
+    cq_head: *const AtomicU32,
+    cq_tail: *const AtomicU32,
+    cq_mask: u32,
+    cqes: *const io_uring_cqe,
+
+    /// Entries pushed to the submission ring, but not yet submitted.
+    pending: u32,
+}
+
+// The raw pointers all point into mappings owned by the ring.
+unsafe impl Send for Ring {}
+
+impl Ring {
+    fn setup(entries: u32) -> io::Result<Self> {
+        if disabled_by_sysctl() {
+            return Err(io::Error::from_raw_os_error(libc::EPERM));
+        }
+
+        let mut params = io_uring_params::default();
+
+        let ret = unsafe { ffi::io_uring_setup(entries, &mut params) };
+
+        #[cfg(feature = "trace")]
+        crate::trace::syscall("io_uring_setup", ret as i64, |_| {});
+
+        if ret < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let fd = unsafe { OwnedFd::from_raw_fd(ret) };
+
+        if params.features & REQUIRED_FEATURES != REQUIRED_FEATURES {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "kernel io_uring does not support multishot poll",
+            ));
+        }
+
+        let sq_ring_len =
+            params.sq_off.array as usize + params.sq_entries as usize * size_of::<u32>();
+        let cq_ring_len =
+            params.cq_off.cqes as usize + params.cq_entries as usize * size_of::<io_uring_cqe>();
+
+        let single_mmap = params.features & features::IORING_FEAT_SINGLE_MMAP != 0;
+
+        let sq_ring_len = if single_mmap {
+            sq_ring_len.max(cq_ring_len)
+        } else {
+            sq_ring_len
+        };
+
+        let sq_ring = map(&fd, sq_ring_len, offsets::IORING_OFF_SQ_RING)?;
+
+        let cq_ring = if single_mmap {
+            sq_ring
+        } else {
+            match map(&fd, cq_ring_len, offsets::IORING_OFF_CQ_RING) {
+                Ok(ptr) => ptr,
+                Err(e) => {
+                    unsafe { ffi::munmap(sq_ring, sq_ring_len) };
+                    return Err(e);
+                }
+            }
+        };
+
+        let sqes_len = params.sq_entries as usize * size_of::<io_uring_sqe>();
+
+        let sqes = match map(&fd, sqes_len, offsets::IORING_OFF_SQES) {
+            Ok(ptr) => ptr as *mut io_uring_sqe,
+            Err(e) => {
+                unsafe { ffi::munmap(sq_ring, sq_ring_len) };
+                if !single_mmap {
+                    unsafe { ffi::munmap(cq_ring, cq_ring_len) };
+                }
+                return Err(e);
+            }
+        };
+
+        let sq = |offset: u32| unsafe { sq_ring.add(offset as usize) };
+        let cq = |offset: u32| unsafe { cq_ring.add(offset as usize) };
+
+        unsafe {
+            Ok(Ring {
+                sq_head: sq(params.sq_off.head) as *const AtomicU32,
+                sq_tail: sq(params.sq_off.tail) as *const AtomicU32,
+                sq_mask: *(sq(params.sq_off.ring_mask) as *const u32),
+                sq_entries: *(sq(params.sq_off.ring_entries) as *const u32),
+                sq_array: sq(params.sq_off.array) as *mut u32,
+
+                cq_head: cq(params.cq_off.head) as *const AtomicU32,
+                cq_tail: cq(params.cq_off.tail) as *const AtomicU32,
+                cq_mask: *(cq(params.cq_off.ring_mask) as *const u32),
+                cqes: cq(params.cq_off.cqes) as *const io_uring_cqe,
+
+                fd,
+                sq_ring,
+                sq_ring_len,
+                cq_ring,
+                cq_ring_len: if single_mmap { 0 } else { cq_ring_len },
+                sqes,
+                sqes_len,
+                pending: 0,
+            })
+        }
+    }
+
+    /// Queue an entry on the submission ring, submitting early if the ring is full.
+    fn push(&mut self, sqe: io_uring_sqe) -> io::Result<()> {
+        let tail = unsafe { (*self.sq_tail).load(Ordering::Relaxed) };
+        let head = unsafe { (*self.sq_head).load(Ordering::Acquire) };
+
+        if tail.wrapping_sub(head) == self.sq_entries {
+            self.enter(0, None, None)?;
+        }
+
+        let index = tail & self.sq_mask;
+
+        unsafe {
+            *self.sqes.add(index as usize) = sqe;
+            *self.sq_array.add(index as usize) = index;
+            // publish the entry to the kernel
+            (*self.sq_tail).store(tail.wrapping_add(1), Ordering::Release);
+        }
+
+        self.pending += 1;
+
+        Ok(())
+    }
+
+    /// Make room for `n` entries, submitting the pending ones if there is not enough, so
+    /// that the next `n` pushes cannot fail.
+    fn reserve(&mut self, n: u32) -> io::Result<()> {
+        let tail = unsafe { (*self.sq_tail).load(Ordering::Relaxed) };
+        let head = unsafe { (*self.sq_head).load(Ordering::Acquire) };
+
+        if self.sq_entries - tail.wrapping_sub(head) < n {
+            self.enter(0, None, None)?;
+        }
+
+        Ok(())
+    }
+
+    /// Submit pending entries and wait for at least `min_complete` completions, with
+    /// `sigmask` as the thread's signal mask while waiting.
+    fn enter(
+        &mut self,
+        min_complete: u32,
+        timeout: Option<Duration>,
+        sigmask: Option<&SigSet>,
+    ) -> io::Result<()> {
+        let mut flags = 0;
+        let mut ts = ffi::kernel_timespec::default();
+        let mut arg = ffi::io_uring_getevents_arg::default();
+        let mut argp = std::ptr::null();
+        let mut argsz = 0;
+
+        if min_complete > 0 {
+            flags |= enter_flags::IORING_ENTER_GETEVENTS;
+
+            if let Some(timeout) = timeout {
+                ts = timeout.into();
+                arg.ts = &ts as *const _ as u64;
+            }
+
+            if let Some(sigmask) = sigmask {
+                arg.sigmask = sigmask.as_ptr() as u64;
+                arg.sigmask_sz = KERNEL_SIGSET_SIZE;
+            }
+
+            if timeout.is_some() || sigmask.is_some() {
+                argp = &arg as *const _ as *const c_void;
+                argsz = size_of::<ffi::io_uring_getevents_arg>();
+                flags |= enter_flags::IORING_ENTER_EXT_ARG;
+            }
+        }
+
+        let ret = unsafe {
+            ffi::io_uring_enter(
+                self.fd.as_raw_fd(),
+                self.pending,
+                min_complete,
+                flags,
+                argp,
+                argsz,
+            )
+        };
+
+        #[cfg(feature = "trace")]
+        crate::trace::syscall("io_uring_enter", ret as i64, |call| {
+            call.op = (min_complete > 0).then_some("IORING_ENTER_GETEVENTS");
+            call.fd = Some(self.fd.as_raw_fd());
+            call.timeout = timeout;
+        });
+
+        if ret < 0 {
+            let err = io::Error::last_os_error();
+
+            return match err.raw_os_error() {
+                // timed out waiting for completions
+                Some(libc::ETIME) => Ok(()),
+                _ => Err(err),
+            };
+        }
+
+        self.pending -= ret as u32;
+
+        Ok(())
+    }
+
+    fn has_completions(&self) -> bool {
+        let head = unsafe { (*self.cq_head).load(Ordering::Relaxed) };
+        let tail = unsafe { (*self.cq_tail).load(Ordering::Acquire) };
+        head != tail
+    }
+
+    /// Hand completions to `f` until it returns false or the ring is empty.
+    ///
+    /// The completion `f` returned false for is left on the ring.
+    fn reap(&mut self, mut f: impl FnMut(io_uring_cqe) -> bool) {
+        let mut head = unsafe { (*self.cq_head).load(Ordering::Relaxed) };
+        let tail = unsafe { (*self.cq_tail).load(Ordering::Acquire) };
+
+        while head != tail {
+            let cqe = unsafe { *self.cqes.add((head & self.cq_mask) as usize) };
+
+            if !f(cqe) {
+                break;
+            }
+
+            head = head.wrapping_add(1);
+        }
+
+        // hand the slots back to the kernel
+        unsafe { (*self.cq_head).store(head, Ordering::Release) };
+    }
+}
+
+impl Drop for Ring {
+    fn drop(&mut self) {
+        unsafe {
+            ffi::munmap(self.sqes as *mut c_void, self.sqes_len);
+            ffi::munmap(self.sq_ring, self.sq_ring_len);
+
+            if self.cq_ring_len > 0 {
+                ffi::munmap(self.cq_ring, self.cq_ring_len);
+            }
+        }
+        // `fd` is closed after this by `OwnedFd`
+    }
+}
+
+fn map(fd: &OwnedFd, len: usize, offset: i64) -> io::Result<*mut c_void> {
+    let ptr = unsafe {
+        ffi::mmap(
+            std::ptr::null_mut(),
+            len,
+            ffi::PROT_READ | ffi::PROT_WRITE,
+            ffi::MAP_SHARED | ffi::MAP_POPULATE,
+            fd.as_raw_fd(),
+            offset,
+        )
+    };
+
+    if ptr == ffi::MAP_FAILED {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(ptr)
+}
+
+/// `kernel.io_uring_disabled` is 0 when enabled for everyone, 1 when restricted to the
+/// `kernel.io_uring_group`, and 2 when disabled. Kernels before 6.6 have no such sysctl.
+fn disabled_by_sysctl() -> bool {
+    std::fs::read_to_string("/proc/sys/kernel/io_uring_disabled")
+        .ok()
+        .and_then(|value| value.trim().parse::<u32>().ok())
+        .is_some_and(|value| value >= 2)
+}
+
+struct Entry {
+    /// `user_data` of the poll request currently armed for the file descriptor.
+    id: u64,
+    token: Token,
+    events: u32,
+}
+
+struct State {
+    ring: Ring,
+    entries: HashMap<RawFd, Entry>,
+    /// Reverse lookup from a request's `user_data` to its file descriptor.
+    ids: HashMap<u64, RawFd>,
+    next_id: u64,
+    trigger: Trigger,
+}
+
+impl State {
+    fn add(&mut self, fd: RawFd, token: Token, interests: Interest) -> io::Result<()> {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let events = interest_to_poll(interests);
+
+        self.ring.push(poll_add(fd, events, id, self.trigger))?;
+        self.entries.insert(fd, Entry { id, token, events });
+        self.ids.insert(id, fd);
+
+        Ok(())
+    }
+
+    fn remove(&mut self, fd: RawFd) -> io::Result<()> {
+        let id = self
+            .entries
+            .get(&fd)
+            .ok_or_else(|| io::Error::from_raw_os_error(libc::ENOENT))?
+            .id;
+
+        // forgotten only once the removal is queued, so that a failed push leaves the
+        // registration in place
+        self.ring.push(io_uring_sqe {
+            opcode: ops::IORING_OP_POLL_REMOVE,
+            fd: -1,
+            addr: id,
+            user_data: REMOVE_USER_DATA,
+            ..Default::default()
+        })?;
+
+        // completions still on the ring for this request are skipped from here on
+        self.entries.remove(&fd);
+        self.ids.remove(&id);
+
+        Ok(())
+    }
+}
+
+/// Rather than the registry, the selector holds the ring
+pub struct Selector {
+    state: Mutex<State>,
+}
+
+impl SysSelector for Selector {
+    type OsEvent = OsEvent;
+    type OsEvents = Vec<Self::OsEvent>;
+
+    fn with_options(options: SelectorOptions) -> io::Result<Self> {
+        let entries = u32::try_from(options.capacity)
+            .unwrap_or(MAX_RING_ENTRIES)
+            .clamp(MIN_RING_ENTRIES, MAX_RING_ENTRIES)
+            .next_power_of_two();
+
+        let ring = Ring::setup(entries)?;
+
+        // the kernel always sets close on exec on the ring file descriptor
+        if !options.cloexec {
+            let ret = unsafe { libc::fcntl(ring.fd.as_raw_fd(), libc::F_SETFD, 0) };
+
+            if ret < 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+
+        Ok(Selector {
+            state: Mutex::new(State {
+                ring,
+                entries: HashMap::new(),
+                ids: HashMap::new(),
+                next_id: 0,
+                trigger: options.trigger,
+            }),
+        })
+    }
+
+    fn backend(&self) -> Backend {
+        Backend::IoUring
+    }
+
+    /// Readable once completions are posted to the ring. Registrations made since the
+    /// last poll are only submitted by the next one.
+    fn raw_fd(&self) -> Option<RawFd> {
+        Some(self.state.lock().unwrap().ring.fd.as_raw_fd())
+    }
+
+    fn register(&self, fd: RawFd, token: Token, interests: Interest) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+
+        // mirror epoll_ctl(EPOLL_CTL_ADD) on a file descriptor that is already registered
+        if state.entries.contains_key(&fd) {
+            return Err(io::Error::from_raw_os_error(libc::EEXIST));
+        }
+
+        state.add(fd, token, interests)
+    }
+
+    fn reregister(&self, fd: RawFd, token: Token, interests: Interest) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+
+        if !state.entries.contains_key(&fd) {
+            return Err(io::Error::from_raw_os_error(libc::ENOENT));
+        }
+
+        // room for both the removal and the new poll, so that the selector cannot be left
+        // without the file descriptor after removing it
+        state.ring.reserve(2)?;
+        state.remove(fd)?;
+        state.add(fd, token, interests)
+    }
+
+    fn deregister(&self, fd: RawFd) -> io::Result<()> {
+        self.state.lock().unwrap().remove(fd)
+    }
+
+    fn poll(&self, events: &mut Self::OsEvents, timeout: Option<Duration>) -> io::Result<usize> {
+        self.select(events, timeout, None)
+    }
+
+    fn poll_with_sigmask(
+        &self,
+        events: &mut Self::OsEvents,
+        timeout: Option<Duration>,
+        sigmask: &SigSet,
+    ) -> io::Result<usize> {
+        self.select(events, timeout, Some(sigmask))
+    }
+}
+
+impl Selector {
+    fn select(
+        &self,
+        events: &mut OsEvents,
+        timeout: Option<Duration>,
+        sigmask: Option<&SigSet>,
+    ) -> io::Result<usize> {
+        events.clear();
+
+        // same error epoll_wait returns for a maxevents of zero
+        if events.capacity() == 0 {
+            return Err(io::Error::from_raw_os_error(libc::EINVAL));
+        }
+
+        let mut state = self.state.lock().unwrap();
+        let state = &mut *state;
+
+        // Submitting queued registrations and waiting is a single syscall. There is no
+        // need to wait if completions from a previous call are still on the ring.
+        let min_complete = if state.ring.has_completions() { 0 } else { 1 };
+
+        if min_complete > 0 || state.ring.pending > 0 {
+            state.ring.enter(min_complete, timeout, sigmask)?;
+        }
+
+        let mut terminated = Vec::new();
+
+        state.ring.reap(|cqe| {
+            let Some(fd) = state.ids.get(&cqe.user_data) else {
+                // poll removal, or a request that has since been removed
+                return true;
+            };
+
+            if events.len() == events.capacity() {
+                return false;
+            }
+
+            let entry = &state.entries[fd];
+
+            let ready = if cqe.res < 0 {
+                // the poll request failed, e.g. `EBADF` for a closed file descriptor
+                events::EPOLLERR
+            } else {
+                cqe.res
+            };
+
+            events.push(OsEvent {
+                events: ready,
+                epoll_data: entry.token.0,
+            });
+
+            // A multishot poll terminates when the kernel cannot post further completions,
+            // and a single-shot poll after every completion. Re-arm it, unless the request
+            // itself failed.
+            if cqe.flags & cqe_flags::IORING_CQE_F_MORE == 0 && cqe.res >= 0 {
+                terminated.push(*fd);
+            }
+
+            true
+        });
+
+        for fd in terminated {
+            let entry = &state.entries[&fd];
+            let sqe = poll_add(fd, entry.events, entry.id, state.trigger);
+            state.ring.push(sqe)?;
+        }
+
+        Ok(events.len())
+    }
+}
+
+fn poll_add(fd: RawFd, events: u32, id: u64, trigger: Trigger) -> io_uring_sqe {
+    let flags = match trigger {
+        Trigger::Edge => poll_flags::IORING_POLL_ADD_MULTI,
+        Trigger::Level => 0,
+    };
+
+    io_uring_sqe {
+        opcode: ops::IORING_OP_POLL_ADD,
+        fd,
+        len: flags,
+        poll32_events: events,
+        user_data: id,
+        ..Default::default()
+    }
+}
+
+fn interest_to_poll(interests: Interest) -> u32 {
+    // multishot polls are edge-triggered by default, there is no need for EPOLLET
+    let mut events: i32 = 0;
+
+    if interests.is_readable() {
+        events |= events::EPOLLIN | events::EPOLLRDHUP;
+    }
+
+    if interests.is_writable() {
+        events |= events::EPOLLOUT;
+    }
+
+    events as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn multishot_poll_is_edge_triggered() {
+        if !is_available() {
+            // nothing to test, `Selector::new` falls back to another backend
+            return;
+        }
+
+        use std::io::Write;
+        use std::os::unix::net::UnixStream;
+
+        let selector = Selector::new().unwrap();
+        let (a, mut b) = UnixStream::pair().unwrap();
+        let mut events = Vec::with_capacity(8);
+        let timeout = Some(Duration::from_millis(50));
+
+        selector
+            .register(a.as_raw_fd(), Token(3), Interest::READABLE)
+            .unwrap();
+
+        b.write_all(b"one").unwrap();
+        assert_eq!(selector.poll(&mut events, timeout).unwrap(), 1);
+        assert_eq!({ events[0].epoll_data }, 3);
+
+        // no new edge
+        assert_eq!(selector.poll(&mut events, timeout).unwrap(), 0);
+
+        // a new edge, without draining first
+        b.write_all(b"two").unwrap();
+        assert_eq!(selector.poll(&mut events, timeout).unwrap(), 1);
+
+        selector.deregister(a.as_raw_fd()).unwrap();
+        b.write_all(b"three").unwrap();
+        assert_eq!(selector.poll(&mut events, timeout).unwrap(), 0);
+    }
+}
@@ -0,0 +1,89 @@
+//! Checks `ffi::timespec` against `libc::timespec`, and runs kevent through both
+//! declarations, checking that each side reads what the other wrote.
+
+use super::ffi::timespec;
+
+assert_same_layout!(timespec => libc::timespec {
+    tv_sec => tv_sec,
+    tv_nsec => tv_nsec,
+});
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::os::fd::AsRawFd;
+    use std::os::unix::net::UnixStream;
+    use std::ptr;
+
+    use super::super::ffi;
+    use super::*;
+    use crate::sys::constants::kqueue::{filters, flags};
+    use crate::sys::events::kqueue::OsEvent;
+
+    /// Uses the upper bits, which a layout mismatch would truncate or shift.
+    const TOKEN: usize = 0x0123_4567_89ab_cdef;
+
+    fn zero() -> timespec {
+        timespec {
+            tv_sec: 0,
+            tv_nsec: 0,
+        }
+    }
+
+    #[test]
+    fn kevent_through_both_declarations() {
+        let (a, mut b) = UnixStream::pair().unwrap();
+        b.write_all(b"hello").unwrap();
+
+        unsafe {
+            let kq = ffi::kqueue();
+            assert!(kq >= 0);
+
+            // registered via ffi, waited on via libc
+            let change = OsEvent {
+                ident: a.as_raw_fd() as usize,
+                filter: filters::EVFILT_READ,
+                flags: flags::EV_ADD,
+                fflags: 0,
+                data: 0,
+                udata: TOKEN,
+            };
+            let ret = ffi::kevent(kq, &change, 1, ptr::null_mut(), 0, ptr::null());
+            assert_eq!(ret, 0);
+
+            let mut theirs: [libc::kevent; 4] = std::mem::zeroed();
+            let timeout = libc::timespec {
+                tv_sec: 0,
+                tv_nsec: 0,
+            };
+            let n = libc::kevent(kq, ptr::null(), 0, theirs.as_mut_ptr(), 4, &timeout);
+            assert_eq!(n, 1);
+            assert_eq!(theirs[0].ident, a.as_raw_fd() as usize);
+            assert_eq!(theirs[0].filter, libc::EVFILT_READ);
+            assert_eq!(theirs[0].data, 5);
+            assert_eq!(theirs[0].udata as usize, TOKEN);
+
+            // modified via libc, waited on via ffi
+            let change = libc::kevent {
+                ident: a.as_raw_fd() as usize,
+                filter: libc::EVFILT_READ,
+                flags: libc::EV_ADD,
+                fflags: 0,
+                data: 0,
+                udata: !TOKEN as *mut libc::c_void,
+            };
+            let ret = libc::kevent(kq, &change, 1, ptr::null_mut(), 0, ptr::null());
+            assert_eq!(ret, 0);
+
+            let mut ours: Vec<OsEvent> = Vec::with_capacity(4);
+            let n = ffi::kevent(kq, ptr::null(), 0, ours.as_mut_ptr(), 4, &zero());
+            assert_eq!(n, 1);
+            ours.set_len(n as usize);
+            assert_eq!(ours[0].filter, filters::EVFILT_READ);
+            assert_eq!(ours[0].data, 5);
+            assert_eq!(ours[0].udata, !TOKEN);
+
+            assert_eq!(ffi::close(kq), 0);
+        }
+    }
+}
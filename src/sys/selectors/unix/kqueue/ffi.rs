@@ -33,6 +33,7 @@ use std::ops::{BitAnd, BitOr};
 
 use crate::sys::events::kqueue::OsEvent;
 
+#[cfg(not(feature = "libc-ffi"))]
 #[link(name = "c")] // link to libc
 extern "C" {
     // use C calling convention
@@ -70,6 +71,45 @@ extern "C" {
     pub(super) fn close(fd: i32) -> i32;
 }
 
+/// The same calls made via the declarations of the `libc` crate, enabled by the `libc-ffi`
+/// feature instead of the hand-written ones above.
+///
+/// `OsEvent` and `timespec` have the same layout as `libc::kevent` and `libc::timespec`, as
+/// checked by the `abi-check` feature, so pointers to them are passed on unchanged.
+#[cfg(feature = "libc-ffi")]
+mod via_libc {
+    use super::{timespec, OsEvent};
+
+    pub(in super::super) unsafe fn kqueue() -> i32 {
+        libc::kqueue()
+    }
+
+    pub(in super::super) unsafe fn kevent(
+        kqfd: i32,
+        changelist: *const OsEvent,
+        nchanges: i32,
+        eventlist: *mut OsEvent,
+        nevents: i32,
+        timeout: *const timespec,
+    ) -> i32 {
+        libc::kevent(
+            kqfd,
+            changelist.cast(),
+            nchanges,
+            eventlist.cast(),
+            nevents,
+            timeout.cast(),
+        )
+    }
+
+    pub(in super::super) unsafe fn close(fd: i32) -> i32 {
+        libc::close(fd)
+    }
+}
+
+#[cfg(feature = "libc-ffi")]
+pub(super) use via_libc::*;
+
 // linux x32 compatibility
 // See https://sourceware.org/bugzilla/show_bug.cgi?id=16437
 #[repr(C)]
@@ -6,4 +6,7 @@
 mod ffi;
 mod selector;
 
+#[cfg(feature = "abi-check")]
+mod abi_check;
+
 pub use selector::Selector;
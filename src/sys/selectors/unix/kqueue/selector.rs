@@ -7,8 +7,9 @@ use std::io;
 use std::mem::MaybeUninit;
 use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
 
-use crate::interests::Interest;
-use crate::interfaces::{SysSelector, Token};
+use crate::interests::{Interest, Trigger};
+use crate::interfaces::{Change, SelectorOptions, SysSelector, Token};
+use crate::poll::Backend;
 
 // types used for interfacing with kqueue syscalls
 use crate::sys::constants::kqueue::{fflags, filters, flags};
@@ -21,26 +22,51 @@ pub struct Selector {
     /// OwnedFd is a wrapper around an i32.
     /// It closes the file descriptor when dropped: no need for `close` syscall.
     kq: OwnedFd,
+
+    /// Trigger mode applied to every registration.
+    trigger: Trigger,
 }
 
 impl SysSelector for Selector {
     type OsEvent = OsEvent;
     type OsEvents = Vec<Self::OsEvent>;
 
-    fn new() -> io::Result<Self> {
-        // TODO: need to set close on execute on the file descriptor, to stop
-        // any forked processes from inheriting a clone of the file descriptor.
+    fn with_options(options: SelectorOptions) -> io::Result<Self> {
         let ret = unsafe { ffi::kqueue() };
 
+        #[cfg(feature = "trace")]
+        crate::trace::syscall("kqueue", ret as i64, |_| {});
+
         if ret < 0 {
             return Err(std::io::Error::last_os_error());
         }
 
+        let kq = unsafe { OwnedFd::from_raw_fd(ret) };
+
+        // kqueue has no flags argument, so close on exec is set separately. This stops
+        // programs started via `exec` from inheriting a clone of the file descriptor.
+        if options.cloexec {
+            let ret = unsafe { libc::fcntl(kq.as_raw_fd(), libc::F_SETFD, libc::FD_CLOEXEC) };
+
+            if ret < 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+        }
+
         Ok(Selector {
-            kq: unsafe { OwnedFd::from_raw_fd(ret) },
+            kq,
+            trigger: options.trigger,
         })
     }
 
+    fn backend(&self) -> Backend {
+        Backend::Kqueue
+    }
+
+    fn raw_fd(&self) -> Option<RawFd> {
+        Some(self.kq.as_raw_fd())
+    }
+
     fn register(&self, fd: RawFd, token: Token, interests: Interest) -> io::Result<()> {
         // NOTE: A new event needs to be created for each filter being used.
         // Currently supported filters are for reading and writing only, hence
@@ -48,13 +74,14 @@ impl SysSelector for Selector {
 
         // It is important to set EV_CLEAR or kqueue will not reset the event after it has been
         // triggered. i.e. by default is behaves in a level triggered mode.
-        let flags = flags::EV_CLEAR | flags::EV_RECEIPT | flags::EV_ADD;
-
-        // TODO: move to using preallocated arrays. However, MaybeUninit will not drop T
-        // when it is dropped.
-        // let mut changelist: [MaybeUninit<OsEvent>; 1] = unsafe { MaybeUninit::uninit_array() };
+        let flags = match self.trigger {
+            Trigger::Edge => flags::EV_CLEAR | flags::EV_RECEIPT | flags::EV_ADD,
+            Trigger::Level => flags::EV_RECEIPT | flags::EV_ADD,
+        };
 
-        let mut changelist: Vec<OsEvent> = Vec::new();
+        // One kevent per filter, at most read, write and the test-only timer. MaybeUninit
+        // never drops its contents, which is fine for a kevent, as it holds no resources.
+        let mut changelist: [MaybeUninit<OsEvent>; 3] = [const { MaybeUninit::uninit() }; 3];
         let mut nchanges: i32 = 0;
 
         // convert out standard `Event` into the `kevent` struct
@@ -68,8 +95,7 @@ impl SysSelector for Selector {
                 udata: token.0,
             };
 
-            changelist.push(kevent);
-            // changelist[nchanges as usize] = MaybeUninit::new(kevent);
+            changelist[nchanges as usize] = MaybeUninit::new(kevent);
             nchanges += 1;
         }
 
@@ -83,8 +109,7 @@ impl SysSelector for Selector {
                 udata: token.0,
             };
 
-            changelist.push(kevent);
-            // changelist[nchanges as usize] = MaybeUninit::new(kevent);
+            changelist[nchanges as usize] = MaybeUninit::new(kevent);
             nchanges += 1;
         }
 
@@ -102,18 +127,16 @@ impl SysSelector for Selector {
                     udata: token.0,
                 };
 
-                changelist.push(kevent);
-                // changelist[nchanges as usize] = MaybeUninit::new(kevent);
+                changelist[nchanges as usize] = MaybeUninit::new(kevent);
                 nchanges += 1;
             }
-            println!("Changelist: {:?}", changelist);
         }
 
         // Now we can call the `kevent` syscall
         let ret = unsafe {
             ffi::kevent(
                 self.kq.as_raw_fd(),
-                changelist.as_ptr(),
+                changelist.as_ptr().cast(),
                 nchanges,
                 std::ptr::null_mut(),
                 0,
@@ -121,21 +144,30 @@ impl SysSelector for Selector {
             )
         };
 
+        #[cfg(feature = "trace")]
+        crate::trace::syscall("kevent", ret as i64, |call| {
+            call.op = Some("EV_ADD");
+            call.fd = Some(fd);
+            call.token = Some(token);
+            call.interests = Some(interests);
+        });
+
         if ret < 0 {
             return Err(std::io::Error::last_os_error());
         }
         Ok(())
     }
 
-    fn reregister(
-        &self,
-        fd: std::os::unix::prelude::RawFd,
-        token: Token,
-        interests: Interest,
-    ) -> io::Result<()> {
-        // I don't believe there are different filters or flags that need
-        // to be used when reregistering an event.
-        self.register(fd, token, interests)
+    /// The filters of interests that are dropped are deleted, as `EV_ADD` only ever adds
+    /// or updates one, which `apply` takes care of.
+    fn reregister(&self, fd: RawFd, token: Token, interests: Interest) -> io::Result<()> {
+        let change = Change::Reregister {
+            fd,
+            token,
+            interests,
+        };
+
+        self.apply(&[change]).remove(0)
     }
 
     fn deregister(&self, fd: RawFd) -> io::Result<()> {
@@ -174,17 +206,171 @@ impl SysSelector for Selector {
             )
         };
 
+        #[cfg(feature = "trace")]
+        crate::trace::syscall("kevent", ret as i64, |call| {
+            call.op = Some("EV_DELETE");
+            call.fd = Some(fd);
+        });
+
         if ret < 0 {
             return Err(std::io::Error::last_os_error());
         }
         Ok(())
     }
 
+    /// Every change is submitted in a single `kevent` call. `EV_RECEIPT` makes the kernel
+    /// report the outcome of each `kevent` in the eventlist, in changelist order, rather
+    /// than stopping at the first error.
+    ///
+    /// A reregistration deletes the filters of interests it drops, as `EV_ADD` only ever
+    /// adds or updates one.
+    fn apply(&self, changes: &[Change]) -> Vec<io::Result<()>> {
+        let add = match self.trigger {
+            Trigger::Edge => flags::EV_CLEAR | flags::EV_RECEIPT | flags::EV_ADD,
+            Trigger::Level => flags::EV_RECEIPT | flags::EV_ADD,
+        };
+        let delete = flags::EV_DELETE | flags::EV_RECEIPT;
+
+        let kevent = |fd: RawFd, filter, flags, token: Token| OsEvent {
+            ident: fd as usize,
+            filter,
+            flags,
+            fflags: 0,
+            data: 0,
+            udata: token.0,
+        };
+
+        let mut changelist: Vec<OsEvent> = Vec::with_capacity(changes.len() * 2);
+        // index into `changes` of each kevent in the changelist, and whether it deletes a
+        // filter that might not have been added, so that `ENOENT` is no error
+        let mut owners: Vec<(usize, bool)> = Vec::with_capacity(changes.len() * 2);
+
+        for (i, change) in changes.iter().enumerate() {
+            match *change {
+                Change::Register {
+                    fd,
+                    token,
+                    interests,
+                } => {
+                    if interests.is_readable() {
+                        changelist.push(kevent(fd, filters::EVFILT_READ, add, token));
+                        owners.push((i, false));
+                    }
+
+                    if interests.is_writable() {
+                        changelist.push(kevent(fd, filters::EVFILT_WRITE, add, token));
+                        owners.push((i, false));
+                    }
+                }
+                Change::Reregister {
+                    fd,
+                    token,
+                    interests,
+                } => {
+                    for (filter, wanted) in [
+                        (filters::EVFILT_READ, interests.is_readable()),
+                        (filters::EVFILT_WRITE, interests.is_writable()),
+                    ] {
+                        let flags = if wanted { add } else { delete };
+                        changelist.push(kevent(fd, filter, flags, token));
+                        owners.push((i, !wanted));
+                    }
+                }
+                Change::Deregister { fd } => {
+                    changelist.push(kevent(fd, filters::EVFILT_READ, delete, Token(0)));
+                    changelist.push(kevent(fd, filters::EVFILT_WRITE, delete, Token(0)));
+                    owners.extend([(i, false), (i, false)]);
+                }
+            }
+        }
+
+        let mut receipts: Vec<OsEvent> = Vec::with_capacity(changelist.len());
+
+        let ret = unsafe {
+            ffi::kevent(
+                self.kq.as_raw_fd(),
+                changelist.as_ptr(),
+                changelist.len() as i32,
+                receipts.as_mut_ptr(),
+                receipts.capacity() as i32,
+                std::ptr::null(),
+            )
+        };
+
+        #[cfg(feature = "trace")]
+        crate::trace::syscall("kevent", ret as i64, |call| {
+            call.op = Some("EV_RECEIPT");
+            call.fd = Some(self.kq.as_raw_fd());
+        });
+
+        if ret < 0 {
+            let errno = io::Error::last_os_error().raw_os_error().unwrap_or(0);
+            return changes
+                .iter()
+                .map(|_| Err(io::Error::from_raw_os_error(errno)))
+                .collect();
+        }
+
+        unsafe { receipts.set_len(ret as usize) };
+
+        // errno of each kevent, 0 on success
+        let mut errnos = vec![Vec::new(); changes.len()];
+
+        for (receipt, &(owner, optional)) in receipts.iter().zip(&owners) {
+            let errno = match receipt.flags & flags::EV_ERROR {
+                0 => 0,
+                // the dropped interest was not registered in the first place
+                _ if optional && receipt.data as i32 == libc::ENOENT => 0,
+                _ => receipt.data as i32,
+            };
+            errnos[owner].push(errno);
+        }
+
+        changes
+            .iter()
+            .zip(errnos)
+            .map(|(change, errnos)| {
+                let errno = match change {
+                    // deleting the filter that was never added fails with `ENOENT`, which
+                    // only matters if neither filter was registered
+                    Change::Deregister { .. } if errnos.iter().all(|&e| e == libc::ENOENT) => {
+                        Some(libc::ENOENT)
+                    }
+                    Change::Deregister { .. } => errnos
+                        .iter()
+                        .copied()
+                        .find(|&e| e != 0 && e != libc::ENOENT),
+                    _ => errnos.iter().copied().find(|&e| e != 0),
+                };
+
+                match errno {
+                    Some(errno) => Err(io::Error::from_raw_os_error(errno)),
+                    None => Ok(()),
+                }
+            })
+            .collect()
+    }
+
     fn poll(
         &self,
         events: &mut Self::OsEvents,
         timeout: Option<std::time::Duration>,
     ) -> io::Result<usize> {
+        events.clear();
+        let n = self.poll_uninit(events.spare_capacity_mut(), timeout)?;
+        unsafe { events.set_len(n) };
+
+        Ok(n)
+    }
+
+    fn poll_uninit(
+        &self,
+        events: &mut [MaybeUninit<OsEvent>],
+        timeout: Option<std::time::Duration>,
+    ) -> io::Result<usize> {
+        #[cfg(feature = "trace")]
+        let requested = timeout;
+
         let timeout: Option<timespec> = timeout.map(Into::into);
 
         let timeout = timeout
@@ -192,25 +378,27 @@ impl SysSelector for Selector {
             .map(|s| s as *const _)
             .unwrap_or(std::ptr::null());
 
-        events.clear();
-
         let ret = unsafe {
             ffi::kevent(
                 self.kq.as_raw_fd(),
                 std::ptr::null(),
                 0,
-                events.as_mut_ptr(),
-                events.capacity() as i32,
+                events.as_mut_ptr().cast(),
+                events.len() as i32,
                 timeout,
             )
         };
 
+        #[cfg(feature = "trace")]
+        crate::trace::syscall("kevent", ret as i64, |call| {
+            call.fd = Some(self.kq.as_raw_fd());
+            call.timeout = requested;
+        });
+
         if ret < 0 {
             return Err(std::io::Error::last_os_error());
         }
 
-        unsafe { events.set_len(ret as usize) };
-
         Ok(ret as usize)
     }
 }
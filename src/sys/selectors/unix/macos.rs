@@ -0,0 +1,230 @@
+//! Selector dispatching to one of the backends compiled in on macOS.
+//!
+//! kqueue is the only OS backend, the mock selector is compiled in with the `testing`
+//! feature.
+
+use std::io;
+use std::mem::MaybeUninit;
+use std::os::fd::RawFd;
+use std::time::Duration;
+
+use crate::interests::Interest;
+use crate::interfaces::{Change, SelectorOptions, SysSelector, Token};
+use crate::poll::Backend;
+use crate::signal::SigSet;
+use crate::sys::events::{OsEvent, OsEvents};
+
+pub enum Selector {
+    Kqueue(super::kqueue::Selector),
+
+    #[cfg(feature = "testing")]
+    Mock(crate::testing::MockSelector),
+
+    #[cfg(feature = "testing")]
+    Faulty(Box<crate::testing::FaultySelector<Selector>>),
+
+    #[cfg(feature = "record")]
+    Recording(Box<crate::record::RecordingSelector<Selector>>),
+
+    #[cfg(feature = "record")]
+    Replay(crate::record::ReplaySelector),
+
+    #[cfg(feature = "metrics")]
+    Metered(Box<crate::metrics::MeteredSelector<Selector>>),
+}
+
+/// Forward a method call to whichever backend is in use.
+macro_rules! dispatch {
+    ($self:ident.$method:ident($($arg:expr),*)) => {
+        match $self {
+            Selector::Kqueue(selector) => selector.$method($($arg),*),
+
+            #[cfg(feature = "testing")]
+            Selector::Mock(selector) => selector.$method($($arg),*),
+
+            #[cfg(feature = "testing")]
+            Selector::Faulty(selector) => selector.$method($($arg),*),
+
+            #[cfg(feature = "record")]
+            Selector::Recording(selector) => selector.$method($($arg),*),
+
+            #[cfg(feature = "record")]
+            Selector::Replay(selector) => selector.$method($($arg),*),
+
+            #[cfg(feature = "metrics")]
+            Selector::Metered(selector) => selector.$method($($arg),*),
+        }
+    };
+}
+
+impl Selector {
+    #[cfg(feature = "testing")]
+    pub(crate) fn mock(&self) -> Option<crate::testing::Mock> {
+        match self {
+            Selector::Mock(selector) => Some(selector.handle()),
+            Selector::Faulty(selector) => selector.inner().mock(),
+            #[cfg(feature = "record")]
+            Selector::Recording(selector) => selector.inner().mock(),
+            #[cfg(feature = "metrics")]
+            Selector::Metered(selector) => selector.inner().mock(),
+            _ => None,
+        }
+    }
+
+    #[cfg(feature = "testing")]
+    pub(crate) fn faulty(self, faults: crate::testing::Faults) -> Self {
+        let selector = crate::testing::FaultySelector::new(self, faults);
+        Selector::Faulty(Box::new(selector))
+    }
+
+    #[cfg(feature = "record")]
+    pub(crate) fn recording(self, recorder: crate::record::Recorder) -> Self {
+        let selector = crate::record::RecordingSelector::new(self, recorder);
+        Selector::Recording(Box::new(selector))
+    }
+
+    #[cfg(feature = "metrics")]
+    pub(crate) fn metered(self, metrics: crate::metrics::Metrics) -> Self {
+        let selector = crate::metrics::MeteredSelector::new(self, metrics);
+        Selector::Metered(Box::new(selector))
+    }
+
+    #[cfg(feature = "testing")]
+    pub(crate) fn mocked(options: SelectorOptions) -> io::Result<Self> {
+        crate::testing::MockSelector::with_options(options).map(Selector::Mock)
+    }
+
+    #[cfg(feature = "record")]
+    pub(crate) fn replay(session: crate::record::Session) -> Self {
+        Selector::Replay(crate::record::ReplaySelector::new(session))
+    }
+
+    /// Create a fresh selector with the same backend and wrappers, e.g. in a child
+    /// process, which shares the kernel side queue of this one with its parent.
+    ///
+    /// A replayed session cannot be restarted, so it fails with `Unsupported`.
+    pub(crate) fn reopen(&self, options: SelectorOptions) -> io::Result<Self> {
+        let options = SelectorOptions {
+            backend: Some(self.backend()),
+            ..options
+        };
+
+        match self {
+            #[cfg(feature = "testing")]
+            Selector::Faulty(selector) => {
+                let inner = selector.inner().reopen(options)?;
+                Ok(inner.faulty(selector.faults().clone()))
+            }
+
+            #[cfg(feature = "record")]
+            Selector::Recording(selector) => {
+                let inner = selector.inner().reopen(options)?;
+                Ok(inner.recording(selector.recorder().clone()))
+            }
+
+            #[cfg(feature = "metrics")]
+            Selector::Metered(selector) => {
+                let inner = selector.inner().reopen(options)?;
+                Ok(inner.metered(selector.metrics().clone()))
+            }
+
+            #[cfg(feature = "record")]
+            Selector::Replay(_) => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "a replayed session cannot be reopened",
+            )),
+
+            #[cfg(feature = "testing")]
+            Selector::Mock(_) => Self::mocked(options),
+
+            _ => Self::with_options(options),
+        }
+    }
+}
+
+impl SysSelector for Selector {
+    type OsEvent = OsEvent;
+    type OsEvents = OsEvents;
+
+    fn with_options(options: SelectorOptions) -> io::Result<Self> {
+        // test-only selectors, which are not backed by the OS
+        #[cfg(feature = "testing")]
+        if options.backend == Some(Backend::Mock) {
+            return Err(not_selectable(Backend::Mock, "mock"));
+        }
+
+        #[cfg(feature = "record")]
+        if options.backend == Some(Backend::Replay) {
+            return Err(not_selectable(Backend::Replay, "replay"));
+        }
+
+        super::kqueue::Selector::with_options(options).map(Selector::Kqueue)
+    }
+
+    fn backend(&self) -> Backend {
+        dispatch!(self.backend())
+    }
+
+    fn raw_fd(&self) -> Option<RawFd> {
+        dispatch!(self.raw_fd())
+    }
+
+    fn register(&self, fd: RawFd, token: Token, interests: Interest) -> io::Result<()> {
+        dispatch!(self.register(fd, token, interests))
+    }
+
+    fn reregister(&self, fd: RawFd, token: Token, interests: Interest) -> io::Result<()> {
+        dispatch!(self.reregister(fd, token, interests))
+    }
+
+    fn rearm(&self, fd: RawFd, interests: Interest) -> io::Result<()> {
+        dispatch!(self.rearm(fd, interests))
+    }
+
+    fn poll(&self, events: &mut Self::OsEvents, timeout: Option<Duration>) -> io::Result<usize> {
+        dispatch!(self.poll(events, timeout))
+    }
+
+    fn poll_uninit(
+        &self,
+        events: &mut [MaybeUninit<OsEvent>],
+        timeout: Option<Duration>,
+    ) -> io::Result<usize> {
+        dispatch!(self.poll_uninit(events, timeout))
+    }
+
+    fn poll_with_sigmask(
+        &self,
+        events: &mut Self::OsEvents,
+        timeout: Option<Duration>,
+        sigmask: &SigSet,
+    ) -> io::Result<usize> {
+        dispatch!(self.poll_with_sigmask(events, timeout, sigmask))
+    }
+
+    fn poll_uninit_with_sigmask(
+        &self,
+        events: &mut [MaybeUninit<OsEvent>],
+        timeout: Option<Duration>,
+        sigmask: &SigSet,
+    ) -> io::Result<usize> {
+        dispatch!(self.poll_uninit_with_sigmask(events, timeout, sigmask))
+    }
+
+    fn deregister(&self, fd: RawFd) -> io::Result<()> {
+        dispatch!(self.deregister(fd))
+    }
+
+    fn apply(&self, changes: &[Change]) -> Vec<io::Result<()>> {
+        dispatch!(self.apply(changes))
+    }
+}
+
+/// Error for a backend that can only be selected via its own `PollBuilder` method.
+#[cfg(any(feature = "testing", feature = "record"))]
+fn not_selectable(backend: Backend, method: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("the {backend} backend is selected via PollBuilder::{method}"),
+    )
+}
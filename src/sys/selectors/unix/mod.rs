@@ -1,15 +1,30 @@
 //! This module contains the platform specific code for Unix systems.
+//!
+//! More than one backend can be compiled in. The `Selector` exported here then dispatches
+//! to the backend chosen when it was created.
 
 #[cfg(target_os = "linux")]
 mod epoll;
 
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+mod io_uring;
+
+#[cfg(target_os = "linux")]
+mod poll;
+
+#[cfg(target_os = "linux")]
+mod linux;
+
 #[cfg(target_os = "linux")]
 #[allow(unused_imports, dead_code)]
-pub use epoll::*;
+pub use linux::*;
 
 #[cfg(target_os = "macos")]
 mod kqueue;
 
+#[cfg(target_os = "macos")]
+mod macos;
+
 #[cfg(target_os = "macos")]
 #[allow(unused_imports, dead_code)]
-pub use kqueue::*;
+pub use macos::*;
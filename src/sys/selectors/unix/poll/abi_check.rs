@@ -0,0 +1,67 @@
+//! Checks `ffi::pollfd` against `libc::pollfd`, and runs poll(2) through both
+//! declarations.
+
+use super::ffi::{nfds_t, pollfd};
+
+assert_same_layout!(pollfd => libc::pollfd {
+    fd => fd,
+    events => events,
+    revents => revents,
+});
+
+const _: () = assert!(std::mem::size_of::<nfds_t>() == std::mem::size_of::<libc::nfds_t>());
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::os::fd::AsRawFd;
+    use std::os::unix::net::UnixStream;
+
+    use super::super::ffi;
+    use super::*;
+    use crate::sys::constants::poll::events;
+
+    #[test]
+    fn poll_through_both_declarations() {
+        let (a, mut b) = UnixStream::pair().unwrap();
+        let (c, _d) = UnixStream::pair().unwrap();
+        b.write_all(b"hello").unwrap();
+
+        let mut ours = [
+            pollfd {
+                fd: a.as_raw_fd(),
+                events: events::POLLIN,
+                revents: 0,
+            },
+            pollfd {
+                fd: c.as_raw_fd(),
+                events: events::POLLIN,
+                revents: 0,
+            },
+        ];
+
+        let mut theirs = ours.map(|fd| libc::pollfd {
+            fd: fd.fd,
+            events: fd.events,
+            revents: 0,
+        });
+
+        unsafe {
+            assert_eq!(ffi::poll(ours.as_mut_ptr(), 2, 0), 1);
+            assert_eq!(libc::poll(theirs.as_mut_ptr(), 2, 0), 1);
+
+            // each declaration also works on the other's array
+            let ret = libc::poll(ours.as_mut_ptr().cast::<libc::pollfd>(), 2, 0);
+            assert_eq!(ret, 1);
+            let ret = ffi::poll(theirs.as_mut_ptr().cast::<pollfd>(), 2, 0);
+            assert_eq!(ret, 1);
+        }
+
+        for (ours, theirs) in ours.iter().zip(&theirs) {
+            assert_eq!(ours.fd, theirs.fd);
+            assert_eq!(ours.revents, theirs.revents);
+        }
+        assert_eq!(ours[0].revents, events::POLLIN);
+        assert_eq!(ours[1].revents, 0);
+    }
+}
@@ -0,0 +1,81 @@
+//! This module contains the code related to the poll(2) syscall.
+//!
+//! Unlike epoll and kqueue, there is no kernel side event queue. The full set of file
+//! descriptors is passed in on every call, and the kernel writes the readiness of each
+//! one back into the same array.
+
+#![allow(dead_code, non_camel_case_types)]
+
+/// Entry in the array passed to `poll`.
+///
+/// ```c
+/// struct pollfd {
+///     int   fd;         /* file descriptor */
+///     short events;     /* requested events */
+///     short revents;    /* returned events */
+/// };
+/// ```
+#[derive(Clone, Copy, Debug)]
+#[repr(C)]
+pub(super) struct pollfd {
+    /// A negative value makes the kernel ignore the entry.
+    pub fd: i32,
+    /// Bit mask of events we are interested in.
+    pub events: i16,
+    /// Filled in by the kernel with the events that actually occurred.
+    pub revents: i16,
+}
+
+/// nfds_t = unsigned long
+pub(super) type nfds_t = std::ffi::c_ulong;
+
+#[cfg(not(feature = "libc-ffi"))]
+#[link(name = "c")] // link to C standard library / libc
+extern "C" {
+    /// wait for some event on a file descriptor
+    ///
+    /// https://man7.org/linux/man-pages/man2/poll.2.html
+    ///
+    /// #include <poll.h>
+    ///
+    /// int poll(struct pollfd *fds, nfds_t nfds, int timeout);
+    ///
+    /// On success, returns the number of elements in fds whose revents fields have been
+    /// set to a nonzero value. A return value of zero indicates the call timed out.
+    /// On error, -1 is returned, and errno is set to indicate the error.
+    pub(super) fn poll(fds: *mut pollfd, nfds: nfds_t, timeout: i32) -> i32;
+
+    /// poll, with a nanosecond timeout and `sigmask` as the thread's signal mask while
+    /// blocked
+    ///
+    /// https://man7.org/linux/man-pages/man2/ppoll.2.html
+    ///
+    /// int ppoll(struct pollfd *fds, nfds_t nfds,
+    ///           const struct timespec *_Nullable tmo_p,
+    ///           const sigset_t *_Nullable sigmask);
+    ///
+    /// A null `tmo_p` blocks indefinitely.
+    pub(super) fn ppoll(
+        fds: *mut pollfd,
+        nfds: nfds_t,
+        timeout: *const libc::timespec,
+        sigmask: *const libc::sigset_t,
+    ) -> i32;
+}
+
+/// poll(2) via the declaration of the `libc` crate, enabled by the `libc-ffi` feature.
+#[cfg(feature = "libc-ffi")]
+pub(super) unsafe fn poll(fds: *mut pollfd, nfds: nfds_t, timeout: i32) -> i32 {
+    libc::poll(fds.cast(), nfds, timeout)
+}
+
+/// ppoll(2) via the declaration of the `libc` crate, enabled by the `libc-ffi` feature.
+#[cfg(feature = "libc-ffi")]
+pub(super) unsafe fn ppoll(
+    fds: *mut pollfd,
+    nfds: nfds_t,
+    timeout: *const libc::timespec,
+    sigmask: *const libc::sigset_t,
+) -> i32 {
+    libc::ppoll(fds.cast(), nfds, timeout, sigmask)
+}
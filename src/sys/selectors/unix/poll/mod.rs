@@ -0,0 +1,12 @@
+//! poll(2) implementation for Unix systems.
+//!
+//! Exports following types:
+//! - `Selector`
+#![allow(unused, dead_code)]
+mod ffi;
+mod selector;
+
+#[cfg(feature = "abi-check")]
+mod abi_check;
+
+pub use selector::Selector;
@@ -0,0 +1,365 @@
+//! Interface to the poll(2) syscall.
+//!
+//! There is no kernel side interest list, so the selector keeps its own table of file
+//! descriptors and their interests, and hands the whole table to the kernel on every call
+//! to `poll`.
+//!
+//! poll(2) is level-triggered only. Edge-triggered behaviour is emulated by disarming
+//! the interests of a file descriptor once they have been reported. They are armed again
+//! by `rearm` or a reregistration, or once a poll finds the source no longer ready for
+//! them, so that callers draining a source until `WouldBlock`, as epoll expects, are
+//! notified of the next edge. Readiness that returns before that poll is mistaken for
+//! the edge already reported, which `rearm` after `WouldBlock` rules out. When built for
+//! level-triggered mode, nothing is ever disarmed.
+
+use std::collections::HashMap;
+use std::io;
+use std::os::fd::RawFd;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::interests::{Interest, Trigger};
+use crate::interfaces::{SelectorOptions, SysSelector, Token};
+use crate::poll::Backend;
+use crate::signal::SigSet;
+
+use crate::sys::constants::poll::events;
+use crate::sys::events::{OsEvent, OsEvents};
+
+// poll syscall
+use super::ffi::{self, pollfd};
+
+/// Bits reporting on the read direction of a file descriptor.
+const READ_EVENTS: i16 = events::POLLIN | events::POLLPRI | events::POLLRDHUP;
+
+/// Conditions that are reported regardless of the requested events.
+const ERROR_EVENTS: i16 = events::POLLERR | events::POLLHUP | events::POLLNVAL;
+
+struct Entry {
+    token: Token,
+    /// Events requested via `register` or `reregister`.
+    interests: i16,
+    /// Subset of `interests` the kernel is currently asked about.
+    armed: i16,
+}
+
+#[derive(Default)]
+struct State {
+    entries: HashMap<RawFd, Entry>,
+    /// Rotates the starting point of each poll, so that file descriptors near the start
+    /// of the table cannot starve the rest when `events` fills up.
+    cursor: usize,
+}
+
+/// Rather than a file descriptor, the selector holds the interest list itself
+pub struct Selector {
+    state: Mutex<State>,
+    trigger: Trigger,
+}
+
+impl SysSelector for Selector {
+    type OsEvent = OsEvent;
+    type OsEvents = Vec<Self::OsEvent>;
+
+    /// There is no file descriptor, so `cloexec` does not apply.
+    fn with_options(options: SelectorOptions) -> io::Result<Self> {
+        Ok(Selector {
+            state: Mutex::new(State::default()),
+            trigger: options.trigger,
+        })
+    }
+
+    fn backend(&self) -> Backend {
+        Backend::Poll
+    }
+
+    fn register(&self, fd: RawFd, token: Token, interests: Interest) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+
+        // mirror epoll_ctl(EPOLL_CTL_ADD) on a file descriptor that is already registered
+        if state.entries.contains_key(&fd) {
+            return Err(io::Error::from_raw_os_error(libc::EEXIST));
+        }
+
+        let interests = interest_to_poll(interests);
+        state.entries.insert(
+            fd,
+            Entry {
+                token,
+                interests,
+                armed: interests,
+            },
+        );
+
+        Ok(())
+    }
+
+    fn reregister(&self, fd: RawFd, token: Token, interests: Interest) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+
+        let entry = state
+            .entries
+            .get_mut(&fd)
+            .ok_or_else(|| io::Error::from_raw_os_error(libc::ENOENT))?;
+
+        entry.token = token;
+        entry.interests = interest_to_poll(interests);
+        entry.armed = entry.interests;
+
+        Ok(())
+    }
+
+    fn rearm(&self, fd: RawFd, interests: Interest) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+
+        let entry = state
+            .entries
+            .get_mut(&fd)
+            .ok_or_else(|| io::Error::from_raw_os_error(libc::ENOENT))?;
+
+        entry.armed |= interest_to_poll(interests) & entry.interests;
+
+        Ok(())
+    }
+
+    fn deregister(&self, fd: RawFd) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+
+        match state.entries.remove(&fd) {
+            Some(_) => Ok(()),
+            None => Err(io::Error::from_raw_os_error(libc::ENOENT)),
+        }
+    }
+
+    fn poll(&self, events: &mut Self::OsEvents, timeout: Option<Duration>) -> io::Result<usize> {
+        self.select(events, timeout, None)
+    }
+
+    fn poll_with_sigmask(
+        &self,
+        events: &mut Self::OsEvents,
+        timeout: Option<Duration>,
+        sigmask: &SigSet,
+    ) -> io::Result<usize> {
+        self.select(events, timeout, Some(sigmask))
+    }
+}
+
+impl Selector {
+    fn select(
+        &self,
+        events: &mut OsEvents,
+        timeout: Option<Duration>,
+        sigmask: Option<&SigSet>,
+    ) -> io::Result<usize> {
+        #[cfg(feature = "trace")]
+        let requested = timeout;
+
+        events.clear();
+
+        // same error epoll_wait returns for a maxevents of zero
+        if events.capacity() == 0 {
+            return Err(io::Error::from_raw_os_error(libc::EINVAL));
+        }
+
+        self.rearm_drained()?;
+
+        // Build the array under the lock, but do not hold it while blocked.
+        let mut fds: Vec<pollfd> = {
+            let state = self.state.lock().unwrap();
+
+            state
+                .entries
+                .iter()
+                .filter(|(_, entry)| entry.armed != 0)
+                .map(|(fd, entry)| pollfd {
+                    fd: *fd,
+                    events: entry.armed,
+                    revents: 0,
+                })
+                .collect()
+        };
+
+        let (_name, ret) = wait(&mut fds, timeout, sigmask);
+
+        #[cfg(feature = "trace")]
+        crate::trace::syscall(_name, ret as i64, |call| call.timeout = requested);
+
+        if ret < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let mut state = self.state.lock().unwrap();
+        let start = state.cursor;
+        state.cursor = state.cursor.wrapping_add(1);
+
+        for i in 0..fds.len() {
+            if events.len() == events.capacity() {
+                // remaining file descriptors stay armed and are reported next time
+                break;
+            }
+
+            let pollfd = fds[(start + i) % fds.len()];
+
+            if pollfd.revents == 0 {
+                continue;
+            }
+
+            // source might have been deregistered while we were blocked
+            let Some(entry) = state.entries.get_mut(&pollfd.fd) else {
+                continue;
+            };
+
+            events.push(OsEvent {
+                events: poll_to_epoll(pollfd.revents),
+                epoll_data: entry.token.0,
+            });
+
+            if self.trigger == Trigger::Edge {
+                entry.armed &= !disarmed_by(pollfd.revents);
+            }
+        }
+
+        Ok(events.len())
+    }
+
+    /// Arm the disarmed interests of every source no longer ready for them, checked
+    /// without blocking.
+    fn rearm_drained(&self) -> io::Result<()> {
+        let mut fds: Vec<pollfd> = {
+            let state = self.state.lock().unwrap();
+
+            state
+                .entries
+                .iter()
+                .filter(|(_, entry)| entry.interests & !entry.armed != 0)
+                .map(|(fd, entry)| pollfd {
+                    fd: *fd,
+                    events: entry.interests & !entry.armed,
+                    revents: 0,
+                })
+                .collect()
+        };
+
+        if fds.is_empty() {
+            return Ok(());
+        }
+
+        let (_name, ret) = wait(&mut fds, Some(Duration::ZERO), None);
+
+        #[cfg(feature = "trace")]
+        crate::trace::syscall(_name, ret as i64, |call| {
+            call.timeout = Some(Duration::ZERO)
+        });
+
+        if ret < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let mut state = self.state.lock().unwrap();
+
+        for pollfd in fds {
+            // deregistered in the meantime, or still failing
+            let Some(entry) = state.entries.get_mut(&pollfd.fd) else {
+                continue;
+            };
+
+            if pollfd.revents & ERROR_EVENTS != 0 {
+                continue;
+            }
+
+            if pollfd.revents & READ_EVENTS == 0 {
+                entry.armed |= entry.interests & READ_EVENTS;
+            }
+
+            if pollfd.revents & events::POLLOUT == 0 {
+                entry.armed |= entry.interests & events::POLLOUT;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Wait via `poll`, or via `ppoll` when given a signal mask, returning the name of the
+/// syscall made and its result.
+fn wait(
+    fds: &mut [pollfd],
+    timeout: Option<Duration>,
+    sigmask: Option<&SigSet>,
+) -> (&'static str, i32) {
+    let nfds = fds.len() as ffi::nfds_t;
+
+    let Some(sigmask) = sigmask else {
+        // A timeout of -1 means block indefinitely
+        // WARNING: below can truncate on sub-millisecond timeouts
+        let timeout = timeout
+            .map(|duration| duration.as_millis() as i32)
+            .unwrap_or(-1);
+
+        let ret = unsafe { ffi::poll(fds.as_mut_ptr(), nfds, timeout) };
+        return ("poll", ret);
+    };
+
+    let ts = timeout.map(|duration| libc::timespec {
+        tv_sec: duration.as_secs() as libc::time_t,
+        tv_nsec: duration.subsec_nanos() as libc::c_long,
+    });
+
+    let ret = unsafe {
+        ffi::ppoll(
+            fds.as_mut_ptr(),
+            nfds,
+            ts.as_ref().map_or(std::ptr::null(), |ts| ts),
+            sigmask.as_ptr(),
+        )
+    };
+
+    ("ppoll", ret)
+}
+
+fn interest_to_poll(interests: Interest) -> i16 {
+    let mut events: i16 = 0;
+
+    if interests.is_readable() {
+        events |= events::POLLIN | events::POLLRDHUP;
+    }
+
+    if interests.is_writable() {
+        events |= events::POLLOUT;
+    }
+
+    events
+}
+
+/// Interests to disarm, given the events just reported.
+fn disarmed_by(revents: i16) -> i16 {
+    if revents & ERROR_EVENTS != 0 {
+        // reported on every call until the source is dealt with
+        return !0;
+    }
+
+    let mut disarm = 0;
+
+    if revents & READ_EVENTS != 0 {
+        disarm |= READ_EVENTS;
+    }
+
+    if revents & events::POLLOUT != 0 {
+        disarm |= events::POLLOUT;
+    }
+
+    disarm
+}
+
+/// The `POLL*` bits match the `EPOLL*` bits, apart from `POLLNVAL`, which has no epoll
+/// equivalent. It is reported as an error, much like `EBADF` from `epoll_ctl`.
+fn poll_to_epoll(revents: i16) -> i32 {
+    let mut events = (revents & !events::POLLNVAL) as u16 as i32;
+
+    if revents & events::POLLNVAL != 0 {
+        events |= (events::POLLERR | events::POLLHUP) as i32;
+    }
+
+    events
+}
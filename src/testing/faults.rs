@@ -0,0 +1,418 @@
+//! Selector wrapper failing chosen calls, to exercise error handling paths.
+//!
+//! A failed call never reaches the wrapped selector, just as a failed syscall leaves the
+//! kernel's interest list untouched.
+
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::os::fd::RawFd;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use crate::interests::Interest;
+use crate::interfaces::{Change, SelectorOptions, SysSelector, Token};
+use crate::poll::Backend;
+use crate::signal::SigSet;
+
+/// Selector operation a fault can be injected into.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Op {
+    Register,
+    Reregister,
+    Deregister,
+    Poll,
+}
+
+impl Op {
+    fn of(change: &Change) -> Op {
+        match change {
+            Change::Register { .. } => Op::Register,
+            Change::Reregister { .. } => Op::Reregister,
+            Change::Deregister { .. } => Op::Deregister,
+        }
+    }
+}
+
+/// Error returned by a failed operation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Fault {
+    /// `EEXIST`, the file descriptor is already registered.
+    AlreadyExists,
+    /// `ENOENT`, the file descriptor is not registered.
+    NotFound,
+    /// `EBADF`, the file descriptor is not open.
+    BadFd,
+    /// `ENOMEM`, the kernel ran out of memory for the interest list.
+    NoMemory,
+    /// `EINTR`, a signal arrived while blocked.
+    Interrupted,
+    /// Any other errno value.
+    Os(i32),
+}
+
+impl Fault {
+    pub fn errno(&self) -> i32 {
+        match self {
+            Fault::AlreadyExists => libc::EEXIST,
+            Fault::NotFound => libc::ENOENT,
+            Fault::BadFd => libc::EBADF,
+            Fault::NoMemory => libc::ENOMEM,
+            Fault::Interrupted => libc::EINTR,
+            Fault::Os(errno) => *errno,
+        }
+    }
+}
+
+impl From<Fault> for io::Error {
+    fn from(fault: Fault) -> Self {
+        io::Error::from_raw_os_error(fault.errno())
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// Number of calls made so far, per operation.
+    calls: HashMap<Op, usize>,
+    /// Faults for the call with the given number, counting from 1.
+    scheduled: HashMap<(Op, usize), Fault>,
+    /// Faults for the next calls, whatever their number.
+    next: HashMap<Op, VecDeque<Fault>>,
+    /// Probability of each call failing.
+    random: HashMap<Op, (f64, Fault)>,
+    rng: SplitMix64,
+    /// Every fault injected so far.
+    injected: Vec<(Op, Fault)>,
+}
+
+impl State {
+    fn fault(&mut self, op: Op) -> Option<Fault> {
+        let n = self.calls.entry(op).or_default();
+        *n += 1;
+        let n = *n;
+
+        let fault = self
+            .next
+            .get_mut(&op)
+            .and_then(VecDeque::pop_front)
+            .or_else(|| self.scheduled.remove(&(op, n)))
+            .or_else(|| {
+                let (probability, fault) = *self.random.get(&op)?;
+                (self.rng.next_f64() < probability).then_some(fault)
+            })?;
+
+        self.injected.push((op, fault));
+        Some(fault)
+    }
+}
+
+/// Configures which calls fail. Clones share the same configuration.
+///
+/// ```
+/// use mini_mio::interfaces::Events;
+/// use mini_mio::poll::Poll;
+/// use mini_mio::testing::{Fault, Faults, Op};
+///
+/// let faults = Faults::new();
+/// faults.fail_nth(Op::Poll, 2, Fault::Interrupted);
+///
+/// let mut poll = Poll::builder().faults(faults.clone()).build().unwrap();
+/// let mut events = Events::with_capacity(8);
+/// let timeout = Some(std::time::Duration::ZERO);
+///
+/// assert!(poll.poll(&mut events, timeout).is_ok());
+/// let err = poll.poll(&mut events, timeout).unwrap_err();
+/// assert_eq!(err.kind(), std::io::ErrorKind::Interrupted);
+/// assert!(poll.poll(&mut events, timeout).is_ok());
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Faults {
+    state: Arc<Mutex<State>>,
+}
+
+impl Faults {
+    /// No faults, until configured otherwise.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Seed used for `fail_randomly`, so that a failing run can be reproduced.
+    pub fn seeded(seed: u64) -> Self {
+        let faults = Self::default();
+        faults.lock().rng = SplitMix64(seed);
+        faults
+    }
+
+    /// Fail the next call of `op`. Repeated calls queue up one fault per call.
+    pub fn fail_next(&self, op: Op, fault: Fault) -> &Self {
+        self.lock().next.entry(op).or_default().push_back(fault);
+        self
+    }
+
+    /// Fail the `n`th call of `op`, counting from 1 since the selector was created.
+    pub fn fail_nth(&self, op: Op, n: usize, fault: Fault) -> &Self {
+        self.lock().scheduled.insert((op, n), fault);
+        self
+    }
+
+    /// Fail each call of `op` with the given probability, between 0 and 1.
+    pub fn fail_randomly(&self, op: Op, probability: f64, fault: Fault) -> &Self {
+        self.lock().random.insert(op, (probability, fault));
+        self
+    }
+
+    /// Stop injecting any faults not injected yet.
+    pub fn clear(&self) {
+        let mut state = self.lock();
+        state.scheduled.clear();
+        state.next.clear();
+        state.random.clear();
+    }
+
+    /// Every fault injected so far, in order.
+    pub fn injected(&self) -> Vec<(Op, Fault)> {
+        self.lock().injected.clone()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+}
+
+/// Wraps a selector, failing the calls configured via `Faults`.
+pub struct FaultySelector<S> {
+    inner: S,
+    faults: Faults,
+}
+
+impl<S: SysSelector> FaultySelector<S> {
+    pub fn new(inner: S, faults: Faults) -> Self {
+        Self { inner, faults }
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    pub(crate) fn faults(&self) -> &Faults {
+        &self.faults
+    }
+
+    fn check(&self, op: Op) -> io::Result<()> {
+        match self.faults.lock().fault(op) {
+            Some(fault) => Err(fault.into()),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<S: SysSelector> SysSelector for FaultySelector<S> {
+    type OsEvent = S::OsEvent;
+    type OsEvents = S::OsEvents;
+
+    fn with_options(options: SelectorOptions) -> io::Result<Self> {
+        Ok(Self::new(S::with_options(options)?, Faults::new()))
+    }
+
+    fn backend(&self) -> Backend {
+        self.inner.backend()
+    }
+
+    fn raw_fd(&self) -> Option<RawFd> {
+        self.inner.raw_fd()
+    }
+
+    fn register(&self, fd: RawFd, token: Token, interests: Interest) -> io::Result<()> {
+        self.check(Op::Register)?;
+        self.inner.register(fd, token, interests)
+    }
+
+    fn reregister(&self, fd: RawFd, token: Token, interests: Interest) -> io::Result<()> {
+        self.check(Op::Reregister)?;
+        self.inner.reregister(fd, token, interests)
+    }
+
+    fn rearm(&self, fd: RawFd, interests: Interest) -> io::Result<()> {
+        self.inner.rearm(fd, interests)
+    }
+
+    fn deregister(&self, fd: RawFd) -> io::Result<()> {
+        self.check(Op::Deregister)?;
+        self.inner.deregister(fd)
+    }
+
+    /// Checks each change on its own, submitting only those not failed to `inner`.
+    fn apply(&self, changes: &[Change]) -> Vec<io::Result<()>> {
+        let checked: Vec<_> = changes
+            .iter()
+            .map(|change| self.check(Op::of(change)))
+            .collect();
+
+        let passed: Vec<_> = changes
+            .iter()
+            .zip(&checked)
+            .filter(|(_, checked)| checked.is_ok())
+            .map(|(change, _)| *change)
+            .collect();
+
+        let mut results = self.inner.apply(&passed).into_iter();
+
+        checked
+            .into_iter()
+            .map(|checked| checked.and_then(|()| results.next().unwrap()))
+            .collect()
+    }
+
+    fn poll(&self, events: &mut Self::OsEvents, timeout: Option<Duration>) -> io::Result<usize> {
+        if let Err(e) = self.check(Op::Poll) {
+            // no events are returned alongside an error
+            events.as_mut().clear();
+            return Err(e);
+        }
+
+        self.inner.poll(events, timeout)
+    }
+
+    fn poll_with_sigmask(
+        &self,
+        events: &mut Self::OsEvents,
+        timeout: Option<Duration>,
+        sigmask: &SigSet,
+    ) -> io::Result<usize> {
+        if let Err(e) = self.check(Op::Poll) {
+            events.as_mut().clear();
+            return Err(e);
+        }
+
+        self.inner.poll_with_sigmask(events, timeout, sigmask)
+    }
+}
+
+/// Small, seedable generator, so that no dependency is needed for `fail_randomly`.
+///
+/// See: https://prng.di.unimi.it/splitmix64.c
+#[derive(Debug, Default)]
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniformly distributed in `[0, 1)`.
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::net::UnixStream;
+
+    use super::*;
+    use crate::interfaces::Events;
+    use crate::poll::Poll;
+
+    fn faulty_poll(faults: &Faults) -> Poll {
+        Poll::builder().faults(faults.clone()).build().unwrap()
+    }
+
+    #[test]
+    fn fail_next_skips_inner_selector() {
+        let faults = Faults::new();
+        let poll = faulty_poll(&faults);
+        let (a, _b) = UnixStream::pair().unwrap();
+
+        faults.fail_next(Op::Register, Fault::NoMemory);
+
+        let err = poll
+            .registry()
+            .register(&a, Token(0), Interest::READABLE)
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::OutOfMemory);
+
+        // the failed call never reached the kernel, so registering again succeeds
+        poll.registry()
+            .register(&a, Token(0), Interest::READABLE)
+            .unwrap();
+
+        assert_eq!(faults.injected(), [(Op::Register, Fault::NoMemory)]);
+    }
+
+    #[test]
+    fn batched_fault_skips_only_its_change() {
+        let faults = Faults::new();
+        let poll = faulty_poll(&faults);
+        let (a, _) = UnixStream::pair().unwrap();
+        let (b, _) = UnixStream::pair().unwrap();
+
+        faults.fail_nth(Op::Register, 2, Fault::NoMemory);
+
+        let err = poll
+            .registry()
+            .batch()
+            .register(&a, Token(0), Interest::READABLE)
+            .register(&b, Token(1), Interest::READABLE)
+            .apply()
+            .unwrap_err();
+        assert_eq!(err.errors.len(), 1);
+        assert_eq!(err.errors[0].0, 1);
+
+        assert!(poll.registry().registered(&a).is_some());
+        // the failed change never reached the kernel either
+        poll.registry()
+            .register(&b, Token(1), Interest::READABLE)
+            .unwrap();
+    }
+
+    #[test]
+    fn error_kinds() {
+        let faults = Faults::new();
+        let poll = faulty_poll(&faults);
+        let (a, _b) = UnixStream::pair().unwrap();
+
+        poll.registry()
+            .register(&a, Token(0), Interest::READABLE)
+            .unwrap();
+
+        faults
+            .fail_next(Op::Reregister, Fault::NotFound)
+            .fail_next(Op::Reregister, Fault::AlreadyExists)
+            .fail_next(Op::Deregister, Fault::BadFd);
+
+        let reregister = || {
+            poll.registry()
+                .reregister(&a, Token(0), Interest::WRITABLE)
+                .unwrap_err()
+        };
+        assert_eq!(reregister().kind(), io::ErrorKind::NotFound);
+        assert_eq!(reregister().kind(), io::ErrorKind::AlreadyExists);
+
+        let err = poll.registry().deregister(&a).unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::EBADF));
+    }
+
+    #[test]
+    fn seeded_faults_are_reproducible() {
+        let run = |seed| {
+            let faults = Faults::seeded(seed);
+            let mut poll = faulty_poll(&faults);
+            let mut events = Events::with_capacity(8);
+
+            faults.fail_randomly(Op::Poll, 0.5, Fault::Interrupted);
+
+            (0..64)
+                .map(|_| poll.poll(&mut events, Some(Duration::ZERO)).is_err())
+                .collect::<Vec<_>>()
+        };
+
+        let failures = run(7);
+        assert_eq!(failures, run(7));
+        assert!(failures.contains(&true));
+        assert!(failures.contains(&false));
+    }
+}
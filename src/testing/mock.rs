@@ -0,0 +1,423 @@
+//! Selector reporting scripted readiness, without making any syscalls.
+
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::os::fd::{AsRawFd, RawFd};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use crate::interests::Interest;
+use crate::interfaces::{SelectorOptions, SysSelector, Token};
+use crate::poll::Backend;
+use crate::signal::SigSet;
+use crate::sys::{OsEvent, OsEvents};
+
+/// Stand-in for a socket, to register with a mock selector.
+///
+/// The file descriptor is only used as a key, it does not need to be open.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FakeSource(pub RawFd);
+
+impl AsRawFd for FakeSource {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0
+    }
+}
+
+/// A call made to the mock selector, in the order they were made.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Call {
+    Register {
+        fd: RawFd,
+        token: Token,
+        interests: Interest,
+    },
+    Reregister {
+        fd: RawFd,
+        token: Token,
+        interests: Interest,
+    },
+    Rearm {
+        fd: RawFd,
+        interests: Interest,
+    },
+    Deregister {
+        fd: RawFd,
+    },
+    Poll {
+        timeout: Option<Duration>,
+    },
+}
+
+/// Readiness that can be injected for a token.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Kind {
+    Readable,
+    Writable,
+    ReadClosed,
+    WriteClosed,
+    Error,
+}
+
+#[derive(Default)]
+struct State {
+    registrations: HashMap<RawFd, (Token, Interest)>,
+    calls: Vec<Call>,
+    /// Events to report, one batch per call to `poll`.
+    batches: VecDeque<Vec<OsEvent>>,
+    polls: usize,
+}
+
+/// Handle used to script a mock selector and inspect the calls made to it.
+///
+/// Clones refer to the same selector. Obtained via `Poll::mock`.
+#[derive(Clone, Default)]
+pub struct Mock {
+    state: Arc<Mutex<State>>,
+}
+
+impl Mock {
+    /// Report `token` as readable.
+    pub fn readable(&self, token: Token) -> &Self {
+        self.inject(token, Kind::Readable)
+    }
+
+    /// Report `token` as writable.
+    pub fn writable(&self, token: Token) -> &Self {
+        self.inject(token, Kind::Writable)
+    }
+
+    /// Report the read direction of `token` as closed, e.g. the peer sent a FIN.
+    pub fn read_closed(&self, token: Token) -> &Self {
+        self.inject(token, Kind::ReadClosed)
+    }
+
+    /// Report the write direction of `token` as closed.
+    pub fn write_closed(&self, token: Token) -> &Self {
+        self.inject(token, Kind::WriteClosed)
+    }
+
+    /// Report an error on `token`.
+    pub fn error(&self, token: Token) -> &Self {
+        self.inject(token, Kind::Error)
+    }
+
+    /// End the current batch.
+    ///
+    /// Readiness injected so far is reported by the next call to `Poll::poll`, anything
+    /// injected afterwards by the call after that. Without a call to `step`, all
+    /// injected readiness is reported by a single call.
+    pub fn step(&self) -> &Self {
+        self.lock().batches.push_back(Vec::new());
+        self
+    }
+
+    /// Every call made to the selector so far.
+    pub fn calls(&self) -> Vec<Call> {
+        self.lock().calls.clone()
+    }
+
+    /// Return and forget the calls made to the selector so far.
+    pub fn take_calls(&self) -> Vec<Call> {
+        std::mem::take(&mut self.lock().calls)
+    }
+
+    /// Number of calls made to `Poll::poll` so far.
+    pub fn polls(&self) -> usize {
+        self.lock().polls
+    }
+
+    /// Token and interests `fd` is currently registered with.
+    pub fn registration(&self, fd: RawFd) -> Option<(Token, Interest)> {
+        self.lock().registrations.get(&fd).copied()
+    }
+
+    /// Number of batches that have not been reported yet.
+    pub fn pending(&self) -> usize {
+        self.lock()
+            .batches
+            .iter()
+            .filter(|batch| !batch.is_empty())
+            .count()
+    }
+
+    fn inject(&self, token: Token, kind: Kind) -> &Self {
+        let mut state = self.lock();
+
+        if state.batches.is_empty() {
+            state.batches.push_back(Vec::new());
+        }
+
+        push_event(state.batches.back_mut().unwrap(), token, kind);
+
+        drop(state);
+        self
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+}
+
+/// Selector that reports whatever readiness has been injected via its `Mock` handle.
+///
+/// `poll` never blocks. Trigger mode is not modelled: readiness is reported exactly as
+/// many times as it was injected.
+pub struct MockSelector {
+    mock: Mock,
+}
+
+impl MockSelector {
+    pub(crate) fn handle(&self) -> Mock {
+        self.mock.clone()
+    }
+}
+
+impl SysSelector for MockSelector {
+    type OsEvent = OsEvent;
+    type OsEvents = OsEvents;
+
+    fn with_options(_options: SelectorOptions) -> io::Result<Self> {
+        Ok(MockSelector {
+            mock: Mock::default(),
+        })
+    }
+
+    fn backend(&self) -> Backend {
+        Backend::Mock
+    }
+
+    fn register(&self, fd: RawFd, token: Token, interests: Interest) -> io::Result<()> {
+        let mut state = self.mock.lock();
+        state.calls.push(Call::Register {
+            fd,
+            token,
+            interests,
+        });
+
+        // mirror epoll_ctl(EPOLL_CTL_ADD) on a file descriptor that is already registered
+        if state.registrations.contains_key(&fd) {
+            return Err(io::Error::from_raw_os_error(libc::EEXIST));
+        }
+
+        state.registrations.insert(fd, (token, interests));
+        Ok(())
+    }
+
+    fn reregister(&self, fd: RawFd, token: Token, interests: Interest) -> io::Result<()> {
+        let mut state = self.mock.lock();
+        state.calls.push(Call::Reregister {
+            fd,
+            token,
+            interests,
+        });
+
+        match state.registrations.get_mut(&fd) {
+            Some(registration) => {
+                *registration = (token, interests);
+                Ok(())
+            }
+            None => Err(io::Error::from_raw_os_error(libc::ENOENT)),
+        }
+    }
+
+    fn rearm(&self, fd: RawFd, interests: Interest) -> io::Result<()> {
+        let mut state = self.mock.lock();
+        state.calls.push(Call::Rearm { fd, interests });
+
+        match state.registrations.contains_key(&fd) {
+            true => Ok(()),
+            false => Err(io::Error::from_raw_os_error(libc::ENOENT)),
+        }
+    }
+
+    fn deregister(&self, fd: RawFd) -> io::Result<()> {
+        let mut state = self.mock.lock();
+        state.calls.push(Call::Deregister { fd });
+
+        match state.registrations.remove(&fd) {
+            Some(_) => Ok(()),
+            None => Err(io::Error::from_raw_os_error(libc::ENOENT)),
+        }
+    }
+
+    /// Nothing blocks, so there is no wait for the mask to apply to.
+    fn poll_with_sigmask(
+        &self,
+        events: &mut Self::OsEvents,
+        timeout: Option<Duration>,
+        _sigmask: &SigSet,
+    ) -> io::Result<usize> {
+        self.poll(events, timeout)
+    }
+
+    fn poll(&self, events: &mut Self::OsEvents, timeout: Option<Duration>) -> io::Result<usize> {
+        let mut state = self.mock.lock();
+        state.calls.push(Call::Poll { timeout });
+        state.polls += 1;
+
+        events.clear();
+
+        // same error epoll_wait returns for a maxevents of zero
+        if events.capacity() == 0 {
+            return Err(io::Error::from_raw_os_error(libc::EINVAL));
+        }
+
+        let Some(mut batch) = state.batches.pop_front() else {
+            // behave as if the timeout expired
+            return Ok(0);
+        };
+
+        if batch.len() > events.capacity() {
+            // remaining events are reported by the next call
+            let rest = batch.split_off(events.capacity());
+            state.batches.push_front(rest);
+        }
+
+        events.extend(batch);
+
+        Ok(events.len())
+    }
+}
+
+/// epoll reports one event per file descriptor, with all of its readiness bits set.
+#[cfg(target_os = "linux")]
+fn push_event(batch: &mut Vec<OsEvent>, token: Token, kind: Kind) {
+    use crate::sys::constants::epoll::events::*;
+
+    let bits = match kind {
+        Kind::Readable => EPOLLIN,
+        Kind::Writable => EPOLLOUT,
+        Kind::ReadClosed => EPOLLIN | EPOLLRDHUP,
+        Kind::WriteClosed => EPOLLOUT | EPOLLERR,
+        Kind::Error => EPOLLERR,
+    };
+
+    match batch.iter_mut().find(|event| event.epoll_data == token.0) {
+        Some(event) => event.events |= bits,
+        None => batch.push(OsEvent {
+            events: bits,
+            epoll_data: token.0,
+        }),
+    }
+}
+
+/// kqueue reports one event per filter, with closed and error conditions set in `flags`.
+#[cfg(target_os = "macos")]
+fn push_event(batch: &mut Vec<OsEvent>, token: Token, kind: Kind) {
+    use crate::sys::constants::kqueue::{filters, flags};
+
+    let (filter, flags) = match kind {
+        Kind::Readable => (filters::EVFILT_READ, 0),
+        Kind::Writable => (filters::EVFILT_WRITE, 0),
+        Kind::ReadClosed => (filters::EVFILT_READ, flags::EV_EOF),
+        Kind::WriteClosed => (filters::EVFILT_WRITE, flags::EV_EOF),
+        Kind::Error => (filters::EVFILT_READ, flags::EV_ERROR),
+    };
+
+    match batch
+        .iter_mut()
+        .find(|event| event.udata == token.0 && event.filter == filter)
+    {
+        Some(event) => event.flags |= flags,
+        None => batch.push(OsEvent {
+            ident: 0,
+            filter,
+            flags,
+            fflags: 0,
+            data: 0,
+            udata: token.0,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interfaces::{Events, SysEvent};
+    use crate::poll::Poll;
+
+    fn mock_poll() -> (Poll, Mock) {
+        let poll = Poll::builder().mock().build().unwrap();
+        let mock = poll.mock().unwrap();
+        (poll, mock)
+    }
+
+    #[test]
+    fn records_calls() {
+        let (poll, mock) = mock_poll();
+        let registry = poll.registry();
+        let source = FakeSource(3);
+
+        registry
+            .register(&source, Token(1), Interest::READABLE)
+            .unwrap();
+        registry
+            .reregister(&source, Token(2), Interest::WRITABLE)
+            .unwrap();
+        registry.deregister(&source).unwrap();
+
+        assert_eq!(
+            mock.take_calls(),
+            vec![
+                Call::Register {
+                    fd: 3,
+                    token: Token(1),
+                    interests: Interest::READABLE
+                },
+                Call::Reregister {
+                    fd: 3,
+                    token: Token(2),
+                    interests: Interest::WRITABLE
+                },
+                Call::Deregister { fd: 3 },
+            ]
+        );
+        assert!(mock.calls().is_empty());
+        assert_eq!(mock.registration(3), None);
+
+        let err = registry.deregister(&source).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn merges_readiness_per_token() {
+        let (mut poll, mock) = mock_poll();
+        let mut events = Events::with_capacity(8);
+
+        mock.readable(Token(1)).writable(Token(1)).error(Token(2));
+
+        poll.poll(&mut events, None).unwrap();
+
+        let readable: Vec<_> = (&events)
+            .into_iter()
+            .filter(|event| event.token() == Token(1))
+            .collect();
+        assert!(readable.iter().any(|event| event.is_readable()));
+        assert!(readable.iter().any(|event| event.is_writable()));
+
+        let error = (&events)
+            .into_iter()
+            .find(|event| event.token() == Token(2))
+            .unwrap();
+        assert!(error.is_error());
+    }
+
+    #[test]
+    fn excess_events_carry_over() {
+        let (mut poll, mock) = mock_poll();
+        let mut events = Events::with_capacity(2);
+
+        for token in 0..3 {
+            mock.readable(Token(token));
+        }
+
+        poll.poll(&mut events, None).unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(mock.pending(), 1);
+
+        poll.poll(&mut events, None).unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(mock.pending(), 0);
+    }
+}
@@ -0,0 +1,42 @@
+//! Utilities for unit-testing code built on top of `Poll`.
+//!
+//! - `Mock`: a `Poll` built via `PollBuilder::mock` never makes a syscall. Instead, readiness
+//!   is injected through a `Mock` handle, and every call made to the selector is recorded.
+//! - `Faults`: makes chosen calls to any backend fail, see `PollBuilder::faults`.
+//!
+//! ```
+//! use mini_mio::interests::Interest;
+//! use mini_mio::interfaces::{Events, SysEvent, Token};
+//! use mini_mio::poll::Poll;
+//! use mini_mio::testing::{Call, FakeSource};
+//!
+//! let mut poll = Poll::builder().mock().build().unwrap();
+//! let mock = poll.mock().unwrap();
+//! let mut events = Events::with_capacity(8);
+//!
+//! let source = FakeSource(7);
+//! poll.registry().register(&source, Token(1), Interest::READABLE).unwrap();
+//!
+//! mock.readable(Token(1));
+//! mock.step();
+//! mock.read_closed(Token(1));
+//!
+//! poll.poll(&mut events, None).unwrap();
+//! assert!((&events).into_iter().all(|event| event.is_readable()));
+//!
+//! poll.poll(&mut events, None).unwrap();
+//! assert!((&events).into_iter().all(|event| event.is_read_closed()));
+//!
+//! // nothing left to report, so the next call returns straight away
+//! poll.poll(&mut events, None).unwrap();
+//! assert!(events.is_empty());
+//!
+//! assert_eq!(mock.polls(), 3);
+//! assert!(matches!(mock.calls()[0], Call::Register { token: Token(1), .. }));
+//! ```
+
+mod faults;
+mod mock;
+
+pub use faults::{Fault, Faults, FaultySelector, Op};
+pub use mock::{Call, FakeSource, Mock, MockSelector};
@@ -0,0 +1,219 @@
+//! Tracing of every syscall made by the selectors.
+//!
+//! Nothing is traced until a `Sink` is installed via `set_sink`. The sink is global, as
+//! syscalls are also made while creating a selector, before any `Poll` exists to hold it.
+//!
+//! ```
+//! use mini_mio::trace::{self, Syscall};
+//!
+//! trace::set_sink(|call: &Syscall| eprintln!("{call}"));
+//! // ... epoll_ctl EPOLL_CTL_ADD fd=5 token=1 interests=READABLE -> 0
+//! trace::take_sink();
+//! ```
+//!
+//! Registrations with the io_uring backend are queued rather than made via a syscall,
+//! and only show up as part of the next `io_uring_enter`.
+
+use std::fmt;
+use std::io;
+use std::os::fd::RawFd;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use crate::interests::Interest;
+use crate::interfaces::Token;
+
+/// A syscall that has just returned.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Syscall {
+    /// Name of the syscall, e.g. `epoll_ctl`.
+    pub name: &'static str,
+    /// Operation requested from a multiplexed syscall, e.g. `EPOLL_CTL_ADD`.
+    pub op: Option<&'static str>,
+    /// File descriptor the call operates on, or the event queue's own descriptor when
+    /// waiting for events.
+    pub fd: Option<RawFd>,
+    pub token: Option<Token>,
+    pub interests: Option<Interest>,
+    pub timeout: Option<Duration>,
+    pub ret: i64,
+    /// Set when `ret` is negative.
+    pub errno: Option<i32>,
+}
+
+impl fmt::Display for Syscall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+
+        if let Some(op) = self.op {
+            write!(f, " {op}")?;
+        }
+
+        if let Some(fd) = self.fd {
+            write!(f, " fd={fd}")?;
+        }
+
+        if let Some(token) = self.token {
+            write!(f, " token={}", token.0)?;
+        }
+
+        if let Some(interests) = self.interests {
+            // Debug separates flags with " | ", keep the line free of spaces
+            let interests = format!("{interests:?}").replace(" | ", "|");
+            write!(f, " interests={interests}")?;
+        }
+
+        if let Some(timeout) = self.timeout {
+            write!(f, " timeout={timeout:?}")?;
+        }
+
+        write!(f, " -> {}", self.ret)?;
+
+        if let Some(errno) = self.errno {
+            let err = io::Error::from_raw_os_error(errno);
+            write!(f, " errno={errno} ({err})")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Receives every traced syscall.
+///
+/// Called on the thread that made the syscall, while the selector might hold a lock, so
+/// implementations should return quickly and must not call back into the selector.
+pub trait Sink: Send + Sync {
+    fn syscall(&self, call: &Syscall);
+}
+
+impl<F> Sink for F
+where
+    F: Fn(&Syscall) + Send + Sync,
+{
+    fn syscall(&self, call: &Syscall) {
+        self(call)
+    }
+}
+
+/// Writes each syscall to stderr, one per line.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StderrSink;
+
+impl Sink for StderrSink {
+    fn syscall(&self, call: &Syscall) {
+        eprintln!("[mini-mio] {call}");
+    }
+}
+
+static SINK: RwLock<Option<Arc<dyn Sink>>> = RwLock::new(None);
+
+/// Install the sink every syscall is reported to, replacing any previous one.
+pub fn set_sink<S: Sink + 'static>(sink: S) {
+    *SINK.write().unwrap() = Some(Arc::new(sink));
+}
+
+/// Remove the installed sink, which stops tracing.
+pub fn take_sink() -> Option<Arc<dyn Sink>> {
+    SINK.write().unwrap().take()
+}
+
+/// Report a syscall that returned `ret`, filling in the details via `fill`.
+///
+/// Called straight after the syscall, before its caller inspects `errno`. The sink might
+/// make syscalls of its own, so `errno` is restored before returning.
+pub(crate) fn syscall(name: &'static str, ret: i64, fill: impl FnOnce(&mut Syscall)) {
+    let errno = io::Error::last_os_error().raw_os_error().unwrap_or(0);
+
+    let Some(sink) = SINK.read().unwrap().clone() else {
+        return;
+    };
+
+    let mut call = Syscall {
+        name,
+        op: None,
+        fd: None,
+        token: None,
+        interests: None,
+        timeout: None,
+        ret,
+        errno: (ret < 0).then_some(errno),
+    };
+
+    fill(&mut call);
+    sink.syscall(&call);
+
+    set_errno(errno);
+}
+
+#[cfg(target_os = "linux")]
+fn set_errno(errno: i32) {
+    unsafe { *libc::__errno_location() = errno };
+}
+
+#[cfg(target_os = "macos")]
+fn set_errno(errno: i32) {
+    unsafe { *libc::__error() = errno };
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::fd::AsRawFd;
+    use std::os::unix::net::UnixStream;
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::poll::{Backend, Poll};
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn traces_epoll_ctl() {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let (a, _b) = UnixStream::pair().unwrap();
+        let fd = a.as_raw_fd();
+
+        {
+            let calls = calls.clone();
+            set_sink(move |call: &Syscall| {
+                // other tests run concurrently, only keep calls for our source
+                if call.fd == Some(fd) {
+                    calls.lock().unwrap().push(call.clone());
+                }
+            });
+        }
+
+        let poll = Poll::builder().backend(Backend::Epoll).build().unwrap();
+        poll.registry()
+            .register(&a, Token(3), Interest::READABLE | Interest::WRITABLE)
+            .unwrap();
+
+        poll.registry().deregister(&a).unwrap();
+
+        let err = poll.registry().deregister(&a).unwrap_err();
+        // errno survived the call to the sink
+        assert_eq!(err.raw_os_error(), Some(libc::ENOENT));
+
+        take_sink();
+
+        let calls = calls.lock().unwrap();
+        assert_eq!(calls.len(), 3);
+
+        assert_eq!(calls[0].name, "epoll_ctl");
+        assert_eq!(calls[0].op, Some("EPOLL_CTL_ADD"));
+        assert_eq!(calls[0].token, Some(Token(3)));
+        assert_eq!(calls[0].ret, 0);
+        assert_eq!(calls[0].errno, None);
+        assert_eq!(
+            calls[0].to_string(),
+            format!("epoll_ctl EPOLL_CTL_ADD fd={fd} token=3 interests=READABLE|WRITABLE -> 0")
+        );
+
+        assert_eq!(calls[2].errno, Some(libc::ENOENT));
+        assert_eq!(
+            calls[2].to_string(),
+            format!(
+                "epoll_ctl EPOLL_CTL_DEL fd={fd} -> -1 errno=2 ({})",
+                io::Error::from_raw_os_error(libc::ENOENT)
+            )
+        );
+    }
+}